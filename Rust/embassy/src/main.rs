@@ -16,13 +16,12 @@ use {defmt_rtt as _, panic_probe as _};
 // use embassy_stm32::Peripherals;
 
 mod pb_dp_interface;
-mod profibus;
 
 //cargo build --release
 //cargo flash --chip stm32f103C8 --release
 
 use crate::pb_dp_interface::{PbDpHwInterface};
-use crate::profibus::{Codec, CodecConfig, Device, DeviceConfig}; //ProfibusConfig as PbDpConfig, /*PbDpSlave*/};
+use profibus::{Codec, CodecConfig, Device, DeviceConfig}; //ProfibusConfig as PbDpConfig, /*PbDpSlave*/};
 
 // #[embassy_executor::task()]
 // async fn profibus_slave(mut codec: Codec<PbDpHwInterface<'static>>) {
@@ -74,6 +73,9 @@ async fn main(_spawner: Spawner) {
     );
 
     let mut device_config = DeviceConfig::default();
+    //TODO: fixed address until the device stack answers Set_Slave_Address,
+    // then load it from a profibus::persistent_storage::FlashStorage like
+    // the RTIC firmware does
     device_config.fdl_config.codec_config.t_s = 0x0B;
 
     // .ident_high(0x00)
@@ -14,18 +14,20 @@
  * LICENSE file for more details.
  */
 
-use crate::profibus::{DataHandlingInterface as PbDataHandling, CodecHwInterface as PbInterface};
+use profibus::{DataHandlingInterface as PbDataHandling, CodecHwInterface as PbInterface};
 
 use embassy_stm32::peripherals::{PB0, PB1, PA7, USART3, DMA1_CH2, DMA1_CH3};
 use embassy_stm32::gpio::Output;
 use embassy_stm32::usart::Uart;
-use embassy_time::{Duration, Timer};
+use embassy_time::{Duration, Instant, Timer};
 //use async_trait::async_trait;
 
 pub struct PbDpHwInterface<'a> {
     uart : Uart<'a, USART3, DMA1_CH2, DMA1_CH3>,
     tx_en: Output<'a, PB1>,
     rx_en: Output<'a, PB0>,
+    // end of the last received telegram, for the monitor
+    rx_end: Instant,
     // buffer : [u8;255],
 }
 
@@ -39,6 +41,7 @@ impl<'a> PbDpHwInterface<'a> {
             uart,
             tx_en,
             rx_en,
+            rx_end: Instant::from_ticks(0),
             // buffer : [0;255],
         }
     }
@@ -87,15 +90,25 @@ impl<'a> PbInterface for PbDpHwInterface<'a> {
         self.uart.write(&_value).await.unwrap();
     }
 
-    async fn receive_uart_data<'out>(&'_ mut self, _value: &'out mut [u8], len: &mut usize)
+    async fn receive_uart_data(&mut self, _value: &mut [u8], len: &mut usize)
     {
         match self.uart.read_until_idle(_value).await
         {
-            Ok(size) => *len = size,
+            Ok(size) => {
+                // read_until_idle returns after one idle character
+                let idle = Duration::from_micros(11_000_000 / u64::from(self.get_baudrate()));
+                let now = Instant::now();
+                self.rx_end = now.checked_sub(idle).unwrap_or(now);
+                *len = size
+            }
             _=> *len = 0,
         }
     }
 
+    fn rx_timestamp_us(&self) -> u64 {
+        self.rx_end.as_micros()
+    }
+
     // async fn receive_uart_data<'s : 'out, 'out>(&'s mut self) -> Option<&'out [u8]>
     // {
     //     match self.uart.read_until_idle(&mut self.buffer[..]).await
@@ -1,20 +0,0 @@
-pub use codec_hw_interface::CodecHwInterface;
-pub use codec::{CodecConfig, Codec};
-pub use fdl::{Fdl, FdlConfig, FdlType};
-pub use data_handling_interface::DataHandlingInterface;
-pub use io::{/*PbDpSlave,*/ ProfibusConfig};
-pub use types::{
-    cmd_type, dpv1_status_byte1, dpv1_status_byte2, dpv1_status_byte3,
-    sap_check_config_request, sap_diagnose_byte1, sap_diagnose_byte2, sap_diagnose_byte3,
-    sap_diagnose_ext, sap_global_control, sap_set_parameter_request, DeviceState, StreamState,
-    sap_codes,FcRequestHighNibble,FcRequestLowNibble,FcResponseHighNibble,FcResponseLowNibble
-};
-pub use device::{Device, DeviceConfig};
-
-pub mod codec_hw_interface;
-pub mod data_handling_interface;
-pub mod io;
-mod codec;
-mod fdl;
-mod device;
-mod types;
@@ -0,0 +1,16 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use profibus::sim::AsyncSimHwInterface;
+use profibus::{Codec, Telegram};
+
+const SLAVE_ADDR: u8 = 0x0B;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(telegram) = Codec::<AsyncSimHwInterface>::check_telegram_format(SLAVE_ADDR, data) {
+        // everything accepted has to encode to the same bytes again
+        let mut buffer = [0; 255];
+        let len = telegram.encode(&mut buffer).unwrap();
+        assert_eq!(Telegram::decode(&buffer[..len]), Ok(telegram));
+    }
+});
@@ -0,0 +1,83 @@
+#![no_main]
+
+//! Feeds telegrams to an interrupt driven slave on a `SimBus`, so every
+//! byte takes the same way as on the target: rx interrupt, end of telegram
+//! timer and `handle_codec_data`.
+//!
+//! The input is a sequence of chunks `[flags, len, len bytes]`. Flag bit 0
+//! fixes destination, length fields, FCS and ED of the chunk, so the fuzzer
+//! gets past the framing and into the SAP handlers. Flag bit 1 sends the
+//! next chunk without SYN pause, which makes both one long telegram.
+
+use libfuzzer_sys::fuzz_target;
+use profibus::sim::{SimBus, SimHwInterface};
+use profibus::telegram::calc_checksum;
+use profibus::{cmd_type, DataHandlingInterface, PbDpSlave, ProfibusConfig};
+
+const BAUDRATE: u32 = 500_000;
+const SLAVE_ADDR: u8 = 0x0B;
+
+const REPAIR: u8 = 0x01;
+const NO_PAUSE: u8 = 0x02;
+
+struct DataHandling {}
+
+impl DataHandlingInterface for DataHandling {}
+
+type Slave = PbDpSlave<SimHwInterface, DataHandling, 64, 2, 5, 4, 3, 5>;
+
+fn repair(frame: &mut Vec<u8>) {
+    let len = frame.len();
+    match frame.first() {
+        Some(&cmd_type::SD1) if len >= 6 => {
+            frame.truncate(6);
+            frame[1] = (frame[1] & 0x80) | SLAVE_ADDR;
+            frame[4] = calc_checksum(&frame[1..4]);
+            frame[5] = cmd_type::ED;
+        }
+        Some(&cmd_type::SD2) if len >= 9 => {
+            let le = (len - 6) as u8;
+            frame[1] = le;
+            frame[2] = le;
+            frame[3] = cmd_type::SD2;
+            frame[4] = (frame[4] & 0x80) | SLAVE_ADDR;
+            frame[len - 2] = calc_checksum(&frame[4..len - 2]);
+            frame[len - 1] = cmd_type::ED;
+        }
+        Some(&cmd_type::SD3) if len >= 14 => {
+            frame.truncate(14);
+            frame[1] = (frame[1] & 0x80) | SLAVE_ADDR;
+            frame[12] = calc_checksum(&frame[1..12]);
+            frame[13] = cmd_type::ED;
+        }
+        _ => (),
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    let bus = SimBus::new(BAUDRATE);
+    let mut slave = Slave::new(
+        bus.interface(),
+        DataHandling {},
+        ProfibusConfig::default().t_s(SLAVE_ADDR),
+        [0x22, 0x20, 0x20, 0x10, 0x10],
+    );
+    // wait for SYN time
+    bus.run_for_ns(&mut slave, 100_000);
+
+    let mut data = data;
+    while let [flags, len, rest @ ..] = data {
+        let len = usize::from(*len).min(rest.len());
+        let mut frame = rest[..len].to_vec();
+        data = &rest[len..];
+        if (flags & REPAIR) != 0 {
+            repair(&mut frame);
+        }
+        bus.send(&frame);
+        if (flags & NO_PAUSE) == 0 {
+            bus.run_for_ns(&mut slave, 10_000_000);
+            bus.take_transmitted();
+        }
+    }
+    bus.run_for_ns(&mut slave, 10_000_000);
+});
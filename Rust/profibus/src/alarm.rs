@@ -0,0 +1,257 @@
+/*********************************************************************
+ * DPV1 alarms
+ *
+ * Copyright (C) 2023 Marcel Maage
+ *
+ * This library is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2.1 of the License, or (at your option) any later version.
+ *
+ * This library is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * LICENSE file for more details.
+ */
+
+//! DPV1 alarms raised by the application.
+//!
+//! A pending alarm is announced with a high priority response to
+//! Data_Exchange and reported as a block of the extended diagnostic: header,
+//! Alarm_Type, slot, Alarm_Specifier with the sequence number and the
+//! alarm data. It stays queued until the master sends Alarm_Ack on SAP 50.
+
+use crate::types::{dpv1_alarm, dpv1_status_byte2, sap_diagnose_ext};
+
+/// Longest alarm data, the block length in the header has six bits.
+pub const MAX_ALARM_DATA_LEN: usize = 59;
+// Header, Alarm_Type, Slot_Number, Alarm_Specifier
+pub const ALARM_HEADER_SIZE: usize = 4;
+pub const MAX_ALARM_BLOCK_LEN: usize = ALARM_HEADER_SIZE + MAX_ALARM_DATA_LEN;
+/// Alarms kept until they are acknowledged.
+pub const ALARM_QUEUE_SIZE: usize = 4;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum AlarmType {
+    Diagnostic,
+    Process,
+    Pull,
+    Plug,
+    Status,
+    Update,
+    /// Manufacturer specific type 32..=126
+    Manufacturer(u8),
+}
+
+impl AlarmType {
+    pub fn code(self) -> u8 {
+        match self {
+            AlarmType::Diagnostic => dpv1_alarm::DIAGNOSE,
+            AlarmType::Process => dpv1_alarm::PROZESS,
+            AlarmType::Pull => dpv1_alarm::PULL,
+            AlarmType::Plug => dpv1_alarm::PLUG,
+            AlarmType::Status => dpv1_alarm::STATUS,
+            AlarmType::Update => dpv1_alarm::UPDATE,
+            AlarmType::Manufacturer(code) => code,
+        }
+    }
+
+    pub fn from_code(code: u8) -> Option<Self> {
+        match code {
+            dpv1_alarm::DIAGNOSE => Some(AlarmType::Diagnostic),
+            dpv1_alarm::PROZESS => Some(AlarmType::Process),
+            dpv1_alarm::PULL => Some(AlarmType::Pull),
+            dpv1_alarm::PLUG => Some(AlarmType::Plug),
+            dpv1_alarm::STATUS => Some(AlarmType::Status),
+            dpv1_alarm::UPDATE => Some(AlarmType::Update),
+            dpv1_alarm::VENDOR_FIRST..=dpv1_alarm::VENDOR_LAST => {
+                Some(AlarmType::Manufacturer(code))
+            }
+            _ => None,
+        }
+    }
+
+    /// Bit of `dpv1_status_byte2` that enables the type in Set_Prm.
+    pub fn enable_bit(self) -> u8 {
+        match self {
+            AlarmType::Diagnostic => dpv1_status_byte2::DIAGNOSE_ALARM,
+            AlarmType::Process => dpv1_status_byte2::PROZESS_ALARM,
+            AlarmType::Pull | AlarmType::Plug => dpv1_status_byte2::PULL_PLUG_ALARM,
+            AlarmType::Status => dpv1_status_byte2::STATUS_ALARM,
+            AlarmType::Update => dpv1_status_byte2::UPDATE_ALARM,
+            AlarmType::Manufacturer(_) => dpv1_status_byte2::VENDOR_ALARM,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum AlarmSpecifier {
+    None = 0,
+    /// Error appears, the slot is disturbed
+    Appears = 1,
+    /// Error disappears, the slot is ok
+    Disappears = 2,
+    /// Error disappears, but the slot is still disturbed
+    DisappearsOtherError = 3,
+}
+
+impl AlarmSpecifier {
+    fn from_bits(bits: u8) -> Self {
+        match bits & dpv1_alarm::SPECIFIER {
+            1 => AlarmSpecifier::Appears,
+            2 => AlarmSpecifier::Disappears,
+            3 => AlarmSpecifier::DisappearsOtherError,
+            _ => AlarmSpecifier::None,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum AlarmError {
+    /// The slave is not in DPV1 mode or the master did not enable the
+    /// alarm type in Set_Prm.
+    NotEnabled,
+    /// Manufacturer specific type outside of 32..=126
+    InvalidType,
+    /// The data do not fit into the alarm block or the diagnostic telegram.
+    TooLong,
+    /// `ALARM_QUEUE_SIZE` alarms wait for their acknowledgement.
+    QueueFull,
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Alarm {
+    pub slot: u8,
+    pub alarm_type: AlarmType,
+    pub specifier: AlarmSpecifier,
+    /// Sequence number 0..=31
+    pub seq_nr: u8,
+    data: [u8; MAX_ALARM_DATA_LEN],
+    data_len: usize,
+}
+
+impl Alarm {
+    pub fn data(&self) -> &[u8] {
+        &self.data[..self.data_len]
+    }
+
+    /// Alarm_Specifier byte with sequence number, as in the diagnostic and
+    /// in Alarm_Ack.
+    pub fn specifier_byte(&self) -> u8 {
+        (self.seq_nr << dpv1_alarm::SEQ_NR_SHIFT) | self.specifier as u8
+    }
+
+    pub fn block_len(&self) -> usize {
+        ALARM_HEADER_SIZE + self.data_len
+    }
+
+    /// Writes the diagnostic block of the alarm into `block`, which must
+    /// hold `block_len` bytes.
+    pub fn write_block(&self, block: &mut [u8]) {
+        block[0] = sap_diagnose_ext::EXT_DIAG_GERAET + self.block_len() as u8;
+        block[1] = self.alarm_type.code();
+        block[2] = self.slot;
+        block[3] = self.specifier_byte();
+        block[ALARM_HEADER_SIZE..self.block_len()].copy_from_slice(self.data());
+    }
+
+    /// Alarm of a diagnostic block, `None` if the block is no alarm.
+    pub fn from_block(block: &[u8]) -> Option<Self> {
+        let header = *block.first()?;
+        if (header & sap_diagnose_ext::EXT_DIAG_TYPE) != sap_diagnose_ext::EXT_DIAG_GERAET {
+            return None;
+        }
+        let block = block.get(..usize::from(header & sap_diagnose_ext::EXT_DIAG_BYTE_CNT))?;
+        if block.len() < ALARM_HEADER_SIZE {
+            return None;
+        }
+        let data = &block[ALARM_HEADER_SIZE..];
+        let mut alarm = Alarm {
+            slot: block[2],
+            alarm_type: AlarmType::from_code(block[1])?,
+            specifier: AlarmSpecifier::from_bits(block[3]),
+            seq_nr: block[3] >> dpv1_alarm::SEQ_NR_SHIFT,
+            data: [0; MAX_ALARM_DATA_LEN],
+            data_len: data.len(),
+        };
+        alarm.data[..data.len()].copy_from_slice(data);
+        Some(alarm)
+    }
+}
+
+/// Alarms waiting for Alarm_Ack, oldest first.
+pub(crate) struct AlarmQueue {
+    alarms: [Option<Alarm>; ALARM_QUEUE_SIZE],
+    next_seq_nr: u8,
+}
+
+impl AlarmQueue {
+    pub(crate) fn new() -> Self {
+        Self {
+            alarms: [None; ALARM_QUEUE_SIZE],
+            next_seq_nr: 0,
+        }
+    }
+
+    /// Queues an alarm and returns its sequence number.
+    pub(crate) fn push(
+        &mut self,
+        slot: u8,
+        alarm_type: AlarmType,
+        specifier: AlarmSpecifier,
+        data: &[u8],
+    ) -> Result<u8, AlarmError> {
+        if AlarmType::from_code(alarm_type.code()) != Some(alarm_type) {
+            return Err(AlarmError::InvalidType);
+        }
+        if data.len() > MAX_ALARM_DATA_LEN {
+            return Err(AlarmError::TooLong);
+        }
+        let free = self
+            .alarms
+            .iter_mut()
+            .find(|alarm| alarm.is_none())
+            .ok_or(AlarmError::QueueFull)?;
+        let seq_nr = self.next_seq_nr;
+        self.next_seq_nr = (seq_nr + 1) % dpv1_alarm::SEQ_NR_COUNT;
+        let mut alarm = Alarm {
+            slot,
+            alarm_type,
+            specifier,
+            seq_nr,
+            data: [0; MAX_ALARM_DATA_LEN],
+            data_len: data.len(),
+        };
+        alarm.data[..data.len()].copy_from_slice(data);
+        *free = Some(alarm);
+        Ok(seq_nr)
+    }
+
+    /// Oldest alarm, the one reported in the diagnostic.
+    pub(crate) fn first(&self) -> Option<&Alarm> {
+        self.alarms[0].as_ref()
+    }
+
+    /// Removes the alarm an Alarm_Ack refers to. Returns `false` if there is
+    /// no such alarm.
+    pub(crate) fn acknowledge(&mut self, slot: u8, alarm_type: u8, specifier: u8) -> bool {
+        let seq_nr = specifier >> dpv1_alarm::SEQ_NR_SHIFT;
+        let Some(pos) = self.alarms.iter().position(|alarm| {
+            alarm.is_some_and(|alarm| {
+                alarm.slot == slot
+                    && alarm.alarm_type.code() == alarm_type
+                    && alarm.seq_nr == seq_nr
+            })
+        }) else {
+            return false;
+        };
+        // keep the order
+        self.alarms[pos..].rotate_left(1);
+        self.alarms[ALARM_QUEUE_SIZE - 1] = None;
+        true
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.alarms = [None; ALARM_QUEUE_SIZE];
+    }
+}
@@ -0,0 +1,315 @@
+/*********************************************************************
+ * Configuration identifier bytes
+ *
+ * Copyright (C) 2023 Marcel Maage
+ *
+ * This library is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2.1 of the License, or (at your option) any later version.
+ *
+ * This library is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * LICENSE file for more details.
+ */
+
+//! Decodes the identifier bytes of Chk_Cfg and Get_Cfg into modules.
+//!
+//! Compact format: one byte with direction, width, consistency and a length
+//! of up to 16 bytes or words. Special format: direction and number of
+//! vendor bytes in the identifier, followed by one length byte per
+//! direction (output first) and the vendor bytes.
+
+use crate::types::sap_check_config_request::*;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Direction {
+    /// Empty slot
+    Empty,
+    Input,
+    Output,
+    InputOutput,
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Width {
+    Byte,
+    Word,
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Consistency {
+    ByteOrWord,
+    Module,
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum CfgError {
+    /// The identifier at `offset` announces more length or vendor bytes
+    /// than follow.
+    Truncated { offset: usize },
+}
+
+/// Data of one direction of a module.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct CfgData {
+    /// Length in bytes, words already counted twice.
+    pub len: usize,
+    pub width: Width,
+    pub consistency: Consistency,
+}
+
+impl CfgData {
+    fn decode(length: u8, count_mask: u8) -> Self {
+        let width = if (length & CFG_WIDTH) == CFG_WORD {
+            Width::Word
+        } else {
+            Width::Byte
+        };
+        let consistency = if (length & CFG_KONSISTENZ) == CFG_KONS_MODUL {
+            Consistency::Module
+        } else {
+            Consistency::ByteOrWord
+        };
+        let mut len = usize::from(length & count_mask) + 1;
+        if width == Width::Word {
+            len *= 2;
+        }
+        Self {
+            len,
+            width,
+            consistency,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct CfgModule<'a> {
+    pub input: Option<CfgData>,
+    pub output: Option<CfgData>,
+    /// Manufacturer specific bytes of the special format.
+    pub vendor_data: &'a [u8],
+}
+
+impl CfgModule<'_> {
+    pub fn direction(&self) -> Direction {
+        match (self.input, self.output) {
+            (None, None) => Direction::Empty,
+            (Some(_), None) => Direction::Input,
+            (None, Some(_)) => Direction::Output,
+            (Some(_), Some(_)) => Direction::InputOutput,
+        }
+    }
+
+    pub fn input_len(&self) -> usize {
+        self.input.map_or(0, |input| input.len)
+    }
+
+    pub fn output_len(&self) -> usize {
+        self.output.map_or(0, |output| output.len)
+    }
+}
+
+/// Iterator over the modules of a config, see `decode`.
+pub struct CfgModules<'a> {
+    config: &'a [u8],
+    pos: usize,
+    /// Identifier of the module being decoded
+    offset: usize,
+}
+
+impl<'a> CfgModules<'a> {
+    fn module(&mut self, identifier: u8) -> Result<CfgModule<'a>, CfgError> {
+        if (identifier & CFG_DIRECTION) != CFG_SPECIAL {
+            let data = CfgData::decode(identifier, CFG_BYTE_CNT);
+            return Ok(CfgModule {
+                input: ((identifier & CFG_DIRECTION) != CFG_OUTPUT).then_some(data),
+                output: ((identifier & CFG_DIRECTION) != CFG_INPUT).then_some(data),
+                vendor_data: &[],
+            });
+        }
+        let direction = identifier & CFG_SP_DIRECTION;
+        let output = if (direction & CFG_SP_OUTPUT) != 0 {
+            Some(CfgData::decode(self.take(1)?[0], CFG_SP_BYTE_CNT))
+        } else {
+            None
+        };
+        let input = if (direction & CFG_SP_INPUT) != 0 {
+            Some(CfgData::decode(self.take(1)?[0], CFG_SP_BYTE_CNT))
+        } else {
+            None
+        };
+        let vendor_data = self.take(usize::from(identifier & CFG_SP_VENDOR_CNT))?;
+        Ok(CfgModule {
+            input,
+            output,
+            vendor_data,
+        })
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], CfgError> {
+        let bytes = self
+            .config
+            .get(self.pos..self.pos + len)
+            .ok_or(CfgError::Truncated {
+                offset: self.offset,
+            })?;
+        self.pos += len;
+        Ok(bytes)
+    }
+}
+
+impl<'a> Iterator for CfgModules<'a> {
+    type Item = Result<CfgModule<'a>, CfgError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let identifier = *self.config.get(self.pos)?;
+        self.offset = self.pos;
+        self.pos += 1;
+        let module = self.module(identifier);
+        if module.is_err() {
+            self.pos = self.config.len();
+        }
+        Some(module)
+    }
+}
+
+/// Modules described by the identifier bytes, in slot order. Decoding stops
+/// after the first error.
+pub fn decode(config: &[u8]) -> CfgModules<'_> {
+    CfgModules {
+        config,
+        pos: 0,
+        offset: 0,
+    }
+}
+
+/// Total input and output bytes of a config.
+pub fn io_len(config: &[u8]) -> Result<(usize, usize), CfgError> {
+    decode(config).try_fold((0, 0), |(input_len, output_len), module| {
+        let module = module?;
+        Ok((
+            input_len + module.input_len(),
+            output_len + module.output_len(),
+        ))
+    })
+}
+
+/// Module type of a modular slave, as a GSD `Module` entry.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct CatalogModule<'a> {
+    pub name: &'a str,
+    pub config: &'a [u8],
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum CatalogError {
+    Cfg(CfgError),
+    /// The identifiers at `offset` start no module of the catalog.
+    UnknownModule {
+        offset: usize,
+    },
+    TooManySlots,
+}
+
+/// Position of a slot's data in the input and output data.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub struct Slot {
+    /// Index into the catalog, or the number of the slot for a slave with a
+    /// fixed config.
+    pub module: usize,
+    pub input_offset: usize,
+    pub input_len: usize,
+    pub output_offset: usize,
+    pub output_len: usize,
+}
+
+/// Slots of a fixed config, one per module. Returns the number of slots
+/// written, modules beyond the end of `slots` are left out.
+pub fn slots(config: &[u8], slots: &mut [Slot]) -> Result<usize, CfgError> {
+    let mut input_offset = 0;
+    let mut output_offset = 0;
+    let mut count = 0;
+    for (module, slot) in decode(config).zip(slots.iter_mut()) {
+        let module = module?;
+        *slot = Slot {
+            module: count,
+            input_offset,
+            input_len: module.input_len(),
+            output_offset,
+            output_len: module.output_len(),
+        };
+        input_offset += module.input_len();
+        output_offset += module.output_len();
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Modules a modular slave accepts in any combination of up to `max_slots`.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct ModuleCatalog<'a> {
+    modules: &'a [CatalogModule<'a>],
+    max_slots: usize,
+}
+
+impl<'a> ModuleCatalog<'a> {
+    pub fn new(modules: &'a [CatalogModule<'a>]) -> Self {
+        Self {
+            modules,
+            max_slots: 1,
+        }
+    }
+
+    pub fn max_slots(mut self, max_slots: usize) -> Self {
+        self.max_slots = max_slots;
+        self
+    }
+
+    pub fn modules(&self) -> &'a [CatalogModule<'a>] {
+        self.modules
+    }
+
+    pub fn max_slot_count(&self) -> usize {
+        self.max_slots
+    }
+
+    /// Splits a Chk_Cfg into modules of the catalog and writes their slots
+    /// into `slots`. Returns the number of slots used. If several modules
+    /// match at one position, the one with the longest config wins.
+    pub fn split(&self, config: &[u8], slots: &mut [Slot]) -> Result<usize, CatalogError> {
+        let mut pos = 0;
+        let mut count = 0;
+        let mut input_offset = 0;
+        let mut output_offset = 0;
+        while pos < config.len() {
+            let (module, catalog_module) = self
+                .modules
+                .iter()
+                .enumerate()
+                .filter(|(_, module)| {
+                    !module.config.is_empty() && config[pos..].starts_with(module.config)
+                })
+                .max_by_key(|(_, module)| module.config.len())
+                .ok_or(CatalogError::UnknownModule { offset: pos })?;
+            if count >= self.max_slots || count >= slots.len() {
+                return Err(CatalogError::TooManySlots);
+            }
+            let (input_len, output_len) =
+                io_len(catalog_module.config).map_err(CatalogError::Cfg)?;
+            slots[count] = Slot {
+                module,
+                input_offset,
+                input_len,
+                output_offset,
+                output_len,
+            };
+            input_offset += input_len;
+            output_offset += output_len;
+            pos += catalog_module.config.len();
+            count += 1;
+        }
+        Ok(count)
+    }
+}
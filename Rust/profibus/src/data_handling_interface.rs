@@ -14,6 +14,9 @@
  * LICENSE file for more details.
  */
 
+use crate::persistent_storage::PersistentStorage;
+use crate::record_handling_interface::RecordHandlingInterface;
+
 pub trait DataHandlingInterface{ 
 
     fn config_error_led(&mut self) {}
@@ -29,4 +32,25 @@ pub trait DataHandlingInterface{
     fn data_processing(&self, _input: &mut [u8], _output: &[u8]) {}
 
     fn debug_write(&mut self, _debug: &str) {}
+
+    /// Every telegram the slave received, before it is checked. Together
+    /// with `telegram_sent` this allows to record the traffic of the slave,
+    /// e.g. as pcap.
+    fn telegram_received(&mut self, _data: &[u8]) {}
+
+    /// Every telegram the slave sends, repetitions included.
+    fn telegram_sent(&mut self, _data: &[u8]) {}
+
+    /// Data records for DPV1 Read/Write. Without, the DPV1 SAPs are not
+    /// activated.
+    fn record_handling(&mut self) -> Option<&mut dyn RecordHandlingInterface> {
+        None
+    }
+
+    /// Storage for values set by the master, e.g. the station address,
+    /// written by `PbDpSlave::store_pending`. Without, they are lost on
+    /// reset.
+    fn persistent_storage(&mut self) -> Option<&mut dyn PersistentStorage> {
+        None
+    }
 }
\ No newline at end of file
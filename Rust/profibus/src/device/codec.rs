@@ -0,0 +1,401 @@
+/*********************************************************************
+ * Profibus Codec
+ *
+ * Copyright (C) 2023 Marcel Maage
+ *
+ * This library is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2.1 of the License, or (at your option) any later version.
+ *
+ * This library is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * LICENSE file for more details.
+ */
+
+use super::codec_hw_interface::CodecHwInterface;
+use crate::telegram::{frame_len, FrameError, Telegram};
+use crate::types::{fc_request_high_nibble, StreamState};
+
+pub struct CodecConfig {
+    pub t_s: u8,
+    pub t_sl: u16,
+    pub t_sdr_min: u16,
+    pub monitor: bool,
+}
+
+impl CodecConfig {
+    #[allow(dead_code)]
+    pub fn t_s(mut self, t_s: u8) -> Self {
+        self.t_s = t_s;
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn t_sl(mut self, t_sl: u16) -> Self {
+        self.t_sl = t_sl;
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn t_sdr_min(mut self, t_sdr_min: u16) -> Self {
+        self.t_sdr_min = t_sdr_min;
+        self
+    }
+
+    /// Passive bus monitor: the codec never transmits and receives the
+    /// telegrams to every station.
+    #[allow(dead_code)]
+    pub fn monitor(mut self, monitor: bool) -> Self {
+        self.monitor = monitor;
+        self
+    }
+}
+
+impl Default for CodecConfig {
+    fn default() -> CodecConfig {
+        CodecConfig {
+            t_s: 126,
+            t_sl: 65000,
+            t_sdr_min: 20,
+            monitor: false,
+        }
+    }
+}
+
+pub struct Connection<'pdu> {
+    pub source_addr: u8,
+    pub destination_addr: u8,
+    pub function_code: u8,
+    pub sap: Option<(u8, u8)>, //(dsap, ssap)
+    pub pdu: &'pdu [u8],
+}
+
+impl<'pdu> Connection<'pdu> {
+    pub fn new(
+        source_addr: u8,
+        destination_addr: u8,
+        function_code: u8,
+        sap: Option<(u8, u8)>, //(dsap, ssap)
+        pdu: &'pdu [u8],
+    ) -> Self {
+        Self {
+            source_addr,
+            destination_addr,
+            function_code,
+            sap,
+            pdu,
+        }
+    }
+}
+
+/// Telegram seen on the bus by `Codec::monitor`.
+pub struct MonitorFrame<'a> {
+    /// Start of the telegram in us, in the time base of the hardware.
+    pub timestamp_us: u64,
+    pub data: &'a [u8],
+    pub telegram: Result<Telegram<'a>, FrameError>,
+}
+
+impl MonitorFrame<'_> {
+    pub fn fcs_ok(&self) -> bool {
+        !matches!(self.telegram, Err(FrameError::ChecksumMismatch { .. }))
+    }
+}
+
+const TX_SIZE: usize = 255;
+
+#[allow(dead_code)]
+pub struct Codec<SerialInterface> {
+    config: CodecConfig,
+    hw_interface: SerialInterface,
+
+    tx_buffer: [u8; TX_SIZE],
+    tx_len: usize,
+
+    timeout_max_syn_time_in_us: u32,
+    timeout_max_rx_time_in_us: u32,
+    timeout_max_tx_time_in_us: u32,
+    timeout_max_sdr_time_in_us: u32,
+    timer_timeout_in_us: u32,
+
+    stream_state: StreamState,
+
+    source_addr: u8,
+    fcv_activated: bool,
+    fcb_last: bool,
+}
+
+const DEFAULT_ADD: u8 = 126;
+
+impl<SerialInterface> Codec<SerialInterface>
+where
+    SerialInterface: CodecHwInterface,
+{
+    pub fn new(mut hw_interface: SerialInterface, mut config: CodecConfig) -> Self {
+        let baudrate = hw_interface.get_baudrate();
+
+        let timeout_max_syn_time_in_us = 33_000_000u32 / baudrate; // 33 TBit = TSYN
+        let timeout_max_rx_time_in_us = 15_000_000u32 / baudrate;
+        let timeout_max_tx_time_in_us = 15_000_000u32 / baudrate;
+        let timeout_max_sdr_time_in_us = 15_000_000u32 / baudrate; // 15 Tbit = TSDR
+
+        let timer_timeout_in_us = timeout_max_syn_time_in_us;
+
+        if (0 == config.t_s) || (config.t_s > DEFAULT_ADD) {
+            config.t_s = DEFAULT_ADD;
+        }
+
+        let stream_state = StreamState::WaitSyn;
+
+        // Timer init
+        hw_interface.config_timer();
+        // Pin Init
+        hw_interface.config_rs485_pin();
+
+        // Uart Init
+        hw_interface.config_uart();
+        hw_interface.rx_rs485_enable();
+
+        #[cfg(feature = "defmt")]
+        defmt::info!("codec start");
+        Self {
+            config,
+            hw_interface,
+            tx_buffer: [0; TX_SIZE],
+            tx_len: 0,
+            timeout_max_syn_time_in_us,
+            timeout_max_rx_time_in_us,
+            timeout_max_tx_time_in_us,
+            timeout_max_sdr_time_in_us,
+            timer_timeout_in_us,
+
+            stream_state,
+
+            source_addr: 0xFF,
+            fcv_activated: false,
+            fcb_last: false,
+        }
+    }
+
+    async fn reset_data_stream(&mut self) {
+        self.stream_state = StreamState::WaitSyn;
+        self.hw_interface
+            .wait_for(self.timeout_max_syn_time_in_us)
+            .await;
+        self.hw_interface.rx_rs485_enable();
+    }
+
+    pub async fn receive<'buf: 'out, 'out>(
+        &'_ mut self,
+        buffer: &'buf mut [u8],
+    ) -> Option<Connection<'out>> {
+        self.hw_interface.rx_rs485_disable();
+        // wait for syn time
+        self.stream_state = StreamState::WaitSyn;
+        self.hw_interface
+            .wait_for(self.timeout_max_syn_time_in_us)
+            .await;
+        self.hw_interface.rx_rs485_enable();
+
+        let rx_len = loop {
+            if let Some(rx_len) = self.receive_and_check(buffer).await {
+                break rx_len;
+            };
+            // reset data stream in case that
+            self.reset_data_stream().await;
+        };
+        self.hw_interface.rx_rs485_disable();
+        self.stream_state = StreamState::HandleData;
+        // already checked in receive_and_check
+        let telegram = Telegram::decode(&buffer[..rx_len]).ok()?;
+        let (dsap, ssap) = telegram.sap();
+        Some(Connection::new(
+            telegram.source_addr()?,
+            telegram.destination_addr()?,
+            telegram.function_code()?,
+            dsap.zip(ssap),
+            telegram.pdu(),
+        ))
+    }
+
+    /// Receives one telegram and returns its length if it is a request to
+    /// this station which was not answered before.
+    async fn receive_and_check(&mut self, buffer: &mut [u8]) -> Option<usize> {
+        let mut rx_len = 0;
+        self.stream_state = StreamState::WaitData;
+        self.hw_interface
+            .receive_uart_data(buffer, &mut rx_len)
+            .await;
+        if self.config.monitor {
+            return Telegram::decode(&buffer[0..rx_len]).ok().map(|_| rx_len);
+        }
+        let telegram = match Self::check_telegram_format(self.config.t_s, &buffer[0..rx_len]) {
+            Ok(telegram) => telegram,
+            Err(_error) => {
+                #[cfg(feature = "defmt")]
+                defmt::debug!("telegram dropped: {}", defmt::Debug2Format(&_error));
+                return None;
+            }
+        };
+        // token and short acknowledge are no requests
+        let (Some(source_addr), Some(function_code)) =
+            (telegram.source_addr(), telegram.function_code())
+        else {
+            return None;
+        };
+
+        if (function_code & 0x30) == fc_request_high_nibble::FCB
+        // fcb start
+        {
+            self.fcv_activated = true;
+            self.fcb_last = true;
+        } else if (function_code & fc_request_high_nibble::FCV) != 0 && self.fcv_activated {
+            if source_addr != self.source_addr {
+                // new address so fcv is deactivated
+                self.fcv_activated = false;
+            } else if ((function_code & fc_request_high_nibble::FCB) != 0) == self.fcb_last {
+                // FCB is identical, repeat message
+                self.transmit().await;
+                return None;
+            } else {
+                // save new FCB bit
+                self.fcb_last = !self.fcb_last;
+            }
+        }
+
+        // save last address
+        self.source_addr = source_addr;
+
+        Some(rx_len)
+    }
+
+    // todo!(give SAP to next higher layer)
+    // todo!(function code analysis => SDN/SDR inclusive FCB/FCV)
+    // todo!(an den FDL layer werden dann die einzelnen PDUs mit SAP übergeben bzw. die zyklischen Daten)
+    // todo!(Problem: ich muss das letzte gesendete Telegram hier speichern, was bedeutet, dass ich eine Speicher brauche
+    // und damit wieder das Referenzproblem auftritt)
+    // todo!(Die FDL hat einzelne SAP Objekte und das Objekt, welches einfach nur für den zyklischen Datenaustausch da ist)
+    // todo!(Beachte die tabelle bezüglch des Löschens des FCB)
+
+    pub fn check_telegram_format(t_s: u8, buffer: &[u8]) -> Result<Telegram<'_>, FrameError> {
+        Telegram::decode_for(buffer, t_s)
+    }
+
+    /// Receives everything on the bus and hands each telegram to `callback`,
+    /// faulty ones included. Request and answer which arrive without pause
+    /// are split by the length of the telegram. Never returns.
+    pub async fn monitor<F>(&mut self, buffer: &mut [u8], mut callback: F)
+    where
+        F: FnMut(MonitorFrame<'_>),
+    {
+        self.hw_interface.rx_rs485_enable();
+        loop {
+            let mut rx_len = 0;
+            self.stream_state = StreamState::WaitData;
+            self.hw_interface
+                .receive_uart_data(buffer, &mut rx_len)
+                .await;
+            let rx_end_us = self.hw_interface.rx_timestamp_us();
+            let baudrate = u64::from(self.hw_interface.get_baudrate().max(1));
+            let mut pos = 0;
+            while pos < rx_len {
+                let len = frame_len(&buffer[pos..rx_len])
+                    .unwrap_or(rx_len)
+                    .clamp(1, rx_len - pos);
+                let data = &buffer[pos..pos + len];
+                // 11 bit per character
+                let timestamp_us =
+                    rx_end_us.saturating_sub(((rx_len - pos) as u64 * 11_000_000) / baudrate);
+                callback(MonitorFrame {
+                    timestamp_us,
+                    data,
+                    telegram: Telegram::decode(data),
+                });
+                pos += len;
+            }
+        }
+    }
+
+    #[allow(dead_code)]
+    pub async fn transmit_message_sd1<'a>(&mut self, connection: Connection<'a>) {
+        self.transmit_message(&Telegram::Sd1 {
+            destination_addr: connection.destination_addr,
+            source_addr: self.config.t_s,
+            function_code: connection.function_code,
+        })
+        .await;
+    }
+
+    #[allow(dead_code)]
+    pub async fn transmit_message_sd2<'a>(&mut self, connection: Connection<'a>) {
+        self.transmit_message(&Telegram::Sd2 {
+            destination_addr: connection.destination_addr,
+            source_addr: self.config.t_s,
+            function_code: connection.function_code,
+            dsap: connection.sap.map(|sap| sap.0),
+            ssap: connection.sap.map(|sap| sap.1),
+            pdu: connection.pdu,
+        })
+        .await;
+    }
+
+    #[allow(dead_code)]
+    pub async fn transmit_message_sd3<'a>(&mut self, connection: Connection<'a>) {
+        self.transmit_message(&Telegram::Sd3 {
+            destination_addr: connection.destination_addr,
+            source_addr: self.config.t_s,
+            function_code: connection.function_code,
+            dsap: connection.sap.map(|sap| sap.0),
+            ssap: connection.sap.map(|sap| sap.1),
+            pdu: connection.pdu,
+        })
+        .await;
+    }
+
+    #[allow(dead_code)]
+    pub async fn transmit_message_sd4<'a>(&mut self, connection: Connection<'a>) {
+        self.transmit_message(&Telegram::Sd4 {
+            destination_addr: connection.destination_addr,
+            source_addr: self.config.t_s,
+        })
+        .await;
+    }
+
+    #[allow(dead_code)]
+    pub async fn transmit_message_sc(&mut self) {
+        self.transmit_message(&Telegram::Sc).await;
+    }
+
+    async fn transmit_message(&mut self, telegram: &Telegram<'_>) {
+        match telegram.encode(&mut self.tx_buffer) {
+            Ok(tx_len) => {
+                self.tx_len = tx_len;
+                self.transmit().await;
+            }
+            Err(_error) => {
+                #[cfg(feature = "defmt")]
+                defmt::error!("telegram not sent: {}", defmt::Debug2Format(&_error));
+            }
+        }
+    }
+
+    pub async fn transmit(&mut self) {
+        if self.config.monitor {
+            return;
+        }
+        if 0 != self.config.t_sdr_min {
+            self.stream_state = StreamState::WaitMinTsdr;
+            let baudrate = self.hw_interface.get_baudrate();
+            self.timer_timeout_in_us = (1_000_000u32 * u32::from(self.config.t_sdr_min)) / baudrate;
+            self.hw_interface.wait_for(self.timer_timeout_in_us).await;
+        }
+
+        self.stream_state = StreamState::SendData;
+        self.hw_interface.wait_for_activ_transmission().await;
+        self.hw_interface.tx_rs485_enable();
+        let buffer = &self.tx_buffer[0..self.tx_len];
+        self.hw_interface.send_uart_data(buffer).await;
+    }
+}
@@ -13,11 +13,11 @@
  * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
  * LICENSE file for more details.
  */
-#![allow(incomplete_features)]
 
 //use async_trait::async_trait;
 
 //#[async_trait]
+#[allow(async_fn_in_trait)]
 pub trait CodecHwInterface {
     fn config_timer(&mut self) {}
 
@@ -39,7 +39,13 @@ pub trait CodecHwInterface {
 
     async fn send_uart_data(&mut self, _value: &[u8]) {}
 
-    async fn receive_uart_data<'out>(&'_ mut self, _value: &'out mut [u8], _len: &mut usize) {}
+    async fn receive_uart_data(&mut self, _value: &mut [u8], _len: &mut usize) {}
+
+    /// Time in us at which the last byte of the last received telegram
+    /// arrived, only needed for the monitor of the codec.
+    fn rx_timestamp_us(&self) -> u64 {
+        0
+    }
     // async fn receive_uart_data<'s : 'out, 'out>(&'s mut self) -> Option<&'out [u8]> {None}
 
     fn get_baudrate(&self) -> u32 {
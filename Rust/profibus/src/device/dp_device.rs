@@ -14,14 +14,10 @@
  * LICENSE file for more details.
  */
 
-use defmt::*;
 
 use super::codec_hw_interface::CodecHwInterface;
 use super::fdl::{Fdl, FdlConfig, FdlType, Service};
-use super::types::{
-    sap_codes, sap_diagnose_byte1, sap_diagnose_byte2, sap_diagnose_byte3, sap_diagnose_ext,
-    sap_global_control, sap_set_parameter_request, DeviceState,
-};
+use crate::types::{sap_diagnose_byte1, DpSlaveState};
 
 const RX_SIZE: usize = 255;
 
@@ -30,6 +26,7 @@ pub struct DeviceConfig {
     pub fdl_config: FdlConfig,
 }
 
+#[allow(dead_code)]
 pub struct Device<
     CodecInterface,
     const BUF_SIZE: usize,
@@ -41,7 +38,7 @@ pub struct Device<
 > {
     fdl: Fdl<CodecInterface>,
     //  pub tx_buffer: [u8; BUF_SIZE],
-    device_state: DeviceState,
+    device_state: DpSlaveState,
 
     input_data: [u8; INPUT_DATA_SIZE],
     input_data_buffer: [u8; INPUT_DATA_SIZE],
@@ -103,7 +100,7 @@ where
         Self {
             // tx_buffer: [0; BUF_SIZE],
             fdl,
-            device_state: DeviceState::Por,
+            device_state: DpSlaveState::Por,
             input_data,
             input_data_buffer,
             output_data,
@@ -126,12 +123,9 @@ where
     pub async fn run(&mut self) -> bool {
         let mut buffer: [u8; RX_SIZE] = [0; RX_SIZE];
 
-        match self.fdl.run(&mut buffer[..]).await {
-            Some(service) => {
-                // service.connection.
-                self.handle_message(service);
-            }
-            None => (),
+        if let Some(service) = self.fdl.run(&mut buffer[..]).await {
+            // service.connection.
+            self.handle_message(service);
         }
         true
     }
@@ -141,7 +135,7 @@ where
 
     fn handle_message(&mut self, service: Service) {
         match service.connection.sap {
-            Some((dsap, ssap)) => {
+            Some((dsap, _ssap)) => {
                 // SAP detected
                 match dsap {
                     54 => self.handle_sap_54(),
@@ -14,12 +14,11 @@
  * LICENSE file for more details.
  */
 
-use defmt::*;
 
 use super::codec::{Codec, CodecConfig, Connection};
 use super::codec_hw_interface::CodecHwInterface;
 
-use super::types::FcRequestLowNibble;
+use crate::types::fc_request_low_nibble;
 
 #[derive(Default)]
 pub struct FdlConfig {
@@ -79,7 +78,8 @@ where
     CodecInterface: CodecHwInterface,
 {
     pub fn new(codec_interface: CodecInterface, config: FdlConfig, fdl_type: FdlType) -> Self {
-        info!("codec start");
+        #[cfg(feature = "defmt")]
+        defmt::info!("codec start");
         let codec = Codec::<CodecInterface>::new(codec_interface, config.codec_config);
         Self {
             codec,
@@ -95,13 +95,13 @@ where
         match self.codec.receive(buffer).await {
             Some(conn) => {
                 let service: FdlService = match conn.function_code & 0x0F {
-                    FcRequestLowNibble::SDA_LOW => FdlService::SdaLow,
-                    FcRequestLowNibble::SDN_LOW => FdlService::SdnLow,
-                    FcRequestLowNibble::SRD_LOW => FdlService::SrdLow,
-                    FcRequestLowNibble::SDA_HIGH => FdlService::SdaHigh,
-                    FcRequestLowNibble::SDN_HIGH => FdlService::SdnHigh,
-                    FcRequestLowNibble::SRD_HIGH => FdlService::SrdHigh,
-                    FcRequestLowNibble::MSRD => FdlService::Msrd,
+                    fc_request_low_nibble::SDA_LOW => FdlService::SdaLow,
+                    fc_request_low_nibble::SDN_LOW => FdlService::SdnLow,
+                    fc_request_low_nibble::SRD_LOW => FdlService::SrdLow,
+                    fc_request_low_nibble::SDA_HIGH => FdlService::SdaHigh,
+                    fc_request_low_nibble::SDN_HIGH => FdlService::SdnHigh,
+                    fc_request_low_nibble::SRD_HIGH => FdlService::SrdHigh,
+                    fc_request_low_nibble::MSRD => FdlService::Msrd,
                     _ => FdlService::None,
                 };
                 Some(Service::new(conn, service))
@@ -0,0 +1,9 @@
+pub use codec::{Codec, CodecConfig, Connection, MonitorFrame};
+pub use codec_hw_interface::CodecHwInterface;
+pub use dp_device::{Device, DeviceConfig};
+pub use fdl::{Fdl, FdlConfig, FdlService, FdlType, Service};
+
+pub mod codec_hw_interface;
+mod codec;
+mod dp_device;
+mod fdl;
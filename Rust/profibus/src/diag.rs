@@ -0,0 +1,287 @@
+/*********************************************************************
+ * Extended diagnostic
+ *
+ * Copyright (C) 2023 Marcel Maage
+ *
+ * This library is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2.1 of the License, or (at your option) any later version.
+ *
+ * This library is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * LICENSE file for more details.
+ */
+
+//! Builds the extended diagnostic following the six standard bytes.
+//!
+//! Device and identifier related blocks start with a header holding the
+//! block type and the block length including the header. A channel related
+//! entry is three bytes: slot, direction and channel number, channel type
+//! and error type. Channel faults raised with `PbDpSlave::channel_fault` are
+//! kept in a table and appended as channel related entries.
+
+use crate::types::sap_diagnose_ext::*;
+
+/// Longest extended diagnostic, Max_Diag_Data_Len is at most 244.
+pub const MAX_EXT_DIAG_LEN: usize = 244 - 6;
+// Header, channel, type
+const CHANNEL_DIAG_SIZE: usize = 3;
+/// Channel faults the slave keeps at the same time.
+pub const MAX_CHANNEL_FAULTS: usize = 16;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum ChannelDirection {
+    Input,
+    Output,
+    InputOutput,
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum ChannelType {
+    Bit = 1,
+    Bit2 = 2,
+    Bit4 = 3,
+    Byte = 4,
+    Word = 5,
+    Word2 = 6,
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum ChannelError {
+    ShortCircuit,
+    Undervoltage,
+    Overvoltage,
+    Overload,
+    Overtemperature,
+    /// Wire break
+    LineBreak,
+    /// Over range
+    UpperLimit,
+    /// Under range
+    LowerLimit,
+    Error,
+    /// Manufacturer specific error type 16..=31, see `manufacturer`
+    Manufacturer(u8),
+}
+
+impl ChannelError {
+    /// Manufacturer specific error type, `None` outside of 16..=31.
+    pub fn manufacturer(code: u8) -> Option<Self> {
+        match code {
+            16..=31 => Some(ChannelError::Manufacturer(code)),
+            _ => None,
+        }
+    }
+
+    /// Error type of the channel diagnostic. A manufacturer specific type
+    /// outside of 16..=31 is sent as `Error`, not as a standard type.
+    pub fn code(self) -> u8 {
+        match self {
+            ChannelError::ShortCircuit => 1,
+            ChannelError::Undervoltage => 2,
+            ChannelError::Overvoltage => 3,
+            ChannelError::Overload => 4,
+            ChannelError::Overtemperature => 5,
+            ChannelError::LineBreak => 6,
+            ChannelError::UpperLimit => 7,
+            ChannelError::LowerLimit => 8,
+            ChannelError::Error => 9,
+            ChannelError::Manufacturer(code @ 16..=31) => code,
+            ChannelError::Manufacturer(_) => ChannelError::Error.code(),
+        }
+    }
+}
+
+/// Diagnostic of one channel of a module.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct ChannelDiag {
+    /// Slot of the module, 0..=63
+    pub slot: u8,
+    /// Channel in the module, 0..=63
+    pub channel: u8,
+    pub direction: ChannelDirection,
+    pub channel_type: ChannelType,
+    pub error: ChannelError,
+}
+
+impl ChannelDiag {
+    /// Same slot, channel and direction.
+    fn is_same_channel(&self, other: &ChannelDiag) -> bool {
+        self.slot == other.slot
+            && self.channel == other.channel
+            && self.direction == other.direction
+    }
+
+    pub fn encode(&self) -> [u8; CHANNEL_DIAG_SIZE] {
+        let direction = match self.direction {
+            ChannelDirection::Input => 0x40,
+            ChannelDirection::Output => 0x80,
+            ChannelDirection::InputOutput => 0xC0,
+        };
+        [
+            EXT_DIAG_KANAL | (self.slot & EXT_DIAG_BYTE_CNT),
+            direction | (self.channel & 0x3F),
+            ((self.channel_type as u8) << 5) | self.error.code(),
+        ]
+    }
+}
+
+/// Extended diagnostic of a slave, handed to `PbDpSlave::set_ext_diag`.
+///
+/// Blocks that do not fit are left out and mark the diagnostic as
+/// overflowed.
+#[derive(Clone, Copy)]
+pub struct ExtDiag {
+    data: [u8; MAX_EXT_DIAG_LEN],
+    len: usize,
+    overflow: bool,
+}
+
+impl Default for ExtDiag {
+    fn default() -> Self {
+        Self {
+            data: [0; MAX_EXT_DIAG_LEN],
+            len: 0,
+            overflow: false,
+        }
+    }
+}
+
+impl ExtDiag {
+    /// Device related block with up to 62 manufacturer specific bytes.
+    pub fn device(mut self, data: &[u8]) -> Self {
+        self.push_block(EXT_DIAG_GERAET, data);
+        self
+    }
+
+    /// Identifier related block marking the modules in `slots` as faulty.
+    pub fn identifier(mut self, slots: &[u8]) -> Self {
+        let mut bitmap = [0u8; EXT_DIAG_BYTE_CNT as usize - 1];
+        let mut bitmap_len = 0;
+        for &slot in slots {
+            let byte = usize::from(slot / 8);
+            if byte >= bitmap.len() {
+                self.overflow = true;
+                continue;
+            }
+            bitmap[byte] |= 1 << (slot % 8);
+            bitmap_len = bitmap_len.max(byte + 1);
+        }
+        if bitmap_len > 0 {
+            self.push_block(EXT_DIAG_KENNUNG, &bitmap[..bitmap_len]);
+        }
+        self
+    }
+
+    pub fn channel(mut self, channel: ChannelDiag) -> Self {
+        if self.len + CHANNEL_DIAG_SIZE > MAX_EXT_DIAG_LEN {
+            self.overflow = true;
+        } else {
+            self.data[self.len..self.len + CHANNEL_DIAG_SIZE].copy_from_slice(&channel.encode());
+            self.len += CHANNEL_DIAG_SIZE;
+        }
+        self
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data[..self.len]
+    }
+
+    pub fn byte_len(&self) -> usize {
+        self.len
+    }
+
+    /// Some blocks did not fit.
+    pub fn is_overflow(&self) -> bool {
+        self.overflow
+    }
+
+    /// Length of the whole blocks within the first `max_len` bytes.
+    pub fn fitting_len(&self, max_len: usize) -> usize {
+        let mut pos = 0;
+        while pos < self.len {
+            let header = self.data[pos];
+            let block_len = if (header & EXT_DIAG_TYPE) == EXT_DIAG_KANAL {
+                CHANNEL_DIAG_SIZE
+            } else {
+                usize::from(header & EXT_DIAG_BYTE_CNT)
+            };
+            if pos + block_len > max_len {
+                break;
+            }
+            pos += block_len;
+        }
+        pos
+    }
+
+    fn push_block(&mut self, block_type: u8, data: &[u8]) {
+        let block_len = data.len() + 1;
+        if block_len > usize::from(EXT_DIAG_BYTE_CNT) || self.len + block_len > MAX_EXT_DIAG_LEN {
+            self.overflow = true;
+            return;
+        }
+        self.data[self.len] = block_type | block_len as u8;
+        self.data[self.len + 1..self.len + block_len].copy_from_slice(data);
+        self.len += block_len;
+    }
+}
+
+/// Channels with a fault, in the order they appeared.
+pub(crate) struct ChannelFaults {
+    faults: [Option<ChannelDiag>; MAX_CHANNEL_FAULTS],
+}
+
+impl ChannelFaults {
+    pub(crate) fn new() -> Self {
+        Self {
+            faults: [None; MAX_CHANNEL_FAULTS],
+        }
+    }
+
+    /// Enters the fault of a channel, replacing an older fault of the same
+    /// channel. Returns whether the table changed, `None` if it is full.
+    pub(crate) fn set(&mut self, fault: ChannelDiag) -> Option<bool> {
+        if let Some(entry) = self
+            .faults
+            .iter_mut()
+            .flatten()
+            .find(|entry| entry.is_same_channel(&fault))
+        {
+            let changed = *entry != fault;
+            *entry = fault;
+            return Some(changed);
+        }
+        let free = self.faults.iter_mut().find(|entry| entry.is_none())?;
+        *free = Some(fault);
+        Some(true)
+    }
+
+    /// Removes the fault of a channel and returns it.
+    pub(crate) fn clear(
+        &mut self,
+        slot: u8,
+        channel: u8,
+        direction: ChannelDirection,
+    ) -> Option<ChannelDiag> {
+        let pos = self.faults.iter().position(|entry| {
+            entry.is_some_and(|entry| {
+                entry.slot == slot && entry.channel == channel && entry.direction == direction
+            })
+        })?;
+        let fault = self.faults[pos];
+        // keep the order
+        self.faults[pos..].rotate_left(1);
+        self.faults[MAX_CHANNEL_FAULTS - 1] = None;
+        fault
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &ChannelDiag> {
+        self.faults.iter().flatten()
+    }
+
+    pub(crate) fn has_slot(&self, slot: u8) -> bool {
+        self.iter().any(|fault| fault.slot == slot)
+    }
+}
@@ -0,0 +1,517 @@
+/*********************************************************************
+ * GSD generator
+ *
+ * Copyright (C) 2023 Marcel Maage
+ *
+ * This library is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2.1 of the License, or (at your option) any later version.
+ *
+ * This library is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * LICENSE file for more details.
+ */
+
+//! Generates the GSD file (revision 5) of a compact slave, so the
+//! engineering tool of the master gets the same ident number, module config
+//! and parameter lengths the firmware uses. Only needs `core::fmt::Write`,
+//! so the firmware can print its own GSD, too.
+
+use core::fmt::{self, Write};
+
+use crate::alarm::MAX_ALARM_BLOCK_LEN;
+use crate::cfg::{self, CatalogModule, CfgError, ModuleCatalog};
+use crate::record_handling_interface::MAX_RECORD_LEN;
+use crate::types::dpv1_status_byte2;
+
+/// Longest string the GSD allows for names and releases.
+pub const MAX_NAME_LEN: usize = 32;
+pub const MAX_USER_PRM_DATA_LEN: usize = 237;
+pub const MAX_DIAG_DATA_LEN: usize = 244;
+pub const MAX_CFG_DATA_LEN: usize = 244;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Baudrate {
+    B9k6,
+    B19k2,
+    B45k45,
+    B93k75,
+    B187k5,
+    B500k,
+    B1M5,
+    B3M,
+    B6M,
+    B12M,
+}
+
+impl Baudrate {
+    pub const ALL: [Baudrate; 10] = [
+        Baudrate::B9k6,
+        Baudrate::B19k2,
+        Baudrate::B45k45,
+        Baudrate::B93k75,
+        Baudrate::B187k5,
+        Baudrate::B500k,
+        Baudrate::B1M5,
+        Baudrate::B3M,
+        Baudrate::B6M,
+        Baudrate::B12M,
+    ];
+
+    /// Name of the baudrate in the GSD keywords, e.g. `1.5M` in `1.5M_supp`.
+    pub fn keyword(self) -> &'static str {
+        match self {
+            Baudrate::B9k6 => "9.6",
+            Baudrate::B19k2 => "19.2",
+            Baudrate::B45k45 => "45.45",
+            Baudrate::B93k75 => "93.75",
+            Baudrate::B187k5 => "187.5",
+            Baudrate::B500k => "500",
+            Baudrate::B1M5 => "1.5M",
+            Baudrate::B3M => "3M",
+            Baudrate::B6M => "6M",
+            Baudrate::B12M => "12M",
+        }
+    }
+
+    /// Inverse of `keyword`, ignoring the case.
+    pub fn from_keyword(keyword: &str) -> Option<Self> {
+        Baudrate::ALL
+            .into_iter()
+            .find(|baudrate| baudrate.keyword().eq_ignore_ascii_case(keyword))
+    }
+
+    pub fn bits_per_second(self) -> u32 {
+        match self {
+            Baudrate::B9k6 => 9_600,
+            Baudrate::B19k2 => 19_200,
+            Baudrate::B45k45 => 45_450,
+            Baudrate::B93k75 => 93_750,
+            Baudrate::B187k5 => 187_500,
+            Baudrate::B500k => 500_000,
+            Baudrate::B1M5 => 1_500_000,
+            Baudrate::B3M => 3_000_000,
+            Baudrate::B6M => 6_000_000,
+            Baudrate::B12M => 12_000_000,
+        }
+    }
+
+    /// Max TSDR in bit times the standard allows for a slave.
+    pub fn max_tsdr(self) -> u16 {
+        match self {
+            Baudrate::B9k6 | Baudrate::B19k2 | Baudrate::B93k75 | Baudrate::B187k5 => 60,
+            Baudrate::B45k45 => 250,
+            Baudrate::B500k => 100,
+            Baudrate::B1M5 => 150,
+            Baudrate::B3M => 250,
+            Baudrate::B6M => 450,
+            Baudrate::B12M => 800,
+        }
+    }
+
+    fn mask(self) -> u16 {
+        1 << (self as u16)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GsdError {
+    Format,
+    /// The value of this keyword exceeds what the GSD allows.
+    TooLong(&'static str),
+    /// The value of this keyword is empty or contains a character the GSD
+    /// can not quote.
+    InvalidValue(&'static str),
+    /// The module config can not be decoded.
+    ModuleConfig(CfgError),
+}
+
+impl From<fmt::Error> for GsdError {
+    fn from(_: fmt::Error) -> Self {
+        GsdError::Format
+    }
+}
+
+pub struct GsdConfig<'a> {
+    vendor_name: &'a str,
+    model_name: &'a str,
+    revision: &'a str,
+    hardware_release: &'a str,
+    software_release: &'a str,
+    ident_number: u16,
+    baudrates: u16,
+    freeze: bool,
+    sync: bool,
+    auto_baud: bool,
+    set_slave_add: bool,
+    min_slave_interval: u16,
+    module_name: &'a str,
+    module_config: &'a [u8],
+    modules: &'a [CatalogModule<'a>],
+    max_module: usize,
+    max_io_len: Option<(usize, usize)>,
+    user_prm_data_len: usize,
+    ext_diag_len: usize,
+    dpv1: bool,
+    max_record_len: usize,
+    alarms: u8,
+}
+
+impl<'a> GsdConfig<'a> {
+    pub fn vendor_name(mut self, vendor_name: &'a str) -> Self {
+        self.vendor_name = vendor_name;
+        self
+    }
+
+    pub fn model_name(mut self, model_name: &'a str) -> Self {
+        self.model_name = model_name;
+        self
+    }
+
+    pub fn revision(mut self, revision: &'a str) -> Self {
+        self.revision = revision;
+        self
+    }
+
+    pub fn hardware_release(mut self, hardware_release: &'a str) -> Self {
+        self.hardware_release = hardware_release;
+        self
+    }
+
+    pub fn software_release(mut self, software_release: &'a str) -> Self {
+        self.software_release = software_release;
+        self
+    }
+
+    pub fn ident_number(mut self, ident_number: u16) -> Self {
+        self.ident_number = ident_number;
+        self
+    }
+
+    /// Adds a supported baudrate. Without any, all baudrates are supported.
+    pub fn baudrate(mut self, baudrate: Baudrate) -> Self {
+        self.baudrates |= baudrate.mask();
+        self
+    }
+
+    pub fn freeze(mut self, freeze: bool) -> Self {
+        self.freeze = freeze;
+        self
+    }
+
+    pub fn sync(mut self, sync: bool) -> Self {
+        self.sync = sync;
+        self
+    }
+
+    pub fn auto_baud(mut self, auto_baud: bool) -> Self {
+        self.auto_baud = auto_baud;
+        self
+    }
+
+    pub fn set_slave_add(mut self, set_slave_add: bool) -> Self {
+        self.set_slave_add = set_slave_add;
+        self
+    }
+
+    /// Shortest time between two polls of the slave in 100 us.
+    pub fn min_slave_interval(mut self, min_slave_interval: u16) -> Self {
+        self.min_slave_interval = min_slave_interval;
+        self
+    }
+
+    pub fn module_name(mut self, module_name: &'a str) -> Self {
+        self.module_name = module_name;
+        self
+    }
+
+    pub fn module_config(mut self, module_config: &'a [u8]) -> Self {
+        self.module_config = module_config;
+        self
+    }
+
+    /// Describes a modular station with the modules of the catalog instead
+    /// of the single module.
+    pub fn modules(mut self, catalog: ModuleCatalog<'a>) -> Self {
+        self.modules = catalog.modules();
+        self.max_module = catalog.max_slot_count();
+        self
+    }
+
+    /// Input and output bytes of the largest config. Without, they are
+    /// taken from the module config or the largest module of the catalog
+    /// in every slot.
+    pub fn max_io_len(mut self, max_input_len: usize, max_output_len: usize) -> Self {
+        self.max_io_len = Some((max_input_len, max_output_len));
+        self
+    }
+
+    pub fn user_prm_data_len(mut self, user_prm_data_len: usize) -> Self {
+        self.user_prm_data_len = user_prm_data_len;
+        self
+    }
+
+    /// DPV1 slave, the DPV1 status bytes are put in front of the user
+    /// parameters.
+    pub fn dpv1(mut self, dpv1: bool) -> Self {
+        self.dpv1 = dpv1;
+        self
+    }
+
+    /// Longest record of Read and Write the slave can send, at most
+    /// `MAX_RECORD_LEN`.
+    pub fn max_record_len(mut self, max_record_len: usize) -> Self {
+        self.max_record_len = max_record_len;
+        self
+    }
+
+    /// Alarm types of a DPV1 slave as bits of `dpv1_status_byte2`. They are
+    /// enabled in the default parameters.
+    pub fn alarms(mut self, alarms: u8) -> Self {
+        self.alarms = alarms;
+        self
+    }
+
+    /// Length of the extended diagnostic after the 6 standard bytes.
+    pub fn ext_diag_len(mut self, ext_diag_len: usize) -> Self {
+        self.ext_diag_len = ext_diag_len;
+        self
+    }
+
+    /// Writes the GSD. Lines end with CR LF as expected by the tools on
+    /// Windows.
+    pub fn write<W: Write>(&self, out: &mut W) -> Result<(), GsdError> {
+        self.check()?;
+        let (input_len, output_len) = match self.max_io_len {
+            Some(max_io_len) => max_io_len,
+            None => self.io_len()?,
+        };
+        let modular = !self.modules.is_empty();
+
+        out.write_str("#Profibus_DP\r\n")?;
+        write!(out, "GSD_Revision = 5\r\n")?;
+        write!(out, "Vendor_Name = \"{}\"\r\n", self.vendor_name)?;
+        write!(out, "Model_Name = \"{}\"\r\n", self.model_name)?;
+        write!(out, "Revision = \"{}\"\r\n", self.revision)?;
+        write!(out, "Ident_Number = 0x{:04X}\r\n", self.ident_number)?;
+        write!(out, "Protocol_Ident = 0\r\n")?;
+        write!(out, "Station_Type = 0\r\n")?;
+        write!(out, "FMS_supp = 0\r\n")?;
+        write!(out, "Hardware_Release = \"{}\"\r\n", self.hardware_release)?;
+        write!(out, "Software_Release = \"{}\"\r\n", self.software_release)?;
+        for baudrate in Baudrate::ALL {
+            if self.supports(baudrate) {
+                write!(out, "{}_supp = 1\r\n", baudrate.keyword())?;
+            }
+        }
+        for baudrate in Baudrate::ALL {
+            if self.supports(baudrate) {
+                write!(
+                    out,
+                    "MaxTsdr_{} = {}\r\n",
+                    baudrate.keyword(),
+                    baudrate.max_tsdr()
+                )?;
+            }
+        }
+        write!(out, "Redundancy = 0\r\n")?;
+        write!(out, "Repeater_Ctrl_Sig = 2\r\n")?;
+        write!(out, "24V_Pins = 0\r\n")?;
+        write!(out, "Freeze_Mode_supp = {}\r\n", u8::from(self.freeze))?;
+        write!(out, "Sync_Mode_supp = {}\r\n", u8::from(self.sync))?;
+        write!(out, "Auto_Baud_supp = {}\r\n", u8::from(self.auto_baud))?;
+        write!(
+            out,
+            "Set_Slave_Add_supp = {}\r\n",
+            u8::from(self.set_slave_add)
+        )?;
+        write!(out, "Min_Slave_Intervall = {}\r\n", self.min_slave_interval)?;
+        write!(out, "Modular_Station = {}\r\n", u8::from(modular))?;
+        write!(
+            out,
+            "Max_Module = {}\r\n",
+            if modular { self.max_module } else { 1 }
+        )?;
+        write!(out, "Max_Input_Len = {}\r\n", input_len)?;
+        write!(out, "Max_Output_Len = {}\r\n", output_len)?;
+        write!(out, "Max_Data_Len = {}\r\n", input_len + output_len)?;
+        write!(out, "Max_Diag_Data_Len = {}\r\n", self.diag_data_len())?;
+        write!(out, "Slave_Family = 0\r\n")?;
+        if self.dpv1 {
+            write!(out, "DPV1_Slave = 1\r\n")?;
+            write!(out, "C1_Read_Write_supp = 1\r\n")?;
+            let max_record_len = self.max_record_len.min(MAX_RECORD_LEN);
+            write!(out, "C1_Max_Data_Len = {}\r\n", max_record_len)?;
+            // 1 s in 10 ms
+            write!(out, "C1_Response_Timeout = 100\r\n")?;
+            write!(out, "C2_Read_Write_supp = 1\r\n")?;
+            write!(out, "C2_Max_Data_Len = {}\r\n", max_record_len)?;
+            write!(out, "C2_Response_Timeout = 100\r\n")?;
+            write!(out, "C2_Max_Count_Channels = 1\r\n")?;
+            // usual length of an Initiate with additional addresses
+            write!(out, "Max_Initiate_PDU_Length = 52\r\n")?;
+            for (alarm, keyword) in [
+                (dpv1_status_byte2::DIAGNOSE_ALARM, "Diagnostic_Alarm_supp"),
+                (dpv1_status_byte2::PROZESS_ALARM, "Process_Alarm_supp"),
+                (dpv1_status_byte2::PULL_PLUG_ALARM, "Pull_Plug_Alarm_supp"),
+                (dpv1_status_byte2::STATUS_ALARM, "Status_Alarm_supp"),
+                (dpv1_status_byte2::UPDATE_ALARM, "Update_Alarm_supp"),
+                (
+                    dpv1_status_byte2::VENDOR_ALARM,
+                    "Manufacturer_Specific_Alarm_supp",
+                ),
+            ] {
+                if (self.alarms & alarm) != 0 {
+                    write!(out, "{} = 1\r\n", keyword)?;
+                }
+            }
+        }
+        write!(out, "User_Prm_Data_Len = {}\r\n", self.prm_data_len())?;
+        if self.prm_data_len() > 0 {
+            // DPV1 mode on, all supported alarms enabled
+            let dpv1_status: &[u8] = if self.dpv1 {
+                &[0x80, self.alarms, 0x00]
+            } else {
+                &[]
+            };
+            out.write_str("User_Prm_Data = ")?;
+            write_hex_list(
+                out,
+                dpv1_status
+                    .iter()
+                    .copied()
+                    .chain((0..self.user_prm_data_len).map(|_| 0)),
+            )?;
+            out.write_str("\r\n")?;
+        }
+        if modular {
+            for (index, module) in self.modules.iter().enumerate() {
+                write!(out, "Module = \"{}\" ", module.name)?;
+                write_hex_list(out, module.config.iter().copied())?;
+                write!(out, "\r\n{}\r\nEndModule\r\n", index + 1)?;
+            }
+        } else {
+            write!(out, "Module = \"{}\" ", self.module_name)?;
+            write_hex_list(out, self.module_config.iter().copied())?;
+            out.write_str("\r\nEndModule\r\n")?;
+        }
+        Ok(())
+    }
+
+    fn io_len(&self) -> Result<(usize, usize), GsdError> {
+        if self.modules.is_empty() {
+            return cfg::io_len(self.module_config).map_err(GsdError::ModuleConfig);
+        }
+        let (mut input_len, mut output_len) = (0, 0);
+        for module in self.modules {
+            let (input, output) = cfg::io_len(module.config).map_err(GsdError::ModuleConfig)?;
+            input_len = input_len.max(input);
+            output_len = output_len.max(output);
+        }
+        Ok((input_len * self.max_module, output_len * self.max_module))
+    }
+
+    fn prm_data_len(&self) -> usize {
+        if self.dpv1 {
+            self.user_prm_data_len + 3
+        } else {
+            self.user_prm_data_len
+        }
+    }
+
+    /// Standard diagnostic, extended diagnostic and room for an alarm.
+    fn diag_data_len(&self) -> usize {
+        let alarm_len = if self.dpv1 && self.alarms != 0 {
+            MAX_ALARM_BLOCK_LEN
+        } else {
+            0
+        };
+        6 + self.ext_diag_len + alarm_len
+    }
+
+    fn supports(&self, baudrate: Baudrate) -> bool {
+        (self.baudrates == 0) || ((self.baudrates & baudrate.mask()) != 0)
+    }
+
+    fn check(&self) -> Result<(), GsdError> {
+        for (keyword, value) in [
+            ("Vendor_Name", self.vendor_name),
+            ("Model_Name", self.model_name),
+            ("Revision", self.revision),
+            ("Hardware_Release", self.hardware_release),
+            ("Software_Release", self.software_release),
+            ("Module", self.module_name),
+        ] {
+            if value.len() > MAX_NAME_LEN {
+                return Err(GsdError::TooLong(keyword));
+            }
+            if value.contains('"') {
+                return Err(GsdError::InvalidValue(keyword));
+            }
+        }
+        if self.prm_data_len() > MAX_USER_PRM_DATA_LEN {
+            return Err(GsdError::TooLong("User_Prm_Data_Len"));
+        }
+        if self.diag_data_len() > MAX_DIAG_DATA_LEN {
+            return Err(GsdError::TooLong("Max_Diag_Data_Len"));
+        }
+        if self.modules.is_empty() {
+            if self.module_config.is_empty() {
+                return Err(GsdError::InvalidValue("Module"));
+            }
+            if self.module_config.len() > MAX_CFG_DATA_LEN {
+                return Err(GsdError::TooLong("Module"));
+            }
+        }
+        for module in self.modules {
+            if module.name.contains('"') || module.config.is_empty() {
+                return Err(GsdError::InvalidValue("Module"));
+            }
+            if module.name.len() > MAX_NAME_LEN || module.config.len() > MAX_CFG_DATA_LEN {
+                return Err(GsdError::TooLong("Module"));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for GsdConfig<'_> {
+    fn default() -> Self {
+        Self {
+            vendor_name: "joao404",
+            model_name: "STM32F1 DP Slave",
+            revision: "1",
+            hardware_release: "1",
+            software_release: "1",
+            ident_number: 0,
+            baudrates: 0,
+            freeze: true,
+            sync: true,
+            auto_baud: false,
+            set_slave_add: true,
+            min_slave_interval: 1,
+            module_name: "Module",
+            module_config: &[],
+            modules: &[],
+            max_module: 1,
+            max_io_len: None,
+            user_prm_data_len: 0,
+            dpv1: false,
+            max_record_len: MAX_RECORD_LEN,
+            alarms: 0,
+            ext_diag_len: 0,
+        }
+    }
+}
+
+fn write_hex_list<W: Write>(out: &mut W, values: impl Iterator<Item = u8>) -> fmt::Result {
+    for (index, value) in values.enumerate() {
+        if index > 0 {
+            out.write_char(',')?;
+        }
+        write!(out, "0x{:02X}", value)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,10 @@
+pub use generator::{
+    Baudrate, GsdConfig, GsdError, MAX_CFG_DATA_LEN, MAX_DIAG_DATA_LEN, MAX_NAME_LEN,
+    MAX_USER_PRM_DATA_LEN,
+};
+#[cfg(feature = "std")]
+pub use parser::{GsdDevice, GsdModule, GsdParseError, PrmDataType, PrmValues, UserPrm};
+
+pub mod generator;
+#[cfg(feature = "std")]
+pub mod parser;
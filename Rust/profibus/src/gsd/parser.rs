@@ -0,0 +1,732 @@
+/*********************************************************************
+ * GSD parser
+ *
+ * Copyright (C) 2023 Marcel Maage
+ *
+ * This library is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2.1 of the License, or (at your option) any later version.
+ *
+ * This library is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * LICENSE file for more details.
+ */
+
+//! Reads a vendor GSD into a `GsdDevice`, from which the slave
+//! configuration and the layout of the user parameters are taken.
+//!
+//! Keywords are matched without case, comments (`;`) and continued lines
+//! (`\`) are handled. Keywords the slave does not need are skipped, as are
+//! `#include` files.
+
+use std::string::{String, ToString};
+use std::vec::Vec;
+
+use super::generator::{Baudrate, MAX_USER_PRM_DATA_LEN};
+use crate::slave::ProfibusConfig;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GsdParseError {
+    /// A line is neither `keyword = value` nor a known statement.
+    InvalidLine {
+        line: usize,
+    },
+    InvalidNumber {
+        line: usize,
+    },
+    InvalidString {
+        line: usize,
+    },
+    /// `Ext_User_Prm_Data_Ref` or `Prm_Text_Ref` to an undefined number.
+    UnknownReference {
+        line: usize,
+        reference: u32,
+    },
+    /// A `Module`, `ExtUserPrmData` or `PrmText` block is not closed.
+    MissingEnd {
+        line: usize,
+    },
+    MissingIdentNumber,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrmDataType {
+    Unsigned8,
+    Unsigned16,
+    Unsigned32,
+    Signed8,
+    Signed16,
+    Signed32,
+    /// Single bit of a byte.
+    Bit(u8),
+    /// Bits `first..=last` of a byte.
+    BitArea(u8, u8),
+}
+
+impl PrmDataType {
+    /// Bytes the parameter occupies in the user parameters.
+    pub fn byte_len(self) -> usize {
+        match self {
+            PrmDataType::Unsigned16 | PrmDataType::Signed16 => 2,
+            PrmDataType::Unsigned32 | PrmDataType::Signed32 => 4,
+            _ => 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PrmValues {
+    Range(i64, i64),
+    List(Vec<i64>),
+}
+
+impl PrmValues {
+    pub fn contains(&self, value: i64) -> bool {
+        match self {
+            PrmValues::Range(min, max) => (*min..=*max).contains(&value),
+            PrmValues::List(values) => values.contains(&value),
+        }
+    }
+}
+
+/// One parameter at `offset` in the user parameters (or in the parameters
+/// of a module), from `Ext_User_Prm_Data_Ref` and its `ExtUserPrmData`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserPrm {
+    pub offset: usize,
+    pub name: String,
+    pub data_type: PrmDataType,
+    pub default: i64,
+    pub values: PrmValues,
+    /// Names of the values from `PrmText`.
+    pub texts: Vec<(i64, String)>,
+}
+
+impl UserPrm {
+    /// Reads the value of the parameter, big endian as on the bus.
+    pub fn read(&self, data: &[u8]) -> Option<i64> {
+        let end = self.offset.checked_add(self.data_type.byte_len())?;
+        let bytes = data.get(self.offset..end)?;
+        let raw = bytes
+            .iter()
+            .fold(0u32, |value, byte| (value << 8) | u32::from(*byte));
+        Some(match self.data_type {
+            PrmDataType::Unsigned8 | PrmDataType::Unsigned16 | PrmDataType::Unsigned32 => {
+                i64::from(raw)
+            }
+            PrmDataType::Signed8 => i64::from(raw as u8 as i8),
+            PrmDataType::Signed16 => i64::from(raw as u16 as i16),
+            PrmDataType::Signed32 => i64::from(raw as i32),
+            PrmDataType::Bit(bit) => i64::from((raw >> bit) & bit_mask(bit, bit)?),
+            PrmDataType::BitArea(first, last) => i64::from((raw >> first) & bit_mask(first, last)?),
+        })
+    }
+
+    /// Writes `value` into the parameter, other bits of the byte are kept.
+    pub fn write(&self, data: &mut [u8], value: i64) -> Option<()> {
+        let len = self.data_type.byte_len();
+        let bytes = data.get_mut(self.offset..self.offset.checked_add(len)?)?;
+        match self.data_type {
+            PrmDataType::Bit(bit) => {
+                let mask = (bit_mask(bit, bit)? as u8) << bit;
+                bytes[0] = (bytes[0] & !mask) | (((value & 1) as u8) << bit);
+            }
+            PrmDataType::BitArea(first, last) => {
+                let mask = (bit_mask(first, last)? as u8) << first;
+                bytes[0] = (bytes[0] & !mask) | (((value as u8) << first) & mask);
+            }
+            _ => {
+                let value = (value as u32).to_be_bytes();
+                bytes.copy_from_slice(&value[4 - len..]);
+            }
+        }
+        Some(())
+    }
+
+    pub fn text(&self, value: i64) -> Option<&str> {
+        self.texts
+            .iter()
+            .find(|(text_value, _)| *text_value == value)
+            .map(|(_, text)| text.as_str())
+    }
+}
+
+/// Mask of `last - first + 1` bits, `None` unless `first <= last <= 7`.
+fn bit_mask(first: u8, last: u8) -> Option<u32> {
+    if first > last || last > 7 {
+        return None;
+    }
+    Some((1u32 << (u32::from(last - first) + 1)) - 1)
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GsdModule {
+    pub name: String,
+    /// Identifier bytes for Chk_Cfg.
+    pub config: Vec<u8>,
+    /// Module reference number of GSD revision 3 and later.
+    pub reference: Option<u32>,
+    pub prm_data_len: usize,
+    /// Constant parameter bytes as (offset, bytes).
+    pub prm_consts: Vec<(usize, Vec<u8>)>,
+    pub prms: Vec<UserPrm>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GsdDevice {
+    pub gsd_revision: u8,
+    pub vendor_name: String,
+    pub model_name: String,
+    pub revision: String,
+    pub hardware_release: String,
+    pub software_release: String,
+    pub ident_number: u16,
+    /// Supported baudrates with their max TSDR in bit times.
+    pub baudrates: Vec<(Baudrate, u16)>,
+    pub freeze: bool,
+    pub sync: bool,
+    pub auto_baud: bool,
+    pub set_slave_add: bool,
+    pub min_slave_interval: u16,
+    pub modular_station: bool,
+    pub max_module: usize,
+    pub max_input_len: usize,
+    pub max_output_len: usize,
+    pub max_data_len: usize,
+    pub max_diag_data_len: usize,
+    /// Length of the global parameters.
+    pub user_prm_data_len: usize,
+    /// Limit of the global and module parameters together, 0 if not given.
+    pub max_user_prm_data_len: usize,
+    pub user_prm_data: Vec<u8>,
+    /// Constant parameter bytes as (offset, bytes).
+    pub prm_consts: Vec<(usize, Vec<u8>)>,
+    pub prms: Vec<UserPrm>,
+    pub modules: Vec<GsdModule>,
+}
+
+impl GsdDevice {
+    pub fn parse(text: &str) -> Result<Self, GsdParseError> {
+        Parser::default().parse(text)
+    }
+
+    /// Ident number of the GSD for the slave, the bus timing is up to the
+    /// hardware.
+    pub fn profibus_config(&self) -> ProfibusConfig {
+        let [ident_high, ident_low] = self.ident_number.to_be_bytes();
+        ProfibusConfig::default()
+            .ident_high(ident_high)
+            .ident_low(ident_low)
+    }
+
+    pub fn module(&self, name: &str) -> Option<&GsdModule> {
+        self.modules.iter().find(|module| module.name == name)
+    }
+
+    /// Chk_Cfg data of the given modules (indices into `modules`) in slot
+    /// order. A compact station has only module 0.
+    pub fn module_config(&self, modules: &[usize]) -> Option<Vec<u8>> {
+        let mut config = Vec::new();
+        for index in modules {
+            config.extend_from_slice(&self.modules.get(*index)?.config);
+        }
+        Some(config)
+    }
+
+    /// User parameters a master sends by default for the given modules: the
+    /// global parameters followed by the parameters of each module. `None`
+    /// for an unknown module or if the parameters exceed
+    /// `Max_User_Prm_Data_Len`.
+    pub fn default_user_prm_data(&self, modules: &[usize]) -> Option<Vec<u8>> {
+        let mut data = self.user_prm_data.clone();
+        let len = prm_len(&self.prm_consts, &self.prms)?.max(self.user_prm_data_len);
+        if data.len() < len {
+            data.resize(len, 0);
+        }
+        apply_defaults(&mut data, &self.prm_consts, &self.prms, 0);
+        for index in modules {
+            let module = self.modules.get(*index)?;
+            let offset = data.len();
+            let len = prm_len(&module.prm_consts, &module.prms)?.max(module.prm_data_len);
+            data.resize(offset.checked_add(len)?, 0);
+            apply_defaults(&mut data, &module.prm_consts, &module.prms, offset);
+        }
+        if self.max_user_prm_data_len != 0 && data.len() > self.max_user_prm_data_len {
+            return None;
+        }
+        Some(data)
+    }
+}
+
+fn prm_len(consts: &[(usize, Vec<u8>)], prms: &[UserPrm]) -> Option<usize> {
+    let mut len = 0;
+    for (offset, bytes) in consts {
+        len = offset.checked_add(bytes.len())?.max(len);
+    }
+    for prm in prms {
+        len = prm.offset.checked_add(prm.data_type.byte_len())?.max(len);
+    }
+    Some(len)
+}
+
+fn apply_defaults(data: &mut [u8], consts: &[(usize, Vec<u8>)], prms: &[UserPrm], offset: usize) {
+    for (const_offset, bytes) in consts {
+        let start = offset + const_offset;
+        data[start..start + bytes.len()].copy_from_slice(bytes);
+    }
+    for prm in prms {
+        let prm = UserPrm {
+            offset: offset + prm.offset,
+            ..prm.clone()
+        };
+        prm.write(data, prm.default);
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ExtUserPrmData {
+    reference: u32,
+    name: String,
+    data_type: PrmDataType,
+    default: i64,
+    values: PrmValues,
+    text_ref: Option<(usize, u32)>,
+}
+
+enum Block {
+    None,
+    Module(usize),
+    ExtUserPrmData(usize),
+    PrmText(usize),
+}
+
+/// `Ext_User_Prm_Data_Ref` seen before all definitions are known.
+struct PrmRef {
+    line: usize,
+    module: Option<usize>,
+    offset: usize,
+    reference: u32,
+}
+
+#[derive(Default)]
+struct Parser {
+    device: GsdDevice,
+    ext_user_prm_data: Vec<ExtUserPrmData>,
+    prm_texts: Vec<(u32, Vec<(i64, String)>)>,
+    prm_refs: Vec<PrmRef>,
+    ident_number: bool,
+}
+
+impl Parser {
+    fn parse(mut self, text: &str) -> Result<GsdDevice, GsdParseError> {
+        let mut block = Block::None;
+        let mut block_line = 0;
+        for (line, content) in logical_lines(text) {
+            let statement = Statement::split(&content);
+            let keyword = statement.keyword.to_ascii_lowercase();
+            match block {
+                Block::Module(index) => match keyword.as_str() {
+                    "endmodule" => block = Block::None,
+                    _ => self.module_line(line, index, &keyword, &statement)?,
+                },
+                Block::ExtUserPrmData(index) => match keyword.as_str() {
+                    "endextuserprmdata" => block = Block::None,
+                    _ => self.ext_user_prm_data_line(line, index, &keyword, &statement)?,
+                },
+                Block::PrmText(index) => match keyword.as_str() {
+                    "endprmtext" => block = Block::None,
+                    "text" => {
+                        let value = number(line, statement.argument.unwrap_or(""))?;
+                        let (text, _) = string(line, statement.value.unwrap_or(""))?;
+                        self.prm_texts[index].1.push((value, text));
+                    }
+                    _ => (),
+                },
+                Block::None => {
+                    block_line = line;
+                    block = self.device_line(line, &keyword, &statement)?;
+                }
+            }
+        }
+        if !matches!(block, Block::None) {
+            return Err(GsdParseError::MissingEnd { line: block_line });
+        }
+        if !self.ident_number {
+            return Err(GsdParseError::MissingIdentNumber);
+        }
+        self.resolve()
+    }
+
+    fn device_line(
+        &mut self,
+        line: usize,
+        keyword: &str,
+        statement: &Statement,
+    ) -> Result<Block, GsdParseError> {
+        let device = &mut self.device;
+        let argument = statement.argument;
+        let Some(value) = statement.value else {
+            // statements without value, e.g. #Profibus_DP
+            return Ok(Block::None);
+        };
+        match keyword {
+            "gsd_revision" => device.gsd_revision = unsigned(line, value)?,
+            "vendor_name" => device.vendor_name = string(line, value)?.0,
+            "model_name" => device.model_name = string(line, value)?.0,
+            "revision" => device.revision = string(line, value)?.0,
+            "hardware_release" => device.hardware_release = string(line, value)?.0,
+            "software_release" => device.software_release = string(line, value)?.0,
+            "ident_number" => {
+                device.ident_number = unsigned(line, value)?;
+                self.ident_number = true;
+            }
+            "freeze_mode_supp" => device.freeze = number(line, value)? != 0,
+            "sync_mode_supp" => device.sync = number(line, value)? != 0,
+            "auto_baud_supp" => device.auto_baud = number(line, value)? != 0,
+            "set_slave_add_supp" => device.set_slave_add = number(line, value)? != 0,
+            "min_slave_intervall" => device.min_slave_interval = unsigned(line, value)?,
+            "modular_station" => device.modular_station = number(line, value)? != 0,
+            "max_module" => device.max_module = unsigned(line, value)?,
+            "max_input_len" => device.max_input_len = unsigned(line, value)?,
+            "max_output_len" => device.max_output_len = unsigned(line, value)?,
+            "max_data_len" => device.max_data_len = unsigned(line, value)?,
+            "max_diag_data_len" => device.max_diag_data_len = unsigned(line, value)?,
+            "user_prm_data_len" => device.user_prm_data_len = prm_offset(line, value)?,
+            "max_user_prm_data_len" => device.max_user_prm_data_len = prm_offset(line, value)?,
+            "user_prm_data" => device.user_prm_data = list(line, value)?,
+            "ext_user_prm_data_const" => {
+                let offset = prm_offset(line, argument.unwrap_or(""))?;
+                device.prm_consts.push((offset, list(line, value)?));
+            }
+            "ext_user_prm_data_ref" => {
+                self.prm_refs.push(PrmRef {
+                    line,
+                    module: None,
+                    offset: prm_offset(line, argument.unwrap_or(""))?,
+                    reference: unsigned(line, value)?,
+                });
+            }
+            "module" => {
+                let (name, rest) = string(line, value)?;
+                device.modules.push(GsdModule {
+                    name,
+                    config: list(line, rest)?,
+                    ..GsdModule::default()
+                });
+                return Ok(Block::Module(device.modules.len() - 1));
+            }
+            "extuserprmdata" => {
+                let reference = unsigned(line, first_token(value))?;
+                let (name, _) = string(line, value[first_token(value).len()..].trim())?;
+                self.ext_user_prm_data.push(ExtUserPrmData {
+                    reference,
+                    name,
+                    data_type: PrmDataType::Unsigned8,
+                    default: 0,
+                    values: PrmValues::Range(0, 255),
+                    text_ref: None,
+                });
+                return Ok(Block::ExtUserPrmData(self.ext_user_prm_data.len() - 1));
+            }
+            "prmtext" => {
+                self.prm_texts.push((unsigned(line, value)?, Vec::new()));
+                return Ok(Block::PrmText(self.prm_texts.len() - 1));
+            }
+            _ => {
+                if let Some(baudrate) = keyword.strip_suffix("_supp") {
+                    if let Some(baudrate) = Baudrate::from_keyword(baudrate) {
+                        if number(line, value)? != 0 {
+                            device.baudrates.push((baudrate, baudrate.max_tsdr()));
+                        }
+                    }
+                } else if let Some(baudrate) = keyword.strip_prefix("maxtsdr_") {
+                    if let Some(baudrate) = Baudrate::from_keyword(baudrate) {
+                        let max_tsdr = unsigned(line, value)?;
+                        if let Some(entry) = device.baudrates.iter_mut().find(|b| b.0 == baudrate) {
+                            entry.1 = max_tsdr;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(Block::None)
+    }
+
+    fn module_line(
+        &mut self,
+        line: usize,
+        index: usize,
+        keyword: &str,
+        statement: &Statement,
+    ) -> Result<(), GsdParseError> {
+        let module = &mut self.device.modules[index];
+        let argument = statement.argument;
+        match (keyword, statement.value) {
+            (_, None) => {
+                if module.config.is_empty() {
+                    // identifier bytes on the line after the name
+                    module.config = list(line, statement.text)?;
+                } else if let Ok(reference) = unsigned(line, statement.text) {
+                    module.reference = Some(reference);
+                }
+            }
+            ("ext_module_prm_data_len", Some(value)) => {
+                module.prm_data_len = prm_offset(line, value)?;
+            }
+            ("ext_user_prm_data_const", Some(value)) => {
+                let offset = prm_offset(line, argument.unwrap_or(""))?;
+                module.prm_consts.push((offset, list(line, value)?));
+            }
+            ("ext_user_prm_data_ref", Some(value)) => {
+                self.prm_refs.push(PrmRef {
+                    line,
+                    module: Some(index),
+                    offset: prm_offset(line, argument.unwrap_or(""))?,
+                    reference: unsigned(line, value)?,
+                });
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    fn ext_user_prm_data_line(
+        &mut self,
+        line: usize,
+        index: usize,
+        keyword: &str,
+        statement: &Statement,
+    ) -> Result<(), GsdParseError> {
+        let data = &mut self.ext_user_prm_data[index];
+        if let Some(value) = statement.value {
+            if keyword == "prm_text_ref" {
+                data.text_ref = Some((line, unsigned(line, value)?));
+            }
+            return Ok(());
+        }
+        data.data_type = match (keyword, statement.argument) {
+            ("unsigned8", _) => PrmDataType::Unsigned8,
+            ("unsigned16", _) => PrmDataType::Unsigned16,
+            ("unsigned32", _) => PrmDataType::Unsigned32,
+            ("signed8", _) => PrmDataType::Signed8,
+            ("signed16", _) => PrmDataType::Signed16,
+            ("signed32", _) => PrmDataType::Signed32,
+            ("bit", Some(bit)) => {
+                let bit = unsigned(line, bit)?;
+                bit_mask(bit, bit).ok_or(GsdParseError::InvalidNumber { line })?;
+                PrmDataType::Bit(bit)
+            }
+            ("bitarea", Some(area)) => {
+                let (first, last) =
+                    split_range(area).ok_or(GsdParseError::InvalidNumber { line })?;
+                let (first, last) = (unsigned(line, first)?, unsigned(line, last)?);
+                bit_mask(first, last).ok_or(GsdParseError::InvalidNumber { line })?;
+                PrmDataType::BitArea(first, last)
+            }
+            _ => return Ok(()),
+        };
+        // "<default> <min>-<max>" or "<default> <value>,<value>,..."
+        let default = first_token(statement.rest);
+        data.default = number(line, default)?;
+        let values = statement.rest[default.len()..].trim();
+        if values.is_empty() {
+            return Ok(());
+        }
+        data.values = if values.contains(',') {
+            PrmValues::List(
+                values
+                    .split(',')
+                    .map(|value| number(line, value))
+                    .collect::<Result<_, _>>()?,
+            )
+        } else if let Some((min, max)) = split_range(values) {
+            PrmValues::Range(number(line, min)?, number(line, max)?)
+        } else {
+            let value = number(line, values)?;
+            PrmValues::Range(value, value)
+        };
+        Ok(())
+    }
+
+    fn resolve(mut self) -> Result<GsdDevice, GsdParseError> {
+        for prm_ref in core::mem::take(&mut self.prm_refs) {
+            let Some(data) = self
+                .ext_user_prm_data
+                .iter()
+                .find(|data| data.reference == prm_ref.reference)
+            else {
+                return Err(GsdParseError::UnknownReference {
+                    line: prm_ref.line,
+                    reference: prm_ref.reference,
+                });
+            };
+            let texts = match data.text_ref {
+                Some((line, reference)) => self
+                    .prm_texts
+                    .iter()
+                    .find(|(text_reference, _)| *text_reference == reference)
+                    .map(|(_, texts)| texts.clone())
+                    .ok_or(GsdParseError::UnknownReference { line, reference })?,
+                None => Vec::new(),
+            };
+            let prm = UserPrm {
+                offset: prm_ref.offset,
+                name: data.name.clone(),
+                data_type: data.data_type,
+                default: data.default,
+                values: data.values.clone(),
+                texts,
+            };
+            match prm_ref.module {
+                Some(index) => self.device.modules[index].prms.push(prm),
+                None => self.device.prms.push(prm),
+            }
+        }
+        Ok(self.device)
+    }
+}
+
+/// One logical line: `keyword(argument) = value` or a statement without
+/// value like `Bit(3) 0 0-1`, whose parts after the keyword are in `rest`.
+struct Statement<'a> {
+    text: &'a str,
+    keyword: &'a str,
+    argument: Option<&'a str>,
+    value: Option<&'a str>,
+    rest: &'a str,
+}
+
+impl<'a> Statement<'a> {
+    fn split(text: &'a str) -> Self {
+        let keyword_end = text
+            .find(|c: char| c == '(' || c == '=' || c.is_whitespace())
+            .unwrap_or(text.len());
+        let keyword = &text[..keyword_end];
+        let mut rest = text[keyword_end..].trim_start();
+        let mut argument = None;
+        if let Some(after) = rest.strip_prefix('(') {
+            if let Some(end) = after.find(')') {
+                argument = Some(after[..end].trim());
+                rest = after[end + 1..].trim_start();
+            }
+        }
+        let value = rest.strip_prefix('=').map(str::trim);
+        Statement {
+            text,
+            keyword,
+            argument,
+            value,
+            rest: if value.is_some() { "" } else { rest },
+        }
+    }
+}
+
+/// Lines without comments, continued lines joined, with the number of their
+/// first line.
+fn logical_lines(text: &str) -> Vec<(usize, String)> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    let mut start = 0;
+    for (index, raw) in text.lines().enumerate() {
+        if current.is_empty() {
+            start = index + 1;
+        }
+        let line = strip_comment(raw).trim_end();
+        if let Some(continued) = line.strip_suffix('\\') {
+            current.push_str(continued);
+            current.push(' ');
+            continue;
+        }
+        current.push_str(line);
+        let content = current.trim();
+        if !content.is_empty() {
+            lines.push((start, content.to_string()));
+        }
+        current.clear();
+    }
+    lines
+}
+
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    for (index, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ';' if !quoted => return &line[..index],
+            _ => (),
+        }
+    }
+    line
+}
+
+fn first_token(text: &str) -> &str {
+    let text = text.trim_start();
+    &text[..text.find(char::is_whitespace).unwrap_or(text.len())]
+}
+
+/// Splits `min-max`, where both may be negative.
+fn split_range(text: &str) -> Option<(&str, &str)> {
+    let text = text.trim();
+    let index = text
+        .char_indices()
+        .skip(1)
+        .find(|(index, c)| *c == '-' && text.as_bytes()[index - 1].is_ascii_alphanumeric())?
+        .0;
+    Some((&text[..index], &text[index + 1..]))
+}
+
+fn number(line: usize, text: &str) -> Result<i64, GsdParseError> {
+    let text = text.trim();
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+    let value = match digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        Some(hex) => i64::from_str_radix(hex, 16),
+        None => digits.parse::<i64>(),
+    }
+    .map_err(|_| GsdParseError::InvalidNumber { line })?;
+    Ok(if negative { -value } else { value })
+}
+
+/// Number which fits into `T` without truncation.
+fn unsigned<T: TryFrom<i64>>(line: usize, text: &str) -> Result<T, GsdParseError> {
+    T::try_from(number(line, text)?).map_err(|_| GsdParseError::InvalidNumber { line })
+}
+
+/// Offset or length within the user parameters.
+fn prm_offset(line: usize, text: &str) -> Result<usize, GsdParseError> {
+    match unsigned(line, text)? {
+        len if len > MAX_USER_PRM_DATA_LEN => Err(GsdParseError::InvalidNumber { line }),
+        len => Ok(len),
+    }
+}
+
+/// Quoted string at the start of `text` and what follows it.
+fn string(line: usize, text: &str) -> Result<(String, &str), GsdParseError> {
+    let text = text
+        .trim_start()
+        .strip_prefix('"')
+        .ok_or(GsdParseError::InvalidString { line })?;
+    let end = text
+        .find('"')
+        .ok_or(GsdParseError::InvalidString { line })?;
+    Ok((text[..end].to_string(), text[end + 1..].trim()))
+}
+
+/// Comma separated bytes, may be empty.
+fn list(line: usize, text: &str) -> Result<Vec<u8>, GsdParseError> {
+    if text.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    text.split(',')
+        .map(|value| {
+            u8::try_from(number(line, value)?).map_err(|_| GsdParseError::InvalidNumber { line })
+        })
+        .collect()
+}
@@ -0,0 +1,67 @@
+/*********************************************************************
+ * Profibus DP slave
+ *
+ * Copyright (C) 2023 Marcel Maage
+ *
+ * This library is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2.1 of the License, or (at your option) any later version.
+ *
+ * This library is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * LICENSE file for more details.
+ */
+
+//! Hardware independent Profibus DP slave stack.
+//!
+//! `slave` contains the interrupt driven stack used by the RTIC firmware,
+//! `device` the async stack used by the Embassy firmware. Both only talk to
+//! the hardware through `HwInterface`/`CodecHwInterface` and
+//! `DataHandlingInterface`. `gsd` generates the GSD file of a slave, `cfg`
+//! decodes the module config exchanged by Chk_Cfg and Get_Cfg and holds the
+//! module catalog of a modular slave. `alarm` describes the DPV1 alarms a
+//! slave reports in its diagnostic, `diag` builds its extended diagnostic.
+//! `persistent_storage` keeps values set by the master over a reset, in
+//! flash pages or, with `std`, in files.
+//!
+//! With the `std` feature `sim` adds host side implementations of these
+//! interfaces to run the stack in `cargo test` without hardware and `gsd`
+//! also parses vendor GSD files. The
+//! `linux` feature adds `linux`, which runs them on a serial port. `pcap`,
+//! also behind `std`, writes captured telegrams for Wireshark.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+pub use data_handling_interface::DataHandlingInterface;
+pub use device::{Codec, CodecConfig, CodecHwInterface, Device, DeviceConfig, Fdl, FdlConfig, FdlType};
+pub use persistent_storage::{PersistentStorage, StorageError, StorageKey};
+pub use record_handling_interface::{RecordError, RecordHandlingInterface};
+pub use slave::{Dpv1Status, HwInterface, PbDpSlave, ProfibusConfig, ReceiveHandling, UartAccess};
+pub use telegram::{FrameError, Telegram};
+pub use types::{
+    cmd_type, dpv1_abort, dpv1_alarm, dpv1_error, dpv1_function, dpv1_status_byte1, dpv1_status_byte2,
+    dpv1_status_byte3, fc_request, fc_request_high_nibble, fc_request_low_nibble, fc_response,
+    fc_response_high_nibble, fc_response_low_nibble, sap, sap_check_config_request, sap_diagnose_byte1,
+    sap_diagnose_byte2, sap_diagnose_byte3, sap_diagnose_ext, sap_global_control,
+    sap_set_parameter_request, DpSlaveState, StreamState,
+};
+
+pub mod alarm;
+pub mod cfg;
+pub mod data_handling_interface;
+pub mod diag;
+pub mod device;
+pub mod gsd;
+#[cfg(all(feature = "linux", target_os = "linux"))]
+pub mod linux;
+#[cfg(feature = "std")]
+pub mod pcap;
+pub mod persistent_storage;
+pub mod record_handling_interface;
+#[cfg(feature = "std")]
+pub mod sim;
+pub mod slave;
+pub mod telegram;
+pub mod types;
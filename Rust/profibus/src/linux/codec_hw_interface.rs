@@ -0,0 +1,116 @@
+/*********************************************************************
+ * CodecHwInterface on a Linux tty
+ *
+ * Copyright (C) 2023 Marcel Maage
+ *
+ * This library is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2.1 of the License, or (at your option) any later version.
+ *
+ * This library is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * LICENSE file for more details.
+ */
+
+use std::future::Future;
+use std::pin::pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+use std::time::{Duration, Instant};
+
+use super::serial_port::SerialPort;
+use crate::device::CodecHwInterface;
+
+/// `CodecHwInterface` implementation on a serial port for the async stack.
+///
+/// The futures block the calling thread, so the codec runs with `block_on`
+/// or any other executor. `receive_uart_data` returns one telegram, which
+/// is everything received until the frame gap of the port. Time stamps
+/// count from the creation of the interface.
+pub struct LinuxCodecHwInterface {
+    port: SerialPort,
+    rx_enabled: bool,
+    start: Instant,
+    rx_end: Duration,
+}
+
+impl LinuxCodecHwInterface {
+    pub fn new(port: SerialPort) -> Self {
+        Self {
+            port,
+            rx_enabled: false,
+            start: Instant::now(),
+            rx_end: Duration::ZERO,
+        }
+    }
+}
+
+impl CodecHwInterface for LinuxCodecHwInterface {
+    async fn wait_for(&mut self, time_in_us: u32) {
+        thread::sleep(Duration::from_micros(u64::from(time_in_us)));
+    }
+
+    fn rx_rs485_enable(&mut self) {
+        if !self.rx_enabled {
+            // the receiver was off, drop what was on the bus meanwhile
+            self.port.flush_input();
+            self.rx_enabled = true;
+        }
+    }
+
+    fn rx_rs485_disable(&mut self) {
+        self.rx_enabled = false;
+    }
+
+    async fn send_uart_data(&mut self, value: &[u8]) {
+        // a failed write looks like a lost telegram to the master
+        let _ = self.port.write_all(value);
+    }
+
+    async fn receive_uart_data(&mut self, value: &mut [u8], len: &mut usize) {
+        *len = match self.port.read_frame(value) {
+            Ok(len) => {
+                // read_frame returns once the line was idle for the frame gap
+                self.rx_end = self.start.elapsed().saturating_sub(self.port.frame_gap());
+                len
+            }
+            Err(_) => {
+                // e.g. a pty without peer, do not spin on it
+                thread::sleep(self.port.frame_gap());
+                0
+            }
+        };
+    }
+
+    fn rx_timestamp_us(&self) -> u64 {
+        self.rx_end.as_micros() as u64
+    }
+
+    fn get_baudrate(&self) -> u32 {
+        self.port.baudrate()
+    }
+}
+
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Runs `future` to completion on the current thread.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut context = Context::from_waker(&waker);
+    loop {
+        match future.as_mut().poll(&mut context) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
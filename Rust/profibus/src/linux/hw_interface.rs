@@ -0,0 +1,238 @@
+/*********************************************************************
+ * HwInterface on a Linux tty
+ *
+ * Copyright (C) 2023 Marcel Maage
+ *
+ * This library is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2.1 of the License, or (at your option) any later version.
+ *
+ * This library is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * LICENSE file for more details.
+ */
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::io;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use super::serial_port::SerialPort;
+use crate::sim::InterruptTarget;
+use crate::slave::HwInterface;
+
+const TIMER_FREQUENCY: u32 = 1_000_000;
+
+struct PortState {
+    port: SerialPort,
+    error: Option<io::Error>,
+
+    timer_deadline: Option<Instant>,
+
+    rx_fifo: VecDeque<u8>,
+    rx_interrupt: bool,
+
+    tx_buffer: Vec<u8>,
+    tx_interrupt: bool,
+    tx_event: bool,
+
+    receive_handling_scheduled: bool,
+}
+
+impl PortState {
+    fn flush_tx(&mut self) {
+        if !self.tx_buffer.is_empty() {
+            if let Err(error) = self.port.write_all(&self.tx_buffer) {
+                self.error.get_or_insert(error);
+            }
+            self.tx_buffer.clear();
+            self.tx_event = true;
+        }
+    }
+}
+
+/// Runs an interrupt driven slave on a serial port.
+///
+/// `interface` hands out the `HwInterface` for the slave, `run_for` then
+/// plays the role of the interrupt controller: it waits on the tty and the
+/// timer and calls the handlers of the slave in the order the events occur.
+///
+/// Linux cannot keep bit times, so every timer the codec starts lasts at
+/// least the frame gap of the port. Use `UartAccess::Dma` for sending,
+/// otherwise each character is written and drained on its own.
+#[derive(Clone)]
+pub struct LinuxBus {
+    state: Rc<RefCell<PortState>>,
+}
+
+impl LinuxBus {
+    pub fn new(port: SerialPort) -> Self {
+        Self {
+            state: Rc::new(RefCell::new(PortState {
+                port,
+                error: None,
+                timer_deadline: None,
+                rx_fifo: VecDeque::new(),
+                rx_interrupt: false,
+                tx_buffer: Vec::new(),
+                tx_interrupt: false,
+                tx_event: false,
+                receive_handling_scheduled: false,
+            })),
+        }
+    }
+
+    pub fn interface(&self) -> LinuxHwInterface {
+        LinuxHwInterface {
+            state: self.state.clone(),
+        }
+    }
+
+    /// Handles bus events for `duration`. Stops at the first I/O error.
+    pub fn run_for<Target: InterruptTarget>(
+        &self,
+        target: &mut Target,
+        duration: Duration,
+    ) -> io::Result<()> {
+        let end = Instant::now() + duration;
+        loop {
+            self.dispatch(target);
+            if let Some(error) = self.state.borrow_mut().error.take() {
+                return Err(error);
+            }
+            let now = Instant::now();
+            if now >= end {
+                return Ok(());
+            }
+            self.wait_for_event(end - now)?;
+        }
+    }
+
+    /// Handles bus events until an I/O error occurs.
+    pub fn run<Target: InterruptTarget>(&self, target: &mut Target) -> io::Result<()> {
+        loop {
+            self.run_for(target, Duration::from_secs(1))?;
+        }
+    }
+
+    fn dispatch<Target: InterruptTarget>(&self, target: &mut Target) {
+        loop {
+            let mut state = self.state.borrow_mut();
+            state.flush_tx();
+            if (state.tx_event && state.tx_interrupt)
+                || (state.rx_interrupt && !state.rx_fifo.is_empty())
+            {
+                // the handler serves received data first
+                let rx_pending = !state.rx_fifo.is_empty();
+                state.tx_event &= rx_pending;
+                drop(state);
+                target.serial_interrupt();
+            } else if state
+                .timer_deadline
+                .is_some_and(|deadline| deadline <= Instant::now())
+            {
+                state.timer_deadline = None;
+                drop(state);
+                target.timer_interrupt();
+            } else if state.receive_handling_scheduled {
+                state.receive_handling_scheduled = false;
+                drop(state);
+                target.receive_handling();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn wait_for_event(&self, max_wait: Duration) -> io::Result<()> {
+        let mut state = self.state.borrow_mut();
+        let mut timeout = max_wait;
+        if let Some(deadline) = state.timer_deadline {
+            timeout = timeout.min(deadline.saturating_duration_since(Instant::now()));
+        }
+        if state.port.wait_readable(Some(timeout))? {
+            let mut buffer = [0; 256];
+            let count = state.port.read(&mut buffer)?;
+            state.rx_fifo.extend(&buffer[..count]);
+        }
+        Ok(())
+    }
+}
+
+/// `HwInterface` implementation on a `LinuxBus`.
+pub struct LinuxHwInterface {
+    state: Rc<RefCell<PortState>>,
+}
+
+impl HwInterface for LinuxHwInterface {
+    fn run_timer(&mut self, timeout_in_us: u32) {
+        let mut state = self.state.borrow_mut();
+        let timeout = Duration::from_micros(u64::from(timeout_in_us)).max(state.port.frame_gap());
+        state.timer_deadline = Some(Instant::now() + timeout);
+    }
+
+    fn stop_timer(&mut self) {
+        self.state.borrow_mut().timer_deadline = None;
+    }
+
+    fn activate_tx_interrupt(&mut self) {
+        self.state.borrow_mut().tx_interrupt = true;
+    }
+
+    fn deactivate_tx_interrupt(&mut self) {
+        self.state.borrow_mut().tx_interrupt = false;
+    }
+
+    fn activate_rx_interrupt(&mut self) {
+        self.state.borrow_mut().rx_interrupt = true;
+    }
+
+    fn deactivate_rx_interrupt(&mut self) {
+        self.state.borrow_mut().rx_interrupt = false;
+    }
+
+    fn is_rx_received(&mut self) -> bool {
+        !self.state.borrow().rx_fifo.is_empty()
+    }
+
+    fn is_tx_done(&mut self) -> bool {
+        self.state.borrow().tx_buffer.is_empty()
+    }
+
+    fn get_uart_value(&mut self) -> Option<u8> {
+        self.state.borrow_mut().rx_fifo.pop_front()
+    }
+
+    fn set_uart_value(&mut self, value: u8) {
+        self.state.borrow_mut().tx_buffer.push(value);
+    }
+
+    fn send_uart_data(&mut self, value: &[u8]) {
+        self.state.borrow_mut().tx_buffer.extend_from_slice(value);
+    }
+
+    fn get_uart_data(&mut self, value: &mut [u8]) {
+        let mut state = self.state.borrow_mut();
+        for slot in value.iter_mut() {
+            match state.rx_fifo.pop_front() {
+                Some(data) => *slot = data,
+                None => break,
+            }
+        }
+    }
+
+    fn schedule_receive_handling(&mut self) {
+        self.state.borrow_mut().receive_handling_scheduled = true;
+    }
+
+    fn get_baudrate(&self) -> u32 {
+        self.state.borrow().port.baudrate()
+    }
+
+    fn get_timer_frequency(&self) -> u32 {
+        TIMER_FREQUENCY
+    }
+}
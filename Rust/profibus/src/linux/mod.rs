@@ -1,5 +1,5 @@
 /*********************************************************************
- * DataHandlingInterface
+ * Linux serial backend
  *
  * Copyright (C) 2023 Marcel Maage
  *
@@ -14,19 +14,13 @@
  * LICENSE file for more details.
  */
 
-pub trait DataHandlingInterface{ 
+//! Runs the stacks on a Linux tty, e.g. a USB-RS485 adapter or one end of
+//! a `socat -d -d pty,raw,echo=0 pty,raw,echo=0` pair.
 
-    fn config_error_led(&mut self) {}
+pub use codec_hw_interface::{block_on, LinuxCodecHwInterface};
+pub use hw_interface::{LinuxBus, LinuxHwInterface};
+pub use serial_port::{SerialConfig, SerialPort};
 
-    fn error_led_on(&mut self) {}
-
-    fn error_led_off(&mut self) {}
-
-    fn millis(&mut self) -> u32 {
-        0
-    }
-
-    fn data_processing(&self, _input: &mut [u8], _output: &[u8]) {}
-
-    fn debug_write(&mut self, _debug: &str) {}
-}
\ No newline at end of file
+pub mod codec_hw_interface;
+pub mod hw_interface;
+pub mod serial_port;
@@ -0,0 +1,290 @@
+/*********************************************************************
+ * Serial port on a Linux tty
+ *
+ * Copyright (C) 2023 Marcel Maage
+ *
+ * This library is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2.1 of the License, or (at your option) any later version.
+ *
+ * This library is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * LICENSE file for more details.
+ */
+
+use std::ffi::CString;
+use std::io;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::time::{Duration, Instant};
+
+// linux/serial.h, not part of the libc crate
+const SER_RS485_ENABLED: u32 = 1 << 0;
+const SER_RS485_RTS_ON_SEND: u32 = 1 << 1;
+const SER_RS485_RTS_AFTER_SEND: u32 = 1 << 2;
+
+#[repr(C)]
+#[derive(Default)]
+struct SerialRs485 {
+    flags: u32,
+    delay_rts_before_send: u32,
+    delay_rts_after_send: u32,
+    padding: [u32; 5],
+}
+
+pub struct SerialConfig {
+    pub(super) baudrate: u32,
+    pub(super) rs485: bool,
+    pub(super) rts_on_send: bool,
+    pub(super) frame_gap_us: u32,
+    pub(super) discard_echo: bool,
+}
+
+impl SerialConfig {
+    pub fn baudrate(mut self, baudrate: u32) -> Self {
+        self.baudrate = baudrate;
+        self
+    }
+
+    /// Lets the kernel driver switch the RS-485 transceiver with RTS
+    /// (TIOCSRS485). Adapters with automatic direction control do not
+    /// need this.
+    pub fn rs485(mut self, rs485: bool) -> Self {
+        self.rs485 = rs485;
+        self
+    }
+
+    /// Level of RTS while sending, true for most transceivers.
+    pub fn rts_on_send(mut self, rts_on_send: bool) -> Self {
+        self.rts_on_send = rts_on_send;
+        self
+    }
+
+    /// Pause on the line after which a telegram is considered complete.
+    /// USB adapters hand over the received bytes in chunks, so this has to
+    /// cover their latency and is usually far longer than the bus timing.
+    pub fn frame_gap_us(mut self, frame_gap_us: u32) -> Self {
+        self.frame_gap_us = frame_gap_us;
+        self
+    }
+
+    /// Throws away everything received while sending, for adapters which
+    /// echo the own telegram back.
+    pub fn discard_echo(mut self, discard_echo: bool) -> Self {
+        self.discard_echo = discard_echo;
+        self
+    }
+}
+
+impl Default for SerialConfig {
+    fn default() -> Self {
+        Self {
+            baudrate: 500_000,
+            rs485: false,
+            rts_on_send: true,
+            frame_gap_us: 2_000,
+            discard_echo: false,
+        }
+    }
+}
+
+/// Raw tty in the Profibus character format: 8 data bits, even parity and
+/// one stop bit. Characters with parity or framing errors are dropped by
+/// the kernel, so the telegram they belong to fails the length or checksum
+/// check of the codec.
+pub struct SerialPort {
+    fd: OwnedFd,
+    baudrate: u32,
+    frame_gap: Duration,
+    discard_echo: bool,
+}
+
+impl SerialPort {
+    pub fn open(path: &str, config: &SerialConfig) -> io::Result<Self> {
+        let path = CString::new(path).map_err(|_| io::ErrorKind::InvalidInput)?;
+        let fd = unsafe {
+            libc::open(
+                path.as_ptr(),
+                libc::O_RDWR | libc::O_NOCTTY | libc::O_NONBLOCK | libc::O_CLOEXEC,
+            )
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let port = Self {
+            fd: unsafe { OwnedFd::from_raw_fd(fd) },
+            baudrate: config.baudrate,
+            frame_gap: Duration::from_micros(u64::from(config.frame_gap_us)),
+            discard_echo: config.discard_echo,
+        };
+        port.config_termios()?;
+        if config.rs485 {
+            port.config_rs485(config.rts_on_send)?;
+        }
+        Ok(port)
+    }
+
+    fn config_termios(&self) -> io::Result<()> {
+        let mut tio: libc::termios2 = unsafe { core::mem::zeroed() };
+        check(unsafe { libc::ioctl(self.fd.as_raw_fd(), libc::TCGETS2, &mut tio) })?;
+        tio.c_iflag = libc::IGNBRK | libc::IGNPAR | libc::INPCK;
+        tio.c_oflag = 0;
+        tio.c_lflag = 0;
+        // any baudrate, 8E1
+        tio.c_cflag = libc::BOTHER | libc::CS8 | libc::PARENB | libc::CREAD | libc::CLOCAL;
+        tio.c_ispeed = self.baudrate;
+        tio.c_ospeed = self.baudrate;
+        tio.c_cc[libc::VMIN] = 0;
+        tio.c_cc[libc::VTIME] = 0;
+        check(unsafe { libc::ioctl(self.fd.as_raw_fd(), libc::TCSETS2, &tio) })?;
+        self.flush_input();
+        Ok(())
+    }
+
+    fn config_rs485(&self, rts_on_send: bool) -> io::Result<()> {
+        let mut rs485 = SerialRs485 {
+            flags: SER_RS485_ENABLED,
+            ..SerialRs485::default()
+        };
+        if rts_on_send {
+            rs485.flags |= SER_RS485_RTS_ON_SEND;
+        } else {
+            rs485.flags |= SER_RS485_RTS_AFTER_SEND;
+        }
+        check(unsafe { libc::ioctl(self.fd.as_raw_fd(), libc::TIOCSRS485, &rs485) })
+    }
+
+    pub fn baudrate(&self) -> u32 {
+        self.baudrate
+    }
+
+    pub fn frame_gap(&self) -> Duration {
+        self.frame_gap
+    }
+
+    /// Waits until data can be read or `timeout` passed, forever for `None`.
+    pub fn wait_readable(&self, timeout: Option<Duration>) -> io::Result<bool> {
+        let mut poll_fd = libc::pollfd {
+            fd: self.fd.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let timeout_ms = match timeout {
+            // round up, poll only knows milliseconds
+            Some(timeout) => timeout.as_micros().div_ceil(1000).min(i32::MAX as u128) as i32,
+            None => -1,
+        };
+        let result = unsafe { libc::poll(&mut poll_fd, 1, timeout_ms) };
+        if result < 0 {
+            let error = io::Error::last_os_error();
+            if error.kind() == io::ErrorKind::Interrupted {
+                return Ok(false);
+            }
+            return Err(error);
+        }
+        Ok(result > 0)
+    }
+
+    /// Reads what is available without blocking.
+    pub fn read(&self, buffer: &mut [u8]) -> io::Result<usize> {
+        let result = unsafe {
+            libc::read(
+                self.fd.as_raw_fd(),
+                buffer.as_mut_ptr().cast(),
+                buffer.len(),
+            )
+        };
+        if result < 0 {
+            let error = io::Error::last_os_error();
+            if error.kind() == io::ErrorKind::WouldBlock {
+                return Ok(0);
+            }
+            return Err(error);
+        }
+        Ok(result as usize)
+    }
+
+    /// Blocks until a telegram arrived, which ends with a pause of the
+    /// configured frame gap or when `buffer` is full.
+    pub fn read_frame(&self, buffer: &mut [u8]) -> io::Result<usize> {
+        let mut len = 0;
+        self.wait_readable(None)?;
+        let mut last_rx = Instant::now();
+        while len < buffer.len() {
+            let count = self.read(&mut buffer[len..])?;
+            if count > 0 {
+                len += count;
+                last_rx = Instant::now();
+            } else {
+                let idle = last_rx.elapsed();
+                if idle >= self.frame_gap {
+                    if len > 0 {
+                        break;
+                    }
+                    self.wait_readable(None)?;
+                    last_rx = Instant::now();
+                } else {
+                    self.wait_readable(Some(self.frame_gap - idle))?;
+                }
+            }
+        }
+        Ok(len)
+    }
+
+    /// Writes `data` and blocks until the last character left the UART.
+    pub fn write_all(&self, mut data: &[u8]) -> io::Result<()> {
+        while !data.is_empty() {
+            let result =
+                unsafe { libc::write(self.fd.as_raw_fd(), data.as_ptr().cast(), data.len()) };
+            if result < 0 {
+                let error = io::Error::last_os_error();
+                match error.kind() {
+                    io::ErrorKind::WouldBlock => self.wait_writable()?,
+                    io::ErrorKind::Interrupted => {}
+                    _ => return Err(error),
+                }
+            } else {
+                data = &data[result as usize..];
+            }
+        }
+        self.drain()
+    }
+
+    fn wait_writable(&self) -> io::Result<()> {
+        let mut poll_fd = libc::pollfd {
+            fd: self.fd.as_raw_fd(),
+            events: libc::POLLOUT,
+            revents: 0,
+        };
+        check(unsafe { libc::poll(&mut poll_fd, 1, -1) })
+    }
+
+    fn drain(&self) -> io::Result<()> {
+        check(unsafe { libc::tcdrain(self.fd.as_raw_fd()) })?;
+        if self.discard_echo {
+            self.flush_input();
+        }
+        Ok(())
+    }
+
+    pub fn flush_input(&self) {
+        unsafe {
+            libc::tcflush(self.fd.as_raw_fd(), libc::TCIFLUSH);
+        }
+    }
+}
+
+impl AsRawFd for SerialPort {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+fn check(result: libc::c_int) -> io::Result<()> {
+    if result < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
@@ -0,0 +1,72 @@
+/*********************************************************************
+ * pcap export of bus traffic
+ *
+ * Copyright (C) 2023 Marcel Maage
+ *
+ * This library is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2.1 of the License, or (at your option) any later version.
+ *
+ * This library is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * LICENSE file for more details.
+ */
+
+//! Writes telegrams into a pcap file which can be opened in Wireshark.
+//!
+//! Each packet is one telegram from start to end delimiter. Sources are the
+//! `Codec::monitor` callback, the `telegram_received`/`telegram_sent` hooks
+//! of `DataHandlingInterface` or the simulated buses.
+
+use std::io::{self, Write};
+
+/// PROFIBUS data link layer, IEC 61158-4-3.
+pub const LINKTYPE_PROFIBUS_DL: u32 = 257;
+/// First of the DLTs 147 to 162 reserved for private use, for an own
+/// dissector.
+pub const LINKTYPE_USER0: u32 = 147;
+
+const MAGIC: u32 = 0xA1B2_C3D4; // timestamps in us
+const SNAPLEN: u32 = 65535;
+
+pub struct PcapWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> PcapWriter<W> {
+    /// Writes the file header.
+    pub fn new(mut writer: W, linktype: u32) -> io::Result<Self> {
+        let mut header = [0; 24];
+        header[0..4].copy_from_slice(&MAGIC.to_le_bytes());
+        header[4..6].copy_from_slice(&2u16.to_le_bytes());
+        header[6..8].copy_from_slice(&4u16.to_le_bytes());
+        // thiszone and sigfigs stay 0
+        header[16..20].copy_from_slice(&SNAPLEN.to_le_bytes());
+        header[20..24].copy_from_slice(&linktype.to_le_bytes());
+        writer.write_all(&header)?;
+        Ok(Self { writer })
+    }
+
+    pub fn write_telegram(&mut self, timestamp_us: u64, data: &[u8]) -> io::Result<()> {
+        let len = u32::try_from(data.len()).map_err(|_| io::ErrorKind::InvalidInput)?;
+        let seconds = u32::try_from(timestamp_us / 1_000_000).unwrap_or(u32::MAX);
+        let micros = (timestamp_us % 1_000_000) as u32;
+        let mut header = [0; 16];
+        header[0..4].copy_from_slice(&seconds.to_le_bytes());
+        header[4..8].copy_from_slice(&micros.to_le_bytes());
+        header[8..12].copy_from_slice(&len.to_le_bytes());
+        header[12..16].copy_from_slice(&len.to_le_bytes());
+        self.writer.write_all(&header)?;
+        self.writer.write_all(data)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
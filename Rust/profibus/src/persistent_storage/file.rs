@@ -0,0 +1,68 @@
+/*********************************************************************
+ * File storage
+ *
+ * Copyright (C) 2023 Marcel Maage
+ *
+ * This library is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2.1 of the License, or (at your option) any later version.
+ *
+ * This library is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * LICENSE file for more details.
+ */
+
+//! Keeps every value in a file of a directory, for slaves running on a
+//! host and for tests.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::{PersistentStorage, StorageError, StorageKey, MAX_VALUE_LEN};
+
+pub struct FileStorage {
+    dir: PathBuf,
+}
+
+impl FileStorage {
+    /// Storage in `dir`, which is created if missing.
+    pub fn new<P: AsRef<Path>>(dir: P) -> io::Result<Self> {
+        fs::create_dir_all(dir.as_ref())?;
+        Ok(Self {
+            dir: dir.as_ref().to_path_buf(),
+        })
+    }
+
+    pub fn path(&self, key: StorageKey) -> PathBuf {
+        let name = match key {
+            StorageKey::Address => "address".to_string(),
+            StorageKey::Prm => "prm".to_string(),
+            StorageKey::Cfg => "cfg".to_string(),
+            StorageKey::Im(_) => format!("im{}", key.id() - StorageKey::Im(0).id()),
+        };
+        self.dir.join(name)
+    }
+}
+
+impl PersistentStorage for FileStorage {
+    fn load(&mut self, key: StorageKey, data: &mut [u8]) -> Option<usize> {
+        let value = fs::read(self.path(key)).ok()?;
+        data.get_mut(..value.len())?.copy_from_slice(&value);
+        Some(value.len())
+    }
+
+    fn store(&mut self, key: StorageKey, data: &[u8]) -> Result<(), StorageError> {
+        if data.len() > MAX_VALUE_LEN {
+            return Err(StorageError::TooLong);
+        }
+        // a reset during the write leaves the old file
+        let path = self.path(key);
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, data)
+            .and_then(|()| fs::rename(&tmp, &path))
+            .map_err(|_| StorageError::WriteFailed)
+    }
+}
@@ -0,0 +1,288 @@
+/*********************************************************************
+ * Flash storage
+ *
+ * Copyright (C) 2023 Marcel Maage
+ *
+ * This library is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2.1 of the License, or (at your option) any later version.
+ *
+ * This library is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * LICENSE file for more details.
+ */
+
+//! EEPROM emulation in flash pages, as on the STM32F1 which only erases
+//! whole pages and writes half-words.
+//!
+//! A page starts with a sequence number and a marker written once the page
+//! is complete. Values are appended as records: key, length, data padded to
+//! a half-word and a checksum, so a record torn by a reset is ignored. When
+//! the page is full the newest records are copied to the next page, which
+//! gets the next sequence number. The pages are used in turn, each one is
+//! erased once per round.
+
+use super::{PersistentStorage, StorageError, StorageKey, MAX_VALUE_LEN};
+
+// Sequence number, marker
+const PAGE_HEADER_SIZE: usize = 4;
+// Key and length before the data, checksum after
+const RECORD_OVERHEAD: usize = 4;
+const ERASED: u16 = 0xFFFF;
+const PAGE_VALID: u16 = 0x0000;
+
+/// Flash pages reserved for the storage. Erased bytes read as 0xFF and an
+/// erased half-word can be written once.
+pub trait FlashInterface {
+    /// Bytes of one page, a multiple of two.
+    fn page_size(&self) -> usize;
+
+    /// At least two pages are needed to copy the values.
+    fn page_count(&self) -> usize;
+
+    fn erase_page(&mut self, page: usize) -> Result<(), StorageError>;
+
+    /// Writes `data` of even length to an erased area at an even `offset`.
+    fn write(&mut self, page: usize, offset: usize, data: &[u8]) -> Result<(), StorageError>;
+
+    fn read(&mut self, page: usize, offset: usize, data: &mut [u8]);
+}
+
+struct Record {
+    key_id: u8,
+    len: usize,
+    // offset of the data within the page
+    data_offset: usize,
+    valid: bool,
+}
+
+impl Record {
+    fn size(&self) -> usize {
+        record_size(self.len)
+    }
+}
+
+fn record_size(len: usize) -> usize {
+    RECORD_OVERHEAD + len + (len % 2)
+}
+
+// Fletcher-16, never 0xFFFF so an unwritten checksum is no match
+fn checksum(key_id: u8, data: &[u8]) -> u16 {
+    let mut sum1: u16 = 0;
+    let mut sum2: u16 = 0;
+    for &byte in [key_id, data.len() as u8].iter().chain(data) {
+        sum1 = (sum1 + u16::from(byte)) % 255;
+        sum2 = (sum2 + sum1) % 255;
+    }
+    (sum2 << 8) | sum1
+}
+
+// `a` was written after `b`, the sequence number wraps
+fn is_newer(a: u16, b: u16) -> bool {
+    a != b && a.wrapping_sub(b) < 0x8000
+}
+
+/// `PersistentStorage` in flash pages, see the module description.
+pub struct FlashStorage<Flash> {
+    flash: Flash,
+    // page with the newest values and its sequence number
+    active: Option<(usize, u16)>,
+    // first free byte of the active page
+    write_offset: usize,
+}
+
+impl<Flash: FlashInterface> FlashStorage<Flash> {
+    /// Looks for the page written last. Without one, the first value erases
+    /// the first page.
+    pub fn new(mut flash: Flash) -> Self {
+        let mut active: Option<(usize, u16)> = None;
+        for page in 0..flash.page_count() {
+            let (sequence, marker) = Self::read_page_header(&mut flash, page);
+            if sequence == ERASED || marker != PAGE_VALID {
+                continue;
+            }
+            let newest = match active {
+                Some((_, newest)) => is_newer(sequence, newest),
+                None => true,
+            };
+            if newest {
+                active = Some((page, sequence));
+            }
+        }
+        let mut storage = Self {
+            flash,
+            active,
+            write_offset: 0,
+        };
+        if let Some((page, _)) = active {
+            let mut offset = PAGE_HEADER_SIZE;
+            while let Some(record) = storage.read_record(page, offset) {
+                offset += record.size();
+            }
+            storage.write_offset = offset;
+        }
+        storage
+    }
+
+    pub fn release(self) -> Flash {
+        self.flash
+    }
+
+    fn read_page_header(flash: &mut Flash, page: usize) -> (u16, u16) {
+        let mut header = [0; PAGE_HEADER_SIZE];
+        flash.read(page, 0, &mut header);
+        (
+            u16::from_le_bytes([header[0], header[1]]),
+            u16::from_le_bytes([header[2], header[3]]),
+        )
+    }
+
+    /// Record at `offset`, `None` behind the last one.
+    fn read_record(&mut self, page: usize, offset: usize) -> Option<Record> {
+        let page_size = self.flash.page_size();
+        if offset + RECORD_OVERHEAD > page_size {
+            return None;
+        }
+        let mut header = [0; 2];
+        self.flash.read(page, offset, &mut header);
+        if u16::from_le_bytes(header) == ERASED {
+            return None;
+        }
+        let mut record = Record {
+            key_id: header[0],
+            len: usize::from(header[1]),
+            data_offset: offset + 2,
+            valid: false,
+        };
+        if offset + record.size() > page_size {
+            return None;
+        }
+        let mut data = [0; MAX_VALUE_LEN];
+        self.flash
+            .read(page, record.data_offset, &mut data[..record.len]);
+        let mut check = [0; 2];
+        self.flash
+            .read(page, offset + record.size() - 2, &mut check);
+        record.valid = u16::from_le_bytes(check) == checksum(record.key_id, &data[..record.len]);
+        Some(record)
+    }
+
+    /// Newest valid record of `key_id` on `page`.
+    fn find(&mut self, page: usize, key_id: u8) -> Option<Record> {
+        let mut offset = PAGE_HEADER_SIZE;
+        let mut found = None;
+        while let Some(record) = self.read_record(page, offset) {
+            offset += record.size();
+            if record.valid && record.key_id == key_id {
+                found = Some(record);
+            }
+        }
+        found
+    }
+
+    fn write_record(
+        &mut self,
+        page: usize,
+        offset: usize,
+        key_id: u8,
+        data: &[u8],
+    ) -> Result<usize, StorageError> {
+        let size = record_size(data.len());
+        if offset + size > self.flash.page_size() {
+            return Err(StorageError::TooLong);
+        }
+        let mut record = [0xFF; MAX_VALUE_LEN + RECORD_OVERHEAD + 1];
+        record[0] = key_id;
+        record[1] = data.len() as u8;
+        record[2..2 + data.len()].copy_from_slice(data);
+        record[size - 2..size].copy_from_slice(&checksum(key_id, data).to_le_bytes());
+        self.flash.write(page, offset, &record[..size])?;
+        Ok(offset + size)
+    }
+
+    /// Copies the newest records and `data` of `key_id` to the next page.
+    fn transfer(&mut self, key_id: u8, data: &[u8]) -> Result<(), StorageError> {
+        let page_count = self.flash.page_count();
+        let (page, sequence) = match self.active {
+            Some((page, sequence)) => {
+                if page_count < 2 {
+                    return Err(StorageError::TooLong);
+                }
+                let sequence = sequence.wrapping_add(1);
+                (
+                    (page + 1) % page_count,
+                    if sequence == ERASED { 0 } else { sequence },
+                )
+            }
+            None => (0, 0),
+        };
+        self.flash.erase_page(page)?;
+        self.flash.write(page, 0, &sequence.to_le_bytes())?;
+        let mut offset = PAGE_HEADER_SIZE;
+
+        if let Some((old_page, _)) = self.active {
+            // keys of the old page, each one is copied once
+            let mut keys = [0u8; 32];
+            let mut scan = PAGE_HEADER_SIZE;
+            while let Some(record) = self.read_record(old_page, scan) {
+                scan += record.size();
+                if record.valid && record.key_id != key_id {
+                    keys[usize::from(record.key_id / 8)] |= 1 << (record.key_id % 8);
+                }
+            }
+            for key in 0..=u8::MAX {
+                if (keys[usize::from(key / 8)] & (1 << (key % 8))) == 0 {
+                    continue;
+                }
+                if let Some(record) = self.find(old_page, key) {
+                    let mut value = [0; MAX_VALUE_LEN];
+                    self.flash
+                        .read(old_page, record.data_offset, &mut value[..record.len]);
+                    offset = self.write_record(page, offset, key, &value[..record.len])?;
+                }
+            }
+        }
+        offset = self.write_record(page, offset, key_id, data)?;
+
+        // from now on the new page is used
+        self.flash.write(page, 2, &PAGE_VALID.to_le_bytes())?;
+        self.active = Some((page, sequence));
+        self.write_offset = offset;
+        Ok(())
+    }
+}
+
+impl<Flash: FlashInterface> PersistentStorage for FlashStorage<Flash> {
+    fn load(&mut self, key: StorageKey, data: &mut [u8]) -> Option<usize> {
+        let (page, _) = self.active?;
+        let record = self.find(page, key.id())?;
+        let value = data.get_mut(..record.len)?;
+        self.flash.read(page, record.data_offset, value);
+        Some(record.len)
+    }
+
+    fn store(&mut self, key: StorageKey, data: &[u8]) -> Result<(), StorageError> {
+        if data.len() > MAX_VALUE_LEN {
+            return Err(StorageError::TooLong);
+        }
+        if let Some((page, _)) = self.active {
+            if self.write_offset + record_size(data.len()) <= self.flash.page_size() {
+                let offset = self.write_offset;
+                return match self.write_record(page, offset, key.id(), data) {
+                    Ok(offset) => {
+                        self.write_offset = offset;
+                        Ok(())
+                    }
+                    Err(error) => {
+                        // the record may be half written, continue on the next page
+                        self.write_offset = self.flash.page_size();
+                        Err(error)
+                    }
+                };
+            }
+        }
+        self.transfer(key.id(), data)
+    }
+}
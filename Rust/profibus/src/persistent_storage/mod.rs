@@ -0,0 +1,121 @@
+/*********************************************************************
+ * PersistentStorage
+ *
+ * Copyright (C) 2023 Marcel Maage
+ *
+ * This library is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2.1 of the License, or (at your option) any later version.
+ *
+ * This library is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * LICENSE file for more details.
+ */
+
+//! Non-volatile storage of the values a slave keeps over a reset.
+//!
+//! Every value is a record of up to `MAX_VALUE_LEN` bytes under a
+//! `StorageKey`. `flash` keeps them in pages of an emulated EEPROM, e.g. the
+//! internal flash of the STM32F1, `file` in a directory on the host.
+
+#[cfg(feature = "std")]
+pub use file::FileStorage;
+pub use flash::{FlashInterface, FlashStorage};
+
+#[cfg(feature = "std")]
+pub mod file;
+pub mod flash;
+
+/// Longest value, a record length has eight bits.
+pub const MAX_VALUE_LEN: usize = 255;
+/// I&M records 0..=15 of the slave.
+pub const IM_RECORD_COUNT: u8 = 16;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum StorageKey {
+    /// Station address and No_Add_Chg of the last Set_Slave_Address
+    Address,
+    /// Parameter data of the last accepted Set_Prm, kept by the application
+    Prm,
+    /// Module config of the last accepted Chk_Cfg, kept by the application
+    Cfg,
+    /// I&M record 0..=15, kept by the application's record handling
+    Im(u8),
+}
+
+impl StorageKey {
+    /// Number of the key within the storage.
+    pub fn id(self) -> u8 {
+        match self {
+            StorageKey::Address => 1,
+            StorageKey::Prm => 2,
+            StorageKey::Cfg => 3,
+            StorageKey::Im(record) => 0x10 + (record % IM_RECORD_COUNT),
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            1 => Some(StorageKey::Address),
+            2 => Some(StorageKey::Prm),
+            3 => Some(StorageKey::Cfg),
+            0x10..=0x1F => Some(StorageKey::Im(id - 0x10)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum StorageError {
+    /// The value could not be written, the old one is still valid.
+    WriteFailed,
+    /// The value is longer than `MAX_VALUE_LEN` or the storage is too
+    /// small to keep it.
+    TooLong,
+}
+
+/// Values the slave keeps over a reset. Handed to the slave by
+/// `DataHandlingInterface::persistent_storage`.
+///
+/// The slave stores the station address when the application calls
+/// `PbDpSlave::store_pending`, never while it answers a telegram. The master
+/// sends Set_Prm and Chk_Cfg again after every reset, so the slave does not
+/// keep them; they and the I&M records are left to the application.
+pub trait PersistentStorage {
+    /// Copies the value of `key` into `data` and returns its length. `None`
+    /// if there is no value or it does not fit into `data`.
+    fn load(&mut self, key: StorageKey, data: &mut [u8]) -> Option<usize>;
+
+    fn store(&mut self, key: StorageKey, data: &[u8]) -> Result<(), StorageError>;
+
+    /// Station address and No_Add_Chg of the last accepted
+    /// Set_Slave_Address, `None` if the address was never set.
+    fn load_address(&mut self) -> Option<(u8, bool)> {
+        let mut data = [0; 2];
+        match self.load(StorageKey::Address, &mut data) {
+            Some(2) => Some((data[0], data[1] != 0)),
+            _ => None,
+        }
+    }
+
+    fn store_address(&mut self, address: u8, no_add_chg: bool) -> Result<(), StorageError> {
+        self.store(StorageKey::Address, &address_value(address, no_add_chg))
+    }
+
+    /// Stores `data` unless the same value is stored already, which saves
+    /// the flash from a write on every startup of the master.
+    fn store_changed(&mut self, key: StorageKey, data: &[u8]) -> Result<(), StorageError> {
+        let mut stored = [0; MAX_VALUE_LEN];
+        match self.load(key, &mut stored) {
+            Some(len) if stored[..len] == *data => Ok(()),
+            _ => self.store(key, data),
+        }
+    }
+}
+
+/// Value of `StorageKey::Address`.
+pub(crate) fn address_value(address: u8, no_add_chg: bool) -> [u8; 2] {
+    [address, u8::from(no_add_chg)]
+}
@@ -0,0 +1,85 @@
+/*********************************************************************
+ * RecordHandlingInterface
+ *
+ * Copyright (C) 2023 Marcel Maage
+ *
+ * This library is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2.1 of the License, or (at your option) any later version.
+ *
+ * This library is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * LICENSE file for more details.
+ */
+
+use crate::types::dpv1_error;
+
+/// Longest data record of a DPV1 Read or Write.
+pub const MAX_RECORD_LEN: usize = 240;
+
+/// Negative answer to a record access, sent to the master as Error_Code_1
+/// with DPV1 Error_Decode.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum RecordError {
+    InvalidIndex,
+    WriteLength,
+    InvalidSlot,
+    StateConflict,
+    AccessDenied,
+    InvalidRange,
+    InvalidParameter,
+    Busy,
+    FeatureNotSupported,
+    /// Any other Error_Code_1 and the vendor specific Error_Code_2.
+    Other {
+        error_code_1: u8,
+        error_code_2: u8,
+    },
+}
+
+impl RecordError {
+    /// Error_Code_1 and Error_Code_2
+    pub fn codes(self) -> (u8, u8) {
+        let error_code_1 = match self {
+            RecordError::InvalidIndex => dpv1_error::INVALID_INDEX,
+            RecordError::WriteLength => dpv1_error::WRITE_LENGTH_ERROR,
+            RecordError::InvalidSlot => dpv1_error::INVALID_SLOT,
+            RecordError::StateConflict => dpv1_error::STATE_CONFLICT,
+            RecordError::AccessDenied => dpv1_error::ACCESS_DENIED,
+            RecordError::InvalidRange => dpv1_error::INVALID_RANGE,
+            RecordError::InvalidParameter => dpv1_error::INVALID_PARAMETER,
+            RecordError::Busy => dpv1_error::RESOURCE_BUSY,
+            RecordError::FeatureNotSupported => dpv1_error::FEATURE_NOT_SUPPORTED,
+            RecordError::Other {
+                error_code_1,
+                error_code_2,
+            } => return (error_code_1, error_code_2),
+        };
+        (error_code_1, 0)
+    }
+}
+
+/// Data records of the slave, read and written by acyclic DPV1 services.
+/// Handed to the slave by `DataHandlingInterface::record_handling`.
+pub trait RecordHandlingInterface {
+    /// Copies record `index` of `slot` into `data`, which is as long as the
+    /// master asked for, and returns the number of bytes copied.
+    fn read_record(&mut self, slot: u8, index: u8, data: &mut [u8]) -> Result<usize, RecordError>;
+
+    fn write_record(&mut self, slot: u8, index: u8, data: &[u8]) -> Result<(), RecordError>;
+
+    /// Data_Transport of a class 2 master: takes `request` and writes the
+    /// answer into `response`, returning its length. Not supported unless
+    /// implemented.
+    fn data_transport(
+        &mut self,
+        _slot: u8,
+        _index: u8,
+        _request: &[u8],
+        _response: &mut [u8],
+    ) -> Result<usize, RecordError> {
+        Err(RecordError::FeatureNotSupported)
+    }
+}
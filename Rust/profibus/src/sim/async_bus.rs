@@ -0,0 +1,224 @@
+/*********************************************************************
+ * Simulated bus for CodecHwInterface
+ *
+ * Copyright (C) 2023 Marcel Maage
+ *
+ * This library is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2.1 of the License, or (at your option) any later version.
+ *
+ * This library is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * LICENSE file for more details.
+ */
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::future::{poll_fn, Future};
+use std::pin::pin;
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+use std::vec::Vec;
+
+use crate::device::CodecHwInterface;
+
+const BITS_PER_BYTE: u64 = 11; // start + 8 data + parity + stop
+
+struct Frame {
+    start_ns: u64,
+    data: Vec<u8>,
+}
+
+#[derive(Default)]
+struct AsyncBusState {
+    baudrate: u32,
+    now_ns: u64,
+
+    rx_enabled: bool,
+    rx_enabled_since_ns: u64,
+    rx_pending: VecDeque<Frame>,
+    rx_waker: Option<Waker>,
+    rx_end_ns: u64,
+
+    tx_enabled: bool,
+    transmitted: Vec<(u64, Vec<u8>)>,
+}
+
+impl AsyncBusState {
+    fn byte_time_ns(&self) -> u64 {
+        (BITS_PER_BYTE * 1_000_000_000) / u64::from(self.baudrate)
+    }
+
+    fn bus_free_ns(&self) -> u64 {
+        let last_rx = self
+            .rx_pending
+            .back()
+            .map(|frame| frame.start_ns + frame.data.len() as u64 * self.byte_time_ns());
+        last_rx.unwrap_or(self.now_ns).max(self.now_ns)
+    }
+}
+
+/// In-memory Profibus segment with a virtual clock for the async stack.
+///
+/// `receive_uart_data` behaves like `read_until_idle`: it returns the next
+/// telegram sent with `send`/`send_after_us` and moves the clock to its end.
+/// Bytes that were on the bus while the receiver was switched off are lost.
+/// `wait_for` and `send_uart_data` advance the clock by the waited time and
+/// the transmission time, so a test can check the timing the codec keeps.
+#[derive(Clone)]
+pub struct AsyncSimBus {
+    state: Rc<RefCell<AsyncBusState>>,
+}
+
+impl AsyncSimBus {
+    pub fn new(baudrate: u32) -> Self {
+        Self {
+            state: Rc::new(RefCell::new(AsyncBusState {
+                baudrate,
+                ..AsyncBusState::default()
+            })),
+        }
+    }
+
+    pub fn interface(&self) -> AsyncSimHwInterface {
+        AsyncSimHwInterface {
+            state: self.state.clone(),
+        }
+    }
+
+    pub fn now_ns(&self) -> u64 {
+        self.state.borrow().now_ns
+    }
+
+    /// Duration of one UART character (11 bit) on this bus.
+    pub fn byte_time_ns(&self) -> u64 {
+        self.state.borrow().byte_time_ns()
+    }
+
+    /// Puts a telegram on the bus directly after the previous one or now.
+    pub fn send(&self, data: &[u8]) {
+        self.send_after_us(0, data);
+    }
+
+    /// Puts a telegram on the bus `idle_us` after the previous one or now.
+    pub fn send_after_us(&self, idle_us: u32, data: &[u8]) {
+        let mut state = self.state.borrow_mut();
+        let start_ns = state.bus_free_ns() + u64::from(idle_us) * 1000;
+        state.rx_pending.push_back(Frame {
+            start_ns,
+            data: data.to_vec(),
+        });
+        if let Some(waker) = state.rx_waker.take() {
+            waker.wake();
+        }
+    }
+
+    /// Returns and clears the telegrams the station transmitted, each with
+    /// the time in ns at which its first byte started.
+    pub fn take_transmitted_with_time(&self) -> Vec<(u64, Vec<u8>)> {
+        core::mem::take(&mut self.state.borrow_mut().transmitted)
+    }
+
+    pub fn take_transmitted(&self) -> Vec<Vec<u8>> {
+        self.take_transmitted_with_time()
+            .into_iter()
+            .map(|(_, data)| data)
+            .collect()
+    }
+
+    /// Number of telegrams not yet picked up by the station.
+    pub fn pending(&self) -> usize {
+        self.state.borrow().rx_pending.len()
+    }
+}
+
+/// Polls `future` on the current thread until it completes or cannot make
+/// progress anymore, which is the case once it waits for a telegram that was
+/// not sent yet. All other futures of the simulated bus are ready at once.
+pub fn run_until_stalled<F: Future>(future: F) -> Option<F::Output> {
+    let mut future = pin!(future);
+    let mut context = Context::from_waker(Waker::noop());
+    match future.as_mut().poll(&mut context) {
+        Poll::Ready(output) => Some(output),
+        Poll::Pending => None,
+    }
+}
+
+/// `CodecHwInterface` implementation of one station on an `AsyncSimBus`.
+pub struct AsyncSimHwInterface {
+    state: Rc<RefCell<AsyncBusState>>,
+}
+
+impl CodecHwInterface for AsyncSimHwInterface {
+    async fn wait_for(&mut self, time_in_us: u32) {
+        self.state.borrow_mut().now_ns += u64::from(time_in_us) * 1000;
+    }
+
+    fn tx_rs485_enable(&mut self) {
+        self.state.borrow_mut().tx_enabled = true;
+    }
+
+    fn tx_rs485_disable(&mut self) {
+        self.state.borrow_mut().tx_enabled = false;
+    }
+
+    fn rx_rs485_enable(&mut self) {
+        let mut state = self.state.borrow_mut();
+        if !state.rx_enabled {
+            state.rx_enabled = true;
+            state.rx_enabled_since_ns = state.now_ns;
+        }
+        state.tx_enabled = false;
+    }
+
+    fn rx_rs485_disable(&mut self) {
+        self.state.borrow_mut().rx_enabled = false;
+    }
+
+    async fn send_uart_data(&mut self, value: &[u8]) {
+        let mut state = self.state.borrow_mut();
+        let now = state.now_ns;
+        state.transmitted.push((now, value.to_vec()));
+        state.now_ns += value.len() as u64 * state.byte_time_ns();
+    }
+
+    async fn receive_uart_data(&mut self, value: &mut [u8], len: &mut usize) {
+        *len = poll_fn(|context| {
+            let mut state = self.state.borrow_mut();
+            let byte_time = state.byte_time_ns();
+            while let Some(frame) = state.rx_pending.pop_front() {
+                // drop the bytes which were complete before the receiver was on
+                let first = frame
+                    .data
+                    .iter()
+                    .enumerate()
+                    .position(|(i, _)| {
+                        frame.start_ns + (i as u64 + 1) * byte_time > state.rx_enabled_since_ns
+                    })
+                    .unwrap_or(frame.data.len());
+                let end_ns = frame.start_ns + frame.data.len() as u64 * byte_time;
+                state.now_ns = state.now_ns.max(end_ns);
+                if first < frame.data.len() {
+                    state.rx_end_ns = end_ns;
+                    let data = &frame.data[first..];
+                    let len = data.len().min(value.len());
+                    value[..len].copy_from_slice(&data[..len]);
+                    return Poll::Ready(len);
+                }
+            }
+            state.rx_waker = Some(context.waker().clone());
+            Poll::Pending
+        })
+        .await;
+    }
+
+    fn rx_timestamp_us(&self) -> u64 {
+        self.state.borrow().rx_end_ns / 1000
+    }
+
+    fn get_baudrate(&self) -> u32 {
+        self.state.borrow().baudrate
+    }
+}
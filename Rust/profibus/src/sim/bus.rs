@@ -0,0 +1,432 @@
+/*********************************************************************
+ * Simulated bus for HwInterface
+ *
+ * Copyright (C) 2023 Marcel Maage
+ *
+ * This library is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2.1 of the License, or (at your option) any later version.
+ *
+ * This library is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * LICENSE file for more details.
+ */
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+use std::vec::Vec;
+
+use crate::data_handling_interface::DataHandlingInterface;
+use crate::slave::{HwInterface, PbDpSlave};
+
+const TIMER_FREQUENCY: u32 = 1_000_000;
+const BITS_PER_BYTE: u64 = 11; // start + 8 data + parity + stop
+
+/// Interrupt entry points of an interrupt driven slave, called by `SimBus`
+/// the same way the UART and timer interrupts call them on the target.
+pub trait InterruptTarget {
+    fn serial_interrupt(&mut self);
+
+    fn timer_interrupt(&mut self);
+
+    fn receive_handling(&mut self);
+}
+
+impl<
+        Serial,
+        DataHandling,
+        const BUF_SIZE: usize,
+        const INPUT_DATA_SIZE: usize,
+        const OUTPUT_DATA_SIZE: usize,
+        const USER_PARA_SIZE: usize,
+        const EXTERN_DIAG_PARA_SIZE: usize,
+        const MODULE_CONFIG_SIZE: usize,
+    > InterruptTarget
+    for PbDpSlave<
+        Serial,
+        DataHandling,
+        BUF_SIZE,
+        INPUT_DATA_SIZE,
+        OUTPUT_DATA_SIZE,
+        USER_PARA_SIZE,
+        EXTERN_DIAG_PARA_SIZE,
+        MODULE_CONFIG_SIZE,
+    >
+where
+    Serial: HwInterface,
+    DataHandling: DataHandlingInterface,
+{
+    fn serial_interrupt(&mut self) {
+        self.serial_interrupt_handler();
+    }
+
+    fn timer_interrupt(&mut self) {
+        self.timer_interrupt_handler();
+    }
+
+    fn receive_handling(&mut self) {
+        self.handle_codec_data();
+    }
+}
+
+#[derive(Default)]
+struct BusState {
+    baudrate: u32,
+    now_ns: u64,
+
+    timer_deadline_ns: Option<u64>,
+
+    // bytes sent by the remote side, with the time their stop bit is on the bus
+    rx_pending: VecDeque<(u64, u8)>,
+    rx_fifo: VecDeque<u8>,
+    rx_interrupt: bool,
+    rx_event: bool,
+
+    tx_pending: VecDeque<u8>,
+    tx_done_ns: Option<u64>,
+    tx_interrupt: bool,
+    tx_event: bool,
+    tx_enabled: bool,
+    transmitted: Vec<(u64, u8)>,
+
+    receive_handling_scheduled: bool,
+}
+
+impl BusState {
+    fn byte_time_ns(&self) -> u64 {
+        (BITS_PER_BYTE * 1_000_000_000) / u64::from(self.baudrate)
+    }
+
+    fn ticks_to_ns(ticks: u32) -> u64 {
+        (u64::from(ticks) * 1_000_000_000) / u64::from(TIMER_FREQUENCY)
+    }
+
+    fn start_next_tx_byte(&mut self) {
+        if let Some(value) = self.tx_pending.pop_front() {
+            let done =
+                self.tx_done_ns.unwrap_or(self.now_ns).max(self.now_ns) + self.byte_time_ns();
+            self.transmitted.push((done, value));
+            self.tx_done_ns = Some(done);
+        }
+    }
+}
+
+/// In-memory Profibus segment with a virtual clock.
+///
+/// The bus hands out a `SimHwInterface` for the slave under test and keeps a
+/// handle on the shared state, so a test can feed master telegrams, advance
+/// time and inspect everything the slave put on the wire. Time only moves in
+/// `run_for_ns`/`run_until_ns`, which deliver UART and timer interrupts in
+/// chronological order.
+#[derive(Clone)]
+pub struct SimBus {
+    state: Rc<RefCell<BusState>>,
+}
+
+impl SimBus {
+    pub fn new(baudrate: u32) -> Self {
+        Self {
+            state: Rc::new(RefCell::new(BusState {
+                baudrate,
+                ..BusState::default()
+            })),
+        }
+    }
+
+    pub fn interface(&self) -> SimHwInterface {
+        SimHwInterface {
+            state: self.state.clone(),
+        }
+    }
+
+    pub fn now_ns(&self) -> u64 {
+        self.state.borrow().now_ns
+    }
+
+    pub fn baudrate(&self) -> u32 {
+        self.state.borrow().baudrate
+    }
+
+    /// Duration of one UART character (11 bit) on this bus.
+    pub fn byte_time_ns(&self) -> u64 {
+        self.state.borrow().byte_time_ns()
+    }
+
+    /// Puts `data` on the bus as seen by the slave. The bytes follow each
+    /// other without gap, starting now or after bytes sent earlier.
+    pub fn send(&self, data: &[u8]) {
+        let mut state = self.state.borrow_mut();
+        let byte_time = state.byte_time_ns();
+        let mut time = state
+            .rx_pending
+            .back()
+            .map(|(time, _)| *time)
+            .unwrap_or(state.now_ns)
+            .max(state.now_ns);
+        for value in data {
+            time += byte_time;
+            state.rx_pending.push_back((time, *value));
+        }
+    }
+
+    /// Returns and clears everything the slave transmitted so far.
+    pub fn take_transmitted(&self) -> Vec<u8> {
+        self.take_transmitted_with_time()
+            .into_iter()
+            .map(|(_, value)| value)
+            .collect()
+    }
+
+    /// Like `take_transmitted`, but every byte comes with the time in ns at
+    /// which its stop bit left the UART.
+    pub fn take_transmitted_with_time(&self) -> Vec<(u64, u8)> {
+        let mut state = self.state.borrow_mut();
+        let now = state.now_ns;
+        let count = state
+            .transmitted
+            .iter()
+            .take_while(|(time, _)| *time <= now)
+            .count();
+        state.transmitted.drain(..count).collect()
+    }
+
+    pub fn is_transmitting(&self) -> bool {
+        let state = self.state.borrow();
+        state.tx_enabled || state.tx_done_ns.is_some() || !state.tx_pending.is_empty()
+    }
+
+    pub fn run_for_ns<Target: InterruptTarget>(&self, target: &mut Target, duration_ns: u64) {
+        let end = self.now_ns() + duration_ns;
+        self.run_until_ns(target, end);
+    }
+
+    pub fn run_until_ns<Target: InterruptTarget>(&self, target: &mut Target, end_ns: u64) {
+        while let Some(time) = self.next_event_ns() {
+            if time > end_ns {
+                break;
+            }
+            self.state.borrow_mut().now_ns = time;
+            self.dispatch(target);
+        }
+        let mut state = self.state.borrow_mut();
+        state.now_ns = state.now_ns.max(end_ns);
+    }
+
+    /// Sends `request` and runs the bus until the slave answered and went
+    /// back to receive mode or `timeout_ns` passed without an answer
+    /// starting. Returns the answer, which is empty if the slave stayed
+    /// silent.
+    pub fn request<Target: InterruptTarget>(
+        &self,
+        target: &mut Target,
+        request: &[u8],
+        timeout_ns: u64,
+    ) -> Vec<u8> {
+        self.request_with_time(target, request, timeout_ns)
+            .into_iter()
+            .map(|(_, value)| value)
+            .collect()
+    }
+
+    /// Like `request`, but every byte of the answer comes with the time in
+    /// ns at which its stop bit left the UART.
+    pub fn request_with_time<Target: InterruptTarget>(
+        &self,
+        target: &mut Target,
+        request: &[u8],
+        timeout_ns: u64,
+    ) -> Vec<(u64, u8)> {
+        self.send(request);
+        let end = self.now_ns() + timeout_ns;
+        let mut response = Vec::new();
+        while let Some(time) = self.next_event_ns() {
+            // the timeout only limits the start of the answer
+            if time > end && response.is_empty() && !self.is_transmitting() {
+                break;
+            }
+            self.state.borrow_mut().now_ns = time;
+            self.dispatch(target);
+            response.extend(self.take_transmitted_with_time());
+            if !response.is_empty() && !self.is_transmitting() {
+                break;
+            }
+        }
+        response
+    }
+
+    fn next_event_ns(&self) -> Option<u64> {
+        let state = self.state.borrow();
+        let mut next: Option<u64> = None;
+        let mut consider = |time: Option<u64>| {
+            if let Some(time) = time {
+                next = Some(next.map_or(time, |next| next.min(time)));
+            }
+        };
+        if state.receive_handling_scheduled || state.rx_event || state.tx_event {
+            consider(Some(state.now_ns));
+        }
+        consider(state.rx_pending.front().map(|(time, _)| *time));
+        consider(state.timer_deadline_ns);
+        consider(state.tx_done_ns);
+        next
+    }
+
+    fn dispatch<Target: InterruptTarget>(&self, target: &mut Target) {
+        let now = self.now_ns();
+        {
+            let mut state = self.state.borrow_mut();
+            while let Some((time, value)) = state.rx_pending.front().copied() {
+                if time > now {
+                    break;
+                }
+                state.rx_pending.pop_front();
+                if !state.tx_enabled {
+                    state.rx_fifo.push_back(value);
+                    state.rx_event = true;
+                }
+            }
+            if state.tx_done_ns.is_some_and(|time| time <= now) {
+                state.tx_done_ns = None;
+                if state.tx_pending.is_empty() {
+                    state.tx_event = true;
+                } else {
+                    state.start_next_tx_byte();
+                }
+            }
+        }
+
+        let (rx, tx) = {
+            let mut state = self.state.borrow_mut();
+            let rx = state.rx_event && state.rx_interrupt && !state.rx_fifo.is_empty();
+            let tx = state.tx_event && state.tx_interrupt;
+            state.rx_event &= !rx;
+            state.tx_event = false;
+            (rx, tx)
+        };
+        if rx || tx {
+            target.serial_interrupt();
+        }
+
+        let timer_expired = {
+            let mut state = self.state.borrow_mut();
+            if state.timer_deadline_ns.is_some_and(|time| time <= now) {
+                state.timer_deadline_ns = None;
+                true
+            } else {
+                false
+            }
+        };
+        if timer_expired {
+            target.timer_interrupt();
+        }
+
+        let receive_handling = {
+            let mut state = self.state.borrow_mut();
+            core::mem::take(&mut state.receive_handling_scheduled)
+        };
+        if receive_handling {
+            target.receive_handling();
+        }
+    }
+}
+
+/// `HwInterface` implementation of one station on a `SimBus`.
+pub struct SimHwInterface {
+    state: Rc<RefCell<BusState>>,
+}
+
+impl HwInterface for SimHwInterface {
+    fn run_timer(&mut self, timeout_in_us: u32) {
+        let mut state = self.state.borrow_mut();
+        state.timer_deadline_ns = Some(state.now_ns + BusState::ticks_to_ns(timeout_in_us));
+    }
+
+    fn stop_timer(&mut self) {
+        self.state.borrow_mut().timer_deadline_ns = None;
+    }
+
+    fn activate_tx_interrupt(&mut self) {
+        self.state.borrow_mut().tx_interrupt = true;
+    }
+
+    fn deactivate_tx_interrupt(&mut self) {
+        self.state.borrow_mut().tx_interrupt = false;
+    }
+
+    fn activate_rx_interrupt(&mut self) {
+        let mut state = self.state.borrow_mut();
+        state.rx_interrupt = true;
+        // a byte received while the interrupt was masked is still pending
+        state.rx_event = !state.rx_fifo.is_empty();
+    }
+
+    fn deactivate_rx_interrupt(&mut self) {
+        self.state.borrow_mut().rx_interrupt = false;
+    }
+
+    fn is_rx_received(&mut self) -> bool {
+        !self.state.borrow().rx_fifo.is_empty()
+    }
+
+    fn is_tx_done(&mut self) -> bool {
+        let state = self.state.borrow();
+        state.tx_done_ns.is_none() && state.tx_pending.is_empty()
+    }
+
+    fn tx_rs485_enable(&mut self) {
+        self.state.borrow_mut().tx_enabled = true;
+    }
+
+    fn tx_rs485_disable(&mut self) {
+        self.state.borrow_mut().tx_enabled = false;
+    }
+
+    fn rx_rs485_enable(&mut self) {
+        self.state.borrow_mut().tx_enabled = false;
+    }
+
+    fn get_uart_value(&mut self) -> Option<u8> {
+        self.state.borrow_mut().rx_fifo.pop_front()
+    }
+
+    fn set_uart_value(&mut self, value: u8) {
+        let mut state = self.state.borrow_mut();
+        state.tx_pending.push_back(value);
+        if state.tx_done_ns.is_none() {
+            state.start_next_tx_byte();
+        }
+    }
+
+    fn send_uart_data(&mut self, value: &[u8]) {
+        let mut state = self.state.borrow_mut();
+        state.tx_pending.extend(value.iter().copied());
+        if state.tx_done_ns.is_none() {
+            state.start_next_tx_byte();
+        }
+    }
+
+    fn get_uart_data(&mut self, value: &mut [u8]) {
+        let mut state = self.state.borrow_mut();
+        for slot in value.iter_mut() {
+            match state.rx_fifo.pop_front() {
+                Some(data) => *slot = data,
+                None => break,
+            }
+        }
+    }
+
+    fn schedule_receive_handling(&mut self) {
+        self.state.borrow_mut().receive_handling_scheduled = true;
+    }
+
+    fn get_baudrate(&self) -> u32 {
+        self.state.borrow().baudrate
+    }
+
+    fn get_timer_frequency(&self) -> u32 {
+        TIMER_FREQUENCY
+    }
+}
@@ -0,0 +1,760 @@
+/*********************************************************************
+ * Simulated DP master class 1 and 2
+ *
+ * Copyright (C) 2023 Marcel Maage
+ *
+ * This library is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2.1 of the License, or (at your option) any later version.
+ *
+ * This library is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * LICENSE file for more details.
+ */
+
+use std::vec::Vec;
+
+use super::bus::{InterruptTarget, SimBus};
+use crate::alarm::Alarm;
+use crate::telegram::{FrameError, Telegram, MAX_TELEGRAM_SIZE};
+use crate::types::{
+    dpv1_function, fc_request, fc_request_low_nibble, fc_response, sap, sap_diagnose_byte1,
+    sap_diagnose_byte2, sap_global_control, sap_set_parameter_request,
+};
+
+const MASTER_SAP: u8 = 62;
+// polls for a DPV1 response before giving up
+const DPV1_POLL_LIMIT: usize = 10;
+const MSAC_C2_MASTER_SAP: u8 = 40;
+
+pub struct MasterConfig {
+    master_addr: u8,
+    slave_addr: u8,
+    ident_high: u8,
+    ident_low: u8,
+    watchdog_factor_1: u8,
+    watchdog_factor_2: u8,
+    min_tsdr: u8,
+    group: u8,
+    freeze: bool,
+    sync: bool,
+    user_para: Vec<u8>,
+    module_config: Vec<u8>,
+    slot_time_bits: u32,
+    retry_limit: u8,
+}
+
+impl MasterConfig {
+    pub fn master_addr(mut self, master_addr: u8) -> Self {
+        self.master_addr = master_addr;
+        self
+    }
+
+    pub fn slave_addr(mut self, slave_addr: u8) -> Self {
+        self.slave_addr = slave_addr;
+        self
+    }
+
+    pub fn ident_high(mut self, ident_high: u8) -> Self {
+        self.ident_high = ident_high;
+        self
+    }
+
+    pub fn ident_low(mut self, ident_low: u8) -> Self {
+        self.ident_low = ident_low;
+        self
+    }
+
+    /// Watchdog time is `factor_1 * factor_2 * 10 ms`, 0 switches it off.
+    pub fn watchdog(mut self, factor_1: u8, factor_2: u8) -> Self {
+        self.watchdog_factor_1 = factor_1;
+        self.watchdog_factor_2 = factor_2;
+        self
+    }
+
+    pub fn min_tsdr(mut self, min_tsdr: u8) -> Self {
+        self.min_tsdr = min_tsdr;
+        self
+    }
+
+    pub fn group(mut self, group: u8) -> Self {
+        self.group = group;
+        self
+    }
+
+    pub fn freeze(mut self, freeze: bool) -> Self {
+        self.freeze = freeze;
+        self
+    }
+
+    pub fn sync(mut self, sync: bool) -> Self {
+        self.sync = sync;
+        self
+    }
+
+    pub fn user_para(mut self, user_para: &[u8]) -> Self {
+        self.user_para = user_para.to_vec();
+        self
+    }
+
+    pub fn module_config(mut self, module_config: &[u8]) -> Self {
+        self.module_config = module_config.to_vec();
+        self
+    }
+
+    pub fn slot_time_bits(mut self, slot_time_bits: u32) -> Self {
+        self.slot_time_bits = slot_time_bits;
+        self
+    }
+
+    pub fn retry_limit(mut self, retry_limit: u8) -> Self {
+        self.retry_limit = retry_limit;
+        self
+    }
+}
+
+impl Default for MasterConfig {
+    fn default() -> MasterConfig {
+        MasterConfig {
+            master_addr: 2,
+            slave_addr: 126,
+            ident_high: 0,
+            ident_low: 0,
+            watchdog_factor_1: 0,
+            watchdog_factor_2: 0,
+            min_tsdr: 11,
+            group: 0,
+            freeze: false,
+            sync: false,
+            user_para: Vec::new(),
+            module_config: Vec::new(),
+            slot_time_bits: 1000,
+            retry_limit: 1,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum MasterError {
+    /// The slave did not answer within the slot time, retries included.
+    NoResponse,
+    /// The answer could not be decoded or was not the expected frame type.
+    InvalidResponse(Vec<u8>),
+    /// The slave is not ready for data exchange after the startup sequence.
+    NotReady(SlaveDiagnostic),
+    /// The slave refused the request with a short response, e.g. SAP not
+    /// active or no resources.
+    Rejected { function_code: u8 },
+    /// Negative DPV1 response with Error_Decode, Error_Code_1 and
+    /// Error_Code_2.
+    Dpv1 {
+        error_decode: u8,
+        error_code_1: u8,
+        error_code_2: u8,
+    },
+    /// The request does not fit into a telegram.
+    Frame(FrameError),
+}
+
+/// The six standard diagnostic bytes and the extended diagnostic.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlaveDiagnostic {
+    pub status_1: u8,
+    pub status_2: u8,
+    pub status_3: u8,
+    pub master_addr: u8,
+    pub ident_high: u8,
+    pub ident_low: u8,
+    pub ext_diag: Vec<u8>,
+}
+
+impl SlaveDiagnostic {
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        if data.len() < 6 {
+            return None;
+        }
+        Some(Self {
+            status_1: data[0],
+            status_2: data[1],
+            status_3: data[2],
+            master_addr: data[3],
+            ident_high: data[4],
+            ident_low: data[5],
+            ext_diag: data[6..].to_vec(),
+        })
+    }
+
+    /// DPV1 alarm, reported as the first block of the extended diagnostic.
+    pub fn alarm(&self) -> Option<Alarm> {
+        Alarm::from_block(&self.ext_diag)
+    }
+
+    pub fn is_ready_for_data_exchange(&self) -> bool {
+        let faults = sap_diagnose_byte1::STATION_NOT_EXISTENT
+            | sap_diagnose_byte1::STATION_NOT_READY
+            | sap_diagnose_byte1::CFG_FAULT
+            | sap_diagnose_byte1::PRM_FAULT;
+        (self.status_1 & faults) == 0 && (self.status_2 & sap_diagnose_byte2::PRM_REQ) == 0
+    }
+}
+
+/// Response of a slave as seen by the master.
+#[derive(Debug, PartialEq, Eq)]
+pub enum SlaveResponse {
+    ShortAck,
+    NoData {
+        function_code: u8,
+    },
+    Data {
+        function_code: u8,
+        data: Vec<u8>,
+    },
+    /// Answer with SAPs, `ssap` is the SAP the slave answered from.
+    SapData {
+        function_code: u8,
+        ssap: u8,
+        data: Vec<u8>,
+    },
+}
+
+/// Class 1 master talking to one slave on a `SimBus`.
+///
+/// Performs the startup sequence of a DP master (Get Diag, Set_Prm, Chk_Cfg,
+/// Get Diag) and the cyclic Data_Exchange, including FCB handling and
+/// retries, so a slave can be brought into data exchange without a PLC.
+///
+/// After `initiate` it acts as a class 2 master: record services then run
+/// over the MSAC_C2 connection instead of MSAC_C1.
+pub struct DpMaster {
+    bus: SimBus,
+    config: MasterConfig,
+    fcb: Option<bool>,
+    // SAP of the slave for the MSAC_C2 connection
+    c2_sap: Option<u8>,
+}
+
+impl DpMaster {
+    pub fn new(bus: SimBus, config: MasterConfig) -> Self {
+        Self {
+            bus,
+            config,
+            fcb: None,
+            c2_sap: None,
+        }
+    }
+
+    pub fn config(&self) -> &MasterConfig {
+        &self.config
+    }
+
+    /// Runs the full startup sequence and returns the final diagnostic.
+    pub fn startup<Target: InterruptTarget>(
+        &mut self,
+        slave: &mut Target,
+    ) -> Result<SlaveDiagnostic, MasterError> {
+        self.get_diagnostic(slave)?;
+        self.set_prm(slave)?;
+        self.chk_cfg(slave)?;
+        let diagnostic = self.get_diagnostic(slave)?;
+        if diagnostic.is_ready_for_data_exchange() {
+            Ok(diagnostic)
+        } else {
+            Err(MasterError::NotReady(diagnostic))
+        }
+    }
+
+    pub fn fdl_status<Target: InterruptTarget>(
+        &mut self,
+        slave: &mut Target,
+    ) -> Result<SlaveResponse, MasterError> {
+        let request = encode(&Telegram::Sd1 {
+            destination_addr: self.config.slave_addr,
+            source_addr: self.config.master_addr,
+            function_code: fc_request::REQUEST + fc_request::FDL_STATUS,
+        })?;
+        self.transaction(slave, &request)
+    }
+
+    pub fn get_diagnostic<Target: InterruptTarget>(
+        &mut self,
+        slave: &mut Target,
+    ) -> Result<SlaveDiagnostic, MasterError> {
+        match self.sap_request(slave, sap::SLAVE_DIAGNOSTIC, &[])? {
+            SlaveResponse::SapData { data, .. } => {
+                SlaveDiagnostic::parse(&data).ok_or(MasterError::InvalidResponse(data))
+            }
+            _ => Err(MasterError::InvalidResponse(Vec::new())),
+        }
+    }
+
+    pub fn set_prm<Target: InterruptTarget>(
+        &mut self,
+        slave: &mut Target,
+    ) -> Result<(), MasterError> {
+        let mut station_status = sap_set_parameter_request::LOCK_SLAVE;
+        if self.config.watchdog_factor_1 != 0 && self.config.watchdog_factor_2 != 0 {
+            station_status |= sap_set_parameter_request::ACTIVATE_WATCHDOG;
+        }
+        if self.config.freeze {
+            station_status |= sap_set_parameter_request::ACTIVATE_FREEZE;
+        }
+        if self.config.sync {
+            station_status |= sap_set_parameter_request::ACTIVATE_SYNC;
+        }
+        let mut data = vec![
+            station_status,
+            self.config.watchdog_factor_1,
+            self.config.watchdog_factor_2,
+            self.config.min_tsdr,
+            self.config.ident_high,
+            self.config.ident_low,
+            self.config.group,
+        ];
+        data.extend_from_slice(&self.config.user_para);
+        self.expect_short_ack(slave, sap::SET_PRM, &data)
+    }
+
+    pub fn chk_cfg<Target: InterruptTarget>(
+        &mut self,
+        slave: &mut Target,
+    ) -> Result<(), MasterError> {
+        let data = self.config.module_config.clone();
+        self.expect_short_ack(slave, sap::CHK_CFG, &data)
+    }
+
+    pub fn get_cfg<Target: InterruptTarget>(
+        &mut self,
+        slave: &mut Target,
+    ) -> Result<Vec<u8>, MasterError> {
+        match self.sap_request(slave, sap::GET_CFG, &[])? {
+            SlaveResponse::SapData { data, .. } => Ok(data),
+            _ => Err(MasterError::InvalidResponse(Vec::new())),
+        }
+    }
+
+    /// Set_Slave_Address with the ident number of the config. On success
+    /// the master addresses the slave by `new_addr` from now on.
+    pub fn set_slave_address<Target: InterruptTarget>(
+        &mut self,
+        slave: &mut Target,
+        new_addr: u8,
+        no_add_chg: bool,
+    ) -> Result<(), MasterError> {
+        let request = [
+            new_addr,
+            self.config.ident_high,
+            self.config.ident_low,
+            u8::from(no_add_chg),
+        ];
+        match self.sap_request(slave, sap::SET_SLAVE_ADR, &request)? {
+            SlaveResponse::ShortAck => {
+                self.config.slave_addr = new_addr;
+                self.fcb = None;
+                Ok(())
+            }
+            SlaveResponse::NoData { function_code } => Err(MasterError::Rejected { function_code }),
+            SlaveResponse::SapData { data, .. } | SlaveResponse::Data { data, .. } => {
+                Err(MasterError::InvalidResponse(data))
+            }
+        }
+    }
+
+    /// Read_Inputs, as any master may do while the slave is in data exchange.
+    pub fn read_inputs<Target: InterruptTarget>(
+        &mut self,
+        slave: &mut Target,
+    ) -> Result<Vec<u8>, MasterError> {
+        self.read_process_image(slave, sap::RD_INP)
+    }
+
+    /// Read_Outputs, the outputs the slave got with the last Data_Exchange.
+    pub fn read_outputs<Target: InterruptTarget>(
+        &mut self,
+        slave: &mut Target,
+    ) -> Result<Vec<u8>, MasterError> {
+        self.read_process_image(slave, sap::RD_OUTP)
+    }
+
+    fn read_process_image<Target: InterruptTarget>(
+        &mut self,
+        slave: &mut Target,
+        dsap: u8,
+    ) -> Result<Vec<u8>, MasterError> {
+        match self.sap_request(slave, dsap, &[])? {
+            SlaveResponse::SapData { data, .. } => Ok(data),
+            SlaveResponse::NoData { function_code } => Err(MasterError::Rejected { function_code }),
+            SlaveResponse::ShortAck => Err(MasterError::InvalidResponse(Vec::new())),
+            SlaveResponse::Data { data, .. } => Err(MasterError::InvalidResponse(data)),
+        }
+    }
+
+    /// Sends a Global_Control telegram as broadcast. The slave does not
+    /// answer, the bus is only run until the telegram is processed.
+    pub fn global_control<Target: InterruptTarget>(
+        &mut self,
+        slave: &mut Target,
+        control: u8,
+        group: u8,
+    ) {
+        let request = encode(&Telegram::Sd2 {
+            destination_addr: 127,
+            source_addr: self.config.master_addr,
+            function_code: fc_request::REQUEST + fc_request_low_nibble::SDN_HIGH,
+            dsap: Some(sap::GLOBAL_CONTROL),
+            ssap: Some(MASTER_SAP),
+            pdu: &[control, group],
+        })
+        .expect("Global_Control fits into a telegram");
+        self.idle(slave);
+        self.bus.send(&request);
+        self.bus.run_for_ns(slave, self.slot_time_ns());
+    }
+
+    pub fn freeze<Target: InterruptTarget>(&mut self, slave: &mut Target, group: u8) {
+        self.global_control(slave, sap_global_control::FREEZE, group);
+    }
+
+    pub fn sync<Target: InterruptTarget>(&mut self, slave: &mut Target, group: u8) {
+        self.global_control(slave, sap_global_control::SYNC, group);
+    }
+
+    /// One cyclic SRD: writes `outputs` and returns the inputs and whether
+    /// the slave signalled new diagnostic data with a high priority answer.
+    pub fn data_exchange<Target: InterruptTarget>(
+        &mut self,
+        slave: &mut Target,
+        outputs: &[u8],
+    ) -> Result<(Vec<u8>, bool), MasterError> {
+        let function_code = self.next_function_code(fc_request::SRD_HIGH);
+        let request = if outputs.is_empty() {
+            encode(&Telegram::Sd1 {
+                destination_addr: self.config.slave_addr,
+                source_addr: self.config.master_addr,
+                function_code,
+            })?
+        } else {
+            encode(&Telegram::Sd2 {
+                destination_addr: self.config.slave_addr,
+                source_addr: self.config.master_addr,
+                function_code,
+                dsap: None,
+                ssap: None,
+                pdu: outputs,
+            })?
+        };
+        match self.transaction(slave, &request)? {
+            SlaveResponse::Data {
+                function_code,
+                data,
+            } => Ok((data, function_code == fc_response::DATA_HIGH)),
+            SlaveResponse::NoData { function_code } => {
+                Ok((Vec::new(), function_code == fc_response::DATA_HIGH))
+            }
+            SlaveResponse::ShortAck => Ok((Vec::new(), false)),
+            SlaveResponse::SapData { data, .. } => Err(MasterError::InvalidResponse(data)),
+        }
+    }
+
+    /// Read of a data record with at most `len` bytes.
+    pub fn read_record<Target: InterruptTarget>(
+        &mut self,
+        slave: &mut Target,
+        slot: u8,
+        index: u8,
+        len: u8,
+    ) -> Result<Vec<u8>, MasterError> {
+        let request = [dpv1_function::READ, slot, index, len];
+        let response = self.dpv1_request(slave, &request)?;
+        Ok(response[4..].to_vec())
+    }
+
+    /// Write of a data record.
+    pub fn write_record<Target: InterruptTarget>(
+        &mut self,
+        slave: &mut Target,
+        slot: u8,
+        index: u8,
+        data: &[u8],
+    ) -> Result<(), MasterError> {
+        let mut request = vec![dpv1_function::WRITE, slot, index, data.len() as u8];
+        request.extend_from_slice(data);
+        self.dpv1_request(slave, &request)?;
+        Ok(())
+    }
+
+    /// MSAC_C2 Data_Transport, returns the data of the answer.
+    pub fn data_transport<Target: InterruptTarget>(
+        &mut self,
+        slave: &mut Target,
+        slot: u8,
+        index: u8,
+        data: &[u8],
+    ) -> Result<Vec<u8>, MasterError> {
+        let mut request = vec![dpv1_function::DATA_TRANSPORT, slot, index, data.len() as u8];
+        request.extend_from_slice(data);
+        let response = self.dpv1_request(slave, &request)?;
+        Ok(response[4..].to_vec())
+    }
+
+    /// Alarm_Ack of a reported alarm.
+    pub fn alarm_ack<Target: InterruptTarget>(
+        &mut self,
+        slave: &mut Target,
+        alarm: &Alarm,
+    ) -> Result<(), MasterError> {
+        let request = [
+            dpv1_function::ALARM_ACK,
+            alarm.slot,
+            alarm.alarm_type.code(),
+            alarm.specifier_byte(),
+        ];
+        match self.sap_request_from(slave, sap::MSAL1_ALARM, sap::MSAC_C1, &request)? {
+            SlaveResponse::ShortAck => Ok(()),
+            SlaveResponse::NoData { function_code } => Err(MasterError::Rejected { function_code }),
+            SlaveResponse::SapData { data, .. } | SlaveResponse::Data { data, .. } => {
+                Err(MasterError::InvalidResponse(data))
+            }
+        }
+    }
+
+    /// Opens an MSAC_C2 connection with a Send_Timeout in 10 ms and returns
+    /// the Initiate response.
+    pub fn initiate<Target: InterruptTarget>(
+        &mut self,
+        slave: &mut Target,
+        send_timeout: u16,
+    ) -> Result<Vec<u8>, MasterError> {
+        let [timeout_high, timeout_low] = send_timeout.to_be_bytes();
+        // Features_Supported Read/Write, no profile, no additional addresses
+        let request = [
+            dpv1_function::INITIATE,
+            0,
+            0,
+            0,
+            timeout_high,
+            timeout_low,
+            0x01,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+        ];
+        self.c2_sap = None;
+        let (ssap, response) = self.dpv1_transaction(slave, sap::MSAC_C2_INITIATE, &request)?;
+        self.c2_sap = Some(ssap);
+        Ok(response)
+    }
+
+    /// Closes the MSAC_C2 connection.
+    pub fn abort<Target: InterruptTarget>(
+        &mut self,
+        slave: &mut Target,
+    ) -> Result<(), MasterError> {
+        let Some(c2_sap) = self.c2_sap.take() else {
+            return Ok(());
+        };
+        match self.sap_request_from(
+            slave,
+            c2_sap,
+            MSAC_C2_MASTER_SAP,
+            &[dpv1_function::ABORT, 0, 0],
+        )? {
+            SlaveResponse::ShortAck => Ok(()),
+            SlaveResponse::SapData { data, .. } | SlaveResponse::Data { data, .. } => {
+                Err(MasterError::InvalidResponse(data))
+            }
+            SlaveResponse::NoData { .. } => Err(MasterError::InvalidResponse(Vec::new())),
+        }
+    }
+
+    /// Sends a DPV1 request over MSAC_C2 if a connection is open, else over
+    /// MSAC_C1, and polls for the response.
+    fn dpv1_request<Target: InterruptTarget>(
+        &mut self,
+        slave: &mut Target,
+        request: &[u8],
+    ) -> Result<Vec<u8>, MasterError> {
+        let dsap = self.c2_sap.unwrap_or(sap::MSAC_C1);
+        self.dpv1_transaction(slave, dsap, request)
+            .map(|(_, response)| response)
+    }
+
+    /// Sends a DPV1 request to `dsap` and polls for the response, which is
+    /// returned with its header and the SAP it came from if it is positive.
+    fn dpv1_transaction<Target: InterruptTarget>(
+        &mut self,
+        slave: &mut Target,
+        dsap: u8,
+        request: &[u8],
+    ) -> Result<(u8, Vec<u8>), MasterError> {
+        let ssap = if dsap == sap::MSAC_C1 {
+            sap::MSAC_C1
+        } else {
+            MSAC_C2_MASTER_SAP
+        };
+        match self.sap_request_from(slave, dsap, ssap, request)? {
+            SlaveResponse::ShortAck => (),
+            SlaveResponse::NoData { function_code } if function_code != fc_response::DATA_LOW => {
+                return Err(MasterError::Rejected { function_code })
+            }
+            SlaveResponse::NoData { .. } => return Err(MasterError::InvalidResponse(Vec::new())),
+            SlaveResponse::SapData { data, .. } | SlaveResponse::Data { data, .. } => {
+                return Err(MasterError::InvalidResponse(data))
+            }
+        }
+        for _ in 0..DPV1_POLL_LIMIT {
+            let (response_sap, data) = match self.sap_request_from(slave, dsap, ssap, &[])? {
+                SlaveResponse::ShortAck => continue,
+                SlaveResponse::SapData { ssap, data, .. } => (ssap, data),
+                SlaveResponse::NoData { .. } => {
+                    return Err(MasterError::InvalidResponse(Vec::new()))
+                }
+                SlaveResponse::Data { data, .. } => return Err(MasterError::InvalidResponse(data)),
+            };
+            return match data[..] {
+                [function, error_decode, error_code_1, error_code_2]
+                    if (function & dpv1_function::ERROR) != 0 =>
+                {
+                    Err(MasterError::Dpv1 {
+                        error_decode,
+                        error_code_1,
+                        error_code_2,
+                    })
+                }
+                [function, ..] if data.len() >= 4 && function == request[0] => {
+                    Ok((response_sap, data))
+                }
+                _ => Err(MasterError::InvalidResponse(data)),
+            };
+        }
+        Err(MasterError::NoResponse)
+    }
+
+    pub fn sap_request<Target: InterruptTarget>(
+        &mut self,
+        slave: &mut Target,
+        dsap: u8,
+        data: &[u8],
+    ) -> Result<SlaveResponse, MasterError> {
+        self.sap_request_from(slave, dsap, MASTER_SAP, data)
+    }
+
+    fn sap_request_from<Target: InterruptTarget>(
+        &mut self,
+        slave: &mut Target,
+        dsap: u8,
+        ssap: u8,
+        data: &[u8],
+    ) -> Result<SlaveResponse, MasterError> {
+        let function_code = self.next_function_code(fc_request::SRD_HIGH);
+        let request = encode(&Telegram::Sd2 {
+            destination_addr: self.config.slave_addr,
+            source_addr: self.config.master_addr,
+            function_code,
+            dsap: Some(dsap),
+            ssap: Some(ssap),
+            pdu: data,
+        })?;
+        self.transaction(slave, &request)
+    }
+
+    fn expect_short_ack<Target: InterruptTarget>(
+        &mut self,
+        slave: &mut Target,
+        dsap: u8,
+        data: &[u8],
+    ) -> Result<(), MasterError> {
+        match self.sap_request(slave, dsap, data)? {
+            SlaveResponse::ShortAck => Ok(()),
+            SlaveResponse::SapData { data, .. } | SlaveResponse::Data { data, .. } => {
+                Err(MasterError::InvalidResponse(data))
+            }
+            SlaveResponse::NoData { .. } => Err(MasterError::InvalidResponse(Vec::new())),
+        }
+    }
+
+    fn next_function_code(&mut self, service: u8) -> u8 {
+        let function_code = match self.fcb {
+            // first request after startup: FCB set, FCV not yet valid
+            None => fc_request::REQUEST + fc_request::FCB + service,
+            Some(fcb) => {
+                let fcb = if fcb { 0 } else { fc_request::FCB };
+                fc_request::REQUEST + fc_request::FCV + fcb + service
+            }
+        };
+        self.fcb = Some((function_code & fc_request::FCB) != 0);
+        function_code
+    }
+
+    fn slot_time_ns(&self) -> u64 {
+        (u64::from(self.config.slot_time_bits) * 1_000_000_000) / u64::from(self.bus.baudrate())
+    }
+
+    fn idle<Target: InterruptTarget>(&self, slave: &mut Target) {
+        // Tsyn of 33 bit plus some safety margin
+        let idle_ns = (66 * 1_000_000_000) / u64::from(self.bus.baudrate());
+        self.bus.run_for_ns(slave, idle_ns);
+    }
+
+    fn transaction<Target: InterruptTarget>(
+        &mut self,
+        slave: &mut Target,
+        request: &[u8],
+    ) -> Result<SlaveResponse, MasterError> {
+        for _ in 0..=self.config.retry_limit {
+            self.idle(slave);
+            let request_time = request.len() as u64 * self.bus.byte_time_ns();
+            let response = self
+                .bus
+                .request(slave, request, request_time + self.slot_time_ns());
+            if !response.is_empty() {
+                return parse_response(self.config.master_addr, &response)
+                    .ok_or(MasterError::InvalidResponse(response));
+            }
+        }
+        // the next request starts a new FCB sequence
+        self.fcb = None;
+        Err(MasterError::NoResponse)
+    }
+}
+
+fn encode(telegram: &Telegram) -> Result<Vec<u8>, MasterError> {
+    let mut buffer = [0; MAX_TELEGRAM_SIZE];
+    let len = telegram.encode(&mut buffer).map_err(MasterError::Frame)?;
+    Ok(buffer[..len].to_vec())
+}
+
+fn parse_response(master_addr: u8, frame: &[u8]) -> Option<SlaveResponse> {
+    match Telegram::decode_for(frame, master_addr).ok()? {
+        Telegram::Sc => Some(SlaveResponse::ShortAck),
+        Telegram::Sd1 { function_code, .. } => Some(SlaveResponse::NoData { function_code }),
+        Telegram::Sd2 {
+            function_code,
+            dsap,
+            ssap,
+            pdu,
+            ..
+        } => {
+            if let (Some(_), Some(ssap)) = (dsap, ssap) {
+                Some(SlaveResponse::SapData {
+                    function_code,
+                    ssap,
+                    data: pdu.to_vec(),
+                })
+            } else {
+                Some(SlaveResponse::Data {
+                    function_code,
+                    data: pdu.to_vec(),
+                })
+            }
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,9 @@
+pub use async_bus::{run_until_stalled, AsyncSimBus, AsyncSimHwInterface};
+pub use bus::{InterruptTarget, SimBus, SimHwInterface};
+pub use master::{DpMaster, MasterConfig, MasterError, SlaveDiagnostic, SlaveResponse};
+pub use trace::{replay, Direction, ReplayConfig, ReplayError, Trace, TraceEntry, TraceError};
+
+pub mod async_bus;
+pub mod bus;
+pub mod master;
+pub mod trace;
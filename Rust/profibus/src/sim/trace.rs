@@ -0,0 +1,255 @@
+/*********************************************************************
+ * Replay of captured bus traffic
+ *
+ * Copyright (C) 2023 Marcel Maage
+ *
+ * This library is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2.1 of the License, or (at your option) any later version.
+ *
+ * This library is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * LICENSE file for more details.
+ */
+
+//! A trace is a text file with one telegram per line:
+//!
+//! ```text
+//! # time in us, M for master or S for slave, telegram in hex
+//! 0       M 10 0B 02 49 56 16
+//! 180     S 10 02 0B 00 0D 16
+//! ```
+//!
+//! Empty lines and everything after `#` are ignored. The time stamp is the
+//! start of the telegram, the hex bytes may be separated by spaces or not.
+
+use std::string::String;
+use std::vec::Vec;
+
+use super::bus::{InterruptTarget, SimBus};
+use crate::telegram::{Telegram, BROADCAST_ADDR, MAX_TELEGRAM_SIZE};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Master,
+    Slave,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceEntry {
+    /// Line in the trace file, for error messages.
+    pub line: usize,
+    pub time_us: u64,
+    pub direction: Direction,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TraceError {
+    InvalidTime {
+        line: usize,
+    },
+    InvalidDirection {
+        line: usize,
+    },
+    InvalidHex {
+        line: usize,
+    },
+    /// Time stamps have to be in chronological order.
+    TimeGoesBack {
+        line: usize,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplayError {
+    /// The slave answered something else than recorded. An empty `expected`
+    /// means the slave should have stayed silent, an empty `received` that
+    /// it did not answer.
+    Mismatch {
+        line: usize,
+        expected: Vec<u8>,
+        received: Vec<u8>,
+    },
+    /// The answer started outside of the window between min and max TSDR.
+    Timing { line: usize, tsdr_bits: u64 },
+    /// A slave telegram without a master telegram before it.
+    UnexpectedSlaveTelegram { line: usize },
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Trace {
+    entries: Vec<TraceEntry>,
+}
+
+impl Trace {
+    pub fn parse(text: &str) -> Result<Self, TraceError> {
+        let mut entries: Vec<TraceEntry> = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            let content = line.split('#').next().unwrap_or("");
+            let mut fields = content.split_whitespace();
+            let Some(time) = fields.next() else {
+                continue;
+            };
+            let time_us = time
+                .parse::<u64>()
+                .map_err(|_| TraceError::InvalidTime { line: line_number })?;
+            let direction = match fields.next() {
+                Some("M") | Some("m") => Direction::Master,
+                Some("S") | Some("s") => Direction::Slave,
+                _ => {
+                    return Err(TraceError::InvalidDirection { line: line_number });
+                }
+            };
+            let hex: String = fields.collect();
+            let data = parse_hex(&hex).ok_or(TraceError::InvalidHex { line: line_number })?;
+            if let Some(last) = entries.last() {
+                if time_us < last.time_us {
+                    return Err(TraceError::TimeGoesBack { line: line_number });
+                }
+            }
+            entries.push(TraceEntry {
+                line: line_number,
+                time_us,
+                direction,
+                data,
+            });
+        }
+        Ok(Self { entries })
+    }
+
+    pub fn entries(&self) -> &[TraceEntry] {
+        &self.entries
+    }
+
+    /// Keeps only the traffic between the master and station `addr`: master
+    /// telegrams to `addr` or broadcast and the answers following them.
+    /// Tokens and telegrams to other slaves of a real bus are dropped.
+    pub fn station(&self, addr: u8) -> Self {
+        let mut entries = Vec::new();
+        let mut keep_answer = false;
+        for entry in &self.entries {
+            match entry.direction {
+                Direction::Master => {
+                    keep_answer = Telegram::decode(&entry.data)
+                        .ok()
+                        .and_then(|telegram| telegram.destination_addr())
+                        .is_some_and(|da| da == addr || da == BROADCAST_ADDR);
+                    if keep_answer {
+                        entries.push(entry.clone());
+                    }
+                }
+                Direction::Slave => {
+                    if keep_answer {
+                        entries.push(entry.clone());
+                    }
+                    keep_answer = false;
+                }
+            }
+        }
+        Self { entries }
+    }
+}
+
+fn parse_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.is_empty() || !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+pub struct ReplayConfig {
+    min_tsdr_bits: u32,
+    max_tsdr_bits: u32,
+}
+
+impl ReplayConfig {
+    pub fn min_tsdr_bits(mut self, min_tsdr_bits: u32) -> Self {
+        self.min_tsdr_bits = min_tsdr_bits;
+        self
+    }
+
+    pub fn max_tsdr_bits(mut self, max_tsdr_bits: u32) -> Self {
+        self.max_tsdr_bits = max_tsdr_bits;
+        self
+    }
+}
+
+impl Default for ReplayConfig {
+    fn default() -> Self {
+        // max TSDR of the standard for up to 500 kBit/s
+        Self {
+            min_tsdr_bits: 11,
+            max_tsdr_bits: 100,
+        }
+    }
+}
+
+/// Feeds the master telegrams of `trace` into `slave` and compares every
+/// answer byte for byte with the slave telegram recorded after it.
+///
+/// Master telegrams are sent with the recorded pauses, measured from the
+/// first one, but never before the slave finished its last answer. The
+/// answer has to start between min and max TSDR after the request. Stops at
+/// the first difference.
+pub fn replay<Target: InterruptTarget>(
+    bus: &SimBus,
+    slave: &mut Target,
+    trace: &Trace,
+    config: &ReplayConfig,
+) -> Result<(), ReplayError> {
+    let baudrate = u64::from(bus.baudrate());
+    let byte_time_ns = bus.byte_time_ns();
+    let max_answer_ns = u64::from(config.max_tsdr_bits) * 1_000_000_000 / baudrate
+        + MAX_TELEGRAM_SIZE as u64 * byte_time_ns;
+    let start_ns = bus.now_ns();
+    let first_us = trace.entries.first().map_or(0, |entry| entry.time_us);
+
+    let mut entries = trace.entries.iter().peekable();
+    while let Some(entry) = entries.next() {
+        if Direction::Slave == entry.direction {
+            return Err(ReplayError::UnexpectedSlaveTelegram { line: entry.line });
+        }
+        let expected = match entries.next_if(|next| Direction::Slave == next.direction) {
+            Some(answer) => answer.data.clone(),
+            None => Vec::new(),
+        };
+
+        let send_ns = start_ns + (entry.time_us - first_us) * 1000;
+        bus.run_until_ns(slave, send_ns);
+        let request_end_ns = bus.now_ns() + entry.data.len() as u64 * byte_time_ns;
+        let response = bus.request_with_time(
+            slave,
+            &entry.data,
+            entry.data.len() as u64 * byte_time_ns + max_answer_ns,
+        );
+
+        let received: Vec<u8> = response.iter().map(|(_, value)| *value).collect();
+        if received != expected {
+            return Err(ReplayError::Mismatch {
+                line: entry.line,
+                expected,
+                received,
+            });
+        }
+        if let Some((first_ns, _)) = response.first() {
+            let tsdr_ns = (first_ns - byte_time_ns).saturating_sub(request_end_ns);
+            let tsdr_bits = tsdr_ns * baudrate / 1_000_000_000;
+            if tsdr_bits < u64::from(config.min_tsdr_bits)
+                || tsdr_bits > u64::from(config.max_tsdr_bits)
+            {
+                return Err(ReplayError::Timing {
+                    line: entry.line,
+                    tsdr_bits,
+                });
+            }
+        }
+    }
+    Ok(())
+}
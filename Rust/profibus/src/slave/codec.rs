@@ -15,10 +15,11 @@
  */
 
 use super::codec_hw_interface::HwInterface;
-use super::slave::PbDpSlave;
-use super::types::{cmd_type, StreamState};
+use super::dp_slave::PbDpSlave;
+use crate::telegram::{Telegram, MAX_PDU_SIZE};
+use crate::types::StreamState;
 
-use super::data_handling_interface::DataHandlingInterface;
+use crate::data_handling_interface::DataHandlingInterface;
 
 #[derive(PartialEq, Eq)]
 pub enum UartAccess {
@@ -134,8 +135,6 @@ impl Default for Codec {
     }
 }
 
-const SAP_OFFSET: u8 = 128;
-const BROADCAST_ADD: u8 = 127;
 const DEFAULT_ADD: u8 = 126;
 
 impl<
@@ -223,21 +222,17 @@ where
 
     pub fn rx_interrupt_handler(&mut self) {
         self.hw_interface.stop_timer();
-        loop {
-            match self.hw_interface.get_uart_value() {
-                Some(data) => {
-                    if StreamState::WaitData == self.codec.stream_state {
-                        self.codec.stream_state = StreamState::GetData;
-                    }
-
-                    if StreamState::GetData == self.codec.stream_state {
-                        self.rx_buffer[self.codec.rx_len] = data;
-                        if self.codec.rx_len < self.rx_buffer.len() {
-                            self.codec.rx_len += 1;
-                        }
-                    }
+        while let Some(data) = self.hw_interface.get_uart_value() {
+            if StreamState::WaitData == self.codec.stream_state {
+                self.codec.stream_state = StreamState::GetData;
+            }
+
+            if StreamState::GetData == self.codec.stream_state {
+                // longer telegrams are cut and fail the length check later on
+                if self.codec.rx_len < self.rx_buffer.len() {
+                    self.rx_buffer[self.codec.rx_len] = data;
+                    self.codec.rx_len += 1;
                 }
-                None => break,
             }
         }
         self.hw_interface.run_timer(self.codec.timer_timeout_in_us);
@@ -289,21 +284,7 @@ where
                 }
             }
             StreamState::WaitMinTsdr => {
-                self.codec.stream_state = StreamState::SendData;
-                self.codec.timer_timeout_in_us = self.codec.timeout_max_tx_time_in_us;
-                self.hw_interface.wait_for_activ_transmission();
-                self.hw_interface.tx_rs485_enable();
-                self.hw_interface.clear_tx_flag();
-                if self.codec.config.tx_handling == UartAccess::SingleByte {
-                    self.hw_interface
-                        .set_uart_value(self.tx_buffer[self.codec.tx_pos]);
-                    self.hw_interface.activate_tx_interrupt();
-                    self.codec.tx_pos += 1;
-                    self.hw_interface.run_timer(self.codec.timer_timeout_in_us);
-                } else if self.codec.config.tx_handling == UartAccess::Dma {
-                    self.hw_interface.send_uart_data(&self.tx_buffer);
-                    self.hw_interface.activate_tx_interrupt();
-                }
+                self.start_transmission();
             }
             StreamState::SendData => {
                 self.codec.stream_state = StreamState::WaitSyn;
@@ -318,54 +299,37 @@ where
         self.hw_interface.run_timer(self.codec.timer_timeout_in_us);
     }
 
-    pub(super) fn transmit_message_sd1(
-        &mut self,
-        destination_addr: u8,
-        function_code: u8,
-        sap_offset: bool,
-    ) {
-        let t_s = self.codec.config.t_s;
-        self.tx_buffer[0] = cmd_type::SD1;
-        self.tx_buffer[1] = destination_addr;
-        self.tx_buffer[2] = t_s + if sap_offset { SAP_OFFSET } else { 0 };
-        self.tx_buffer[3] = function_code;
-        self.tx_buffer[4] = calc_checksum(&self.tx_buffer[1..4]);
-        self.tx_buffer[5] = cmd_type::ED;
-        self.codec.tx_len = 6;
-        self.transmit();
+    pub(super) fn transmit_message_sd1(&mut self, destination_addr: u8, function_code: u8) {
+        self.transmit_message(&Telegram::Sd1 {
+            destination_addr,
+            source_addr: self.codec.config.t_s,
+            function_code,
+        });
     }
 
+    /// Sends `pdu1` followed by `pdu2`. `sap` is (dsap, ssap).
     pub(super) fn transmit_message_sd2(
         &mut self,
         destination_addr: u8,
         function_code: u8,
-        sap_offset: bool,
+        sap: Option<(u8, u8)>,
         pdu1: &[u8],
         pdu2: &[u8],
     ) {
-        let t_s = self.codec.config.t_s;
-        self.tx_buffer[0] = cmd_type::SD2;
-        self.tx_buffer[1] = 3 + pdu1.len().to_le_bytes()[0] + pdu2.len().to_le_bytes()[0];
-        self.tx_buffer[2] = 3 + pdu1.len().to_le_bytes()[0] + pdu2.len().to_le_bytes()[0];
-        self.tx_buffer[3] = cmd_type::SD2;
-        self.tx_buffer[4] = destination_addr;
-        self.tx_buffer[5] = t_s + if sap_offset { SAP_OFFSET } else { 0 };
-        self.tx_buffer[6] = function_code;
-        if pdu1.len() > 0 {
-            for i in 0..pdu1.len() {
-                self.tx_buffer[7 + i] = pdu1[i];
-            }
+        let mut pdu = [0; MAX_PDU_SIZE];
+        let pdu_len = pdu1.len() + pdu2.len();
+        if pdu_len <= pdu.len() {
+            pdu[..pdu1.len()].copy_from_slice(pdu1);
+            pdu[pdu1.len()..pdu_len].copy_from_slice(pdu2);
+            self.transmit_message(&Telegram::Sd2 {
+                destination_addr,
+                source_addr: self.codec.config.t_s,
+                function_code,
+                dsap: sap.map(|sap| sap.0),
+                ssap: sap.map(|sap| sap.1),
+                pdu: &pdu[..pdu_len],
+            });
         }
-        if pdu2.len() > 0 {
-            for i in 0..pdu2.len() {
-                self.tx_buffer[7 + i + pdu1.len()] = pdu2[i];
-            }
-        }
-        let checksum = calc_checksum(&self.tx_buffer[4..7]) + calc_checksum(pdu1) + calc_checksum(pdu2);
-        self.tx_buffer[7 + pdu1.len() + pdu2.len()] = checksum;
-        self.tx_buffer[8 + pdu1.len() + pdu2.len()] = cmd_type::ED;
-        self.codec.tx_len = 9 + pdu1.len() + pdu2.len();
-        self.transmit();
     }
 
     #[allow(dead_code)]
@@ -373,37 +337,37 @@ where
         &mut self,
         destination_addr: u8,
         function_code: u8,
-        sap_offset: bool,
-        pdu: &[u8; 8],
+        sap: Option<(u8, u8)>,
+        pdu: &[u8],
     ) {
-        let t_s = self.codec.config.t_s;
-        self.tx_buffer[0] = cmd_type::SD3;
-        self.tx_buffer[1] = destination_addr;
-        self.tx_buffer[2] = t_s + if sap_offset { SAP_OFFSET } else { 0 };
-        self.tx_buffer[3] = function_code;
-        for i in 0..pdu.len() {
-            self.tx_buffer[4 + i] = pdu[i];
-        }
-        self.tx_buffer[12] = calc_checksum(&self.tx_buffer[1..12]);
-        self.tx_buffer[13] = cmd_type::ED;
-        self.codec.tx_len = 14;
-        self.transmit();
+        self.transmit_message(&Telegram::Sd3 {
+            destination_addr,
+            source_addr: self.codec.config.t_s,
+            function_code,
+            dsap: sap.map(|sap| sap.0),
+            ssap: sap.map(|sap| sap.1),
+            pdu,
+        });
     }
 
     #[allow(dead_code)]
-    pub(super) fn transmit_message_sd4(&mut self, destination_addr: u8, sap_offset: bool) {
-        let t_s = self.codec.config.t_s;
-        self.tx_buffer[0] = cmd_type::SD4;
-        self.tx_buffer[1] = destination_addr;
-        self.tx_buffer[2] = t_s + if sap_offset { SAP_OFFSET } else { 0 };
-        self.codec.tx_len = 3;
-        self.transmit();
+    pub(super) fn transmit_message_sd4(&mut self, destination_addr: u8) {
+        self.transmit_message(&Telegram::Sd4 {
+            destination_addr,
+            source_addr: self.codec.config.t_s,
+        });
     }
 
     pub(super) fn transmit_message_sc(&mut self) {
-        self.tx_buffer[0] = cmd_type::SC;
-        self.codec.tx_len = 1;
-        self.transmit();
+        self.transmit_message(&Telegram::Sc);
+    }
+
+    fn transmit_message(&mut self, telegram: &Telegram) {
+        // a telegram which does not fit into the buffer is not sent at all
+        if let Ok(tx_len) = telegram.encode(&mut self.tx_buffer) {
+            self.codec.tx_len = tx_len;
+            self.transmit();
+        }
     }
 
     pub(super) fn transmit(&mut self) {
@@ -413,26 +377,35 @@ where
             self.codec.stream_state = StreamState::WaitMinTsdr;
             let counter_frequency = self.hw_interface.get_timer_frequency();
             let baudrate = self.hw_interface.get_baudrate();
-            self.codec.timer_timeout_in_us =
-                (counter_frequency * u32::from(self.codec.config.t_sdr_min)) / baudrate / 2u32;
+            let timeout = (u64::from(counter_frequency) * u64::from(self.codec.config.t_sdr_min))
+                / u64::from(baudrate)
+                / 2;
+            self.codec.timer_timeout_in_us = u32::try_from(timeout).unwrap_or(u32::MAX);
             self.hw_interface.run_timer(self.codec.timer_timeout_in_us);
         } else {
-            self.codec.stream_state = StreamState::SendData;
-            self.hw_interface.wait_for_activ_transmission();
-            self.codec.timer_timeout_in_us = self.codec.timeout_max_tx_time_in_us;
-            // activate Send Interrupt
-            self.hw_interface.tx_rs485_enable();
-            self.hw_interface.clear_tx_flag();
-            if self.codec.config.tx_handling == UartAccess::SingleByte {
-                self.hw_interface
-                    .set_uart_value(self.tx_buffer[self.codec.tx_pos]);
-                self.hw_interface.activate_tx_interrupt();
-                self.codec.tx_pos += 1;
-                self.hw_interface.run_timer(self.codec.timer_timeout_in_us);
-            } else if self.codec.config.tx_handling == UartAccess::Dma {
-                self.hw_interface.send_uart_data(&self.tx_buffer);
-                self.hw_interface.activate_tx_interrupt();
-            }
+            self.start_transmission();
+        }
+    }
+
+    fn start_transmission(&mut self) {
+        self.data_handling_interface
+            .telegram_sent(&self.tx_buffer[..self.codec.tx_len]);
+        self.codec.stream_state = StreamState::SendData;
+        self.hw_interface.wait_for_activ_transmission();
+        self.codec.timer_timeout_in_us = self.codec.timeout_max_tx_time_in_us;
+        // activate Send Interrupt
+        self.hw_interface.tx_rs485_enable();
+        self.hw_interface.clear_tx_flag();
+        if self.codec.config.tx_handling == UartAccess::SingleByte {
+            self.hw_interface
+                .set_uart_value(self.tx_buffer[self.codec.tx_pos]);
+            self.hw_interface.activate_tx_interrupt();
+            self.codec.tx_pos += 1;
+            self.hw_interface.run_timer(self.codec.timer_timeout_in_us);
+        } else if self.codec.config.tx_handling == UartAccess::Dma {
+            self.hw_interface
+                .send_uart_data(&self.tx_buffer[..self.codec.tx_len]);
+            self.hw_interface.activate_tx_interrupt();
         }
     }
 
@@ -442,116 +415,49 @@ where
         let rx_len = self.codec.rx_len;
         let t_s = self.codec.config.t_s;
         let buf = self.rx_buffer;
-        match buf[0] {
-            cmd_type::SD1 => {
-                if 6 == rx_len {
-                    if cmd_type::ED == buf[5] {
-                        let destination_addr = buf[1];
-                        let source_addr = buf[2];
-                        let mut function_code = buf[3];
-                        let fcs_data = buf[4]; // Frame Check Sequence
-
-                        if check_destination_addr(t_s, destination_addr) {
-                            if fcs_data == calc_checksum(&buf[1..4]) {
-                                // FCV und FCB loeschen, da vorher überprüft
-                                function_code &= 0xCF;
-                                response = self.fdl_handle_data(
-                                    source_addr,
-                                    destination_addr,
-                                    function_code,
-                                    &[0; 0],
-                                );
-                            }
-                        }
-                    }
-                }
-            }
-
-            cmd_type::SD2 => {
-                if rx_len > 4 {
-                    if rx_len == usize::from(buf[1] + 6) {
-                        if cmd_type::ED == buf[rx_len - 1] {
-                            let pdu_len = buf[1]; // DA+SA+FC+Nutzdaten
-                            let destination_addr = buf[4];
-                            let source_addr = buf[5];
-                            let mut function_code = buf[6];
-                            let fcs_data = buf[usize::from(pdu_len + 4)]; // Frame Check Sequence
-                            if check_destination_addr(t_s, destination_addr) {
-                                if fcs_data == calc_checksum(&buf[4..usize::from(rx_len - 2)]) {
-                                    // FCV und FCB loeschen, da vorher überprüft
-                                    function_code &= 0xCF;
-                                    response = self.fdl_handle_data(
-                                        source_addr,
-                                        destination_addr,
-                                        function_code,
-                                        &buf[7..usize::from(rx_len - 2)],
-                                    );
-                                }
-                            }
-                        }
-                    }
-                }
+        self.data_handling_interface.telegram_received(&buf[..rx_len]);
+        match Telegram::decode_for(&buf[..rx_len], t_s) {
+            Ok(Telegram::Sd1 {
+                destination_addr,
+                source_addr,
+                function_code,
+            }) => {
+                response =
+                    self.fdl_handle_data(source_addr, destination_addr, function_code, None, &[]);
             }
 
-            cmd_type::SD3 => {
-                if 14 == rx_len {
-                    if cmd_type::ED == buf[13] {
-                        let destination_addr = buf[1];
-                        let source_addr = buf[2];
-                        let mut function_code = buf[3];
-                        let fcs_data = buf[12]; // Frame Check Sequence
-
-                        if check_destination_addr(t_s, destination_addr) {
-                            if fcs_data == calc_checksum(&buf[1..12]) {
-                                // FCV und FCB loeschen, da vorher überprüft
-                                function_code &= 0xCF;
-                                response = self.fdl_handle_data(
-                                    source_addr,
-                                    destination_addr,
-                                    function_code,
-                                    &buf[4..12],
-                                );
-                            }
-                        }
-                    }
-                }
-            }
-
-            cmd_type::SD4 => {
-                if 3 == rx_len {
-                    let destination_addr = buf[1];
-                    let _source_addr = buf[2];
-
-                    if check_destination_addr(self.codec.config.t_s, destination_addr) {
-                        //TODO
-                    }
-                }
+            // DP always addresses both SAPs or none
+            Ok(Telegram::Sd2 {
+                destination_addr,
+                source_addr,
+                function_code,
+                dsap,
+                ssap,
+                pdu,
+            })
+            | Ok(Telegram::Sd3 {
+                destination_addr,
+                source_addr,
+                function_code,
+                dsap,
+                ssap,
+                pdu,
+            }) if dsap.is_some() == ssap.is_some() => {
+                response = self.fdl_handle_data(
+                    source_addr,
+                    destination_addr,
+                    function_code,
+                    dsap.zip(ssap),
+                    pdu,
+                );
             }
 
+            // token, short acknowledge and faulty telegrams are not answered
             _ => (),
-        } // match self.buffer[0]
+        }
         if !response {
             self.reset_data_stream();
         }
         self.hw_interface.activate_rx_interrupt();
     }
 }
-
-fn calc_checksum(data: &[u8]) -> u8 {
-    let mut checksum: u8 = 0;
-    for x in data {
-        checksum += *x;
-    }
-    checksum
-}
-
-fn check_destination_addr(address: u8, destination: u8) -> bool {
-    if ((destination & 0x7F) != address) &&  // Slave
-           ((destination & 0x7F) != BROADCAST_ADD)
-    // Broadcast
-    {
-        false
-    } else {
-        true
-    }
-}
@@ -0,0 +1,1367 @@
+use super::codec::{CodecConfig, Codec, ReceiveHandling, UartAccess};
+use super::codec_hw_interface::HwInterface;
+use super::dpv1::{self, C2Connection, DPV1_HEADER_SIZE, DPV1_PDU_SIZE};
+use crate::alarm::{
+    AlarmError, AlarmQueue, AlarmSpecifier, AlarmType, ALARM_HEADER_SIZE, MAX_ALARM_BLOCK_LEN,
+};
+use crate::cfg::{self, ModuleCatalog, Slot};
+use crate::data_handling_interface::DataHandlingInterface;
+use crate::diag::{ChannelDiag, ChannelDirection, ChannelFaults, ExtDiag};
+use crate::gsd::GsdConfig;
+use crate::persistent_storage::{self, PersistentStorage, StorageError, StorageKey, MAX_VALUE_LEN};
+
+use crate::types::{
+    dpv1_function, dpv1_status_byte1, dpv1_status_byte2, dpv1_status_byte3,
+    fc_request, fc_response, sap,
+    sap_diagnose_byte1, sap_diagnose_byte2,
+    sap_diagnose_byte3, sap_global_control, sap_set_parameter_request,
+    DpSlaveState, StreamState,
+};
+
+const MASTER_ADD_DEFAULT: u8 = 0xFF;
+// 126 ist die Auslieferungsadresse, 127 Broadcast
+const MAX_SLAVE_ADD: u8 = 125;
+
+#[derive(Default)]
+pub struct FdlConfig {
+    ident_high: u8,
+    ident_low: u8,
+    dpv1: bool,
+    dpv1_alarms: u8,
+}
+
+impl FdlConfig {
+    #[allow(dead_code)]
+    pub fn ident_high(mut self, ident_high: u8) -> Self {
+        self.ident_high = ident_high;
+        self
+    }
+    #[allow(dead_code)]
+    pub fn ident_low(mut self, ident_low: u8) -> Self {
+        self.ident_low = ident_low;
+        self
+    }
+}
+
+
+#[derive(Default)]
+pub struct ProfibusConfig {
+    codec: CodecConfig,
+    fdl : FdlConfig,
+}
+
+impl ProfibusConfig {
+    pub fn ident_high(mut self, ident_high: u8) -> Self {
+        self.fdl.ident_high = ident_high;
+        self
+    }
+    pub fn ident_low(mut self, ident_low: u8) -> Self {
+        self.fdl.ident_low = ident_low;
+        self
+    }
+
+    pub fn t_s(mut self, t_s: u8) -> Self {
+        self.codec.t_s = t_s;
+        self
+    }
+
+    pub fn t_sl(mut self, t_sl: u16) -> Self {
+        self.codec.t_sl = t_sl;
+        self
+    }
+
+    pub fn t_sdr_min(mut self, t_sdr_min: u16) -> Self {
+        self.codec.t_sdr_min = t_sdr_min;
+        self
+    }
+
+    pub fn rx_handling(mut self, rx_handling: UartAccess) -> Self {
+        self.codec.rx_handling = rx_handling;
+        self
+    }
+
+    pub fn tx_handling(mut self, tx_handling: UartAccess) -> Self {
+        self.codec.tx_handling = tx_handling;
+        self
+    }
+
+    pub fn receive_handling(mut self, receive_handling: ReceiveHandling) -> Self {
+        self.codec.receive_handling = receive_handling;
+        self
+    }
+
+    /// The first three user parameter bytes of Set_Prm are the DPV1 status
+    /// bytes, see `Dpv1Status`.
+    pub fn dpv1(mut self, dpv1: bool) -> Self {
+        self.fdl.dpv1 = dpv1;
+        self
+    }
+
+    /// Alarms (`dpv1_status_byte2`) the master may enable.
+    pub fn dpv1_alarms(mut self, dpv1_alarms: u8) -> Self {
+        self.fdl.dpv1_alarms = dpv1_alarms;
+        self
+    }
+}
+
+/// DPV1 status bytes of the accepted Set_Prm.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Dpv1Status(pub [u8; 3]);
+
+impl Dpv1Status {
+    pub fn dpv1_mode(&self) -> bool {
+        (self.0[0] & dpv1_status_byte1::DPV1_MODE) != 0
+    }
+
+    /// Outputs are set to their safe state by Data_Exchange without data
+    /// instead of by zeros.
+    pub fn fail_safe(&self) -> bool {
+        (self.0[0] & dpv1_status_byte1::FAIL_SAVE_MODE) != 0
+    }
+
+    /// The watchdog factors are in 1 ms instead of 10 ms.
+    pub fn watchdog_1ms(&self) -> bool {
+        (self.0[0] & dpv1_status_byte1::WATCHDOG_TB_1MS) != 0
+    }
+
+    /// Enabled alarms as bits of `dpv1_status_byte2`.
+    pub fn alarms(&self) -> u8 {
+        self.0[1] & DPV1_ALARMS
+    }
+
+    /// Chk_Cfg is checked by the application instead of the slave.
+    pub fn check_config_mode(&self) -> bool {
+        (self.0[1] & dpv1_status_byte2::CHECK_CONFIG_MODE) != 0
+    }
+
+    /// User parameters are structured in blocks.
+    pub fn parameter_block(&self) -> bool {
+        (self.0[2] & dpv1_status_byte3::PARAMETER_BLOCK) != 0
+    }
+
+    /// Checks for reserved bits and features the slave does not support.
+    /// Chk_Cfg is always compared by the slave, so the check config mode is
+    /// refused as well.
+    fn is_supported(&self, alarms: u8) -> bool {
+        (self.0[0] & !DPV1_BYTE1_SUPPORTED) == 0
+            && (self.0[1] & !alarms) == 0
+            && (self.0[2] & !dpv1_status_byte3::PARAMETER_BLOCK) == 0
+    }
+}
+
+const DPV1_ALARMS: u8 = dpv1_status_byte2::PULL_PLUG_ALARM
+    | dpv1_status_byte2::PROZESS_ALARM
+    | dpv1_status_byte2::DIAGNOSE_ALARM
+    | dpv1_status_byte2::VENDOR_ALARM
+    | dpv1_status_byte2::STATUS_ALARM
+    | dpv1_status_byte2::UPDATE_ALARM;
+
+// Publisher mode (DXB) is not supported
+const DPV1_BYTE1_SUPPORTED: u8 = dpv1_status_byte1::DPV1_MODE
+    | dpv1_status_byte1::FAIL_SAVE_MODE
+    | dpv1_status_byte1::WATCHDOG_TB_1MS;
+
+
+/// Station address and No_Add_Chg kept in `storage`, if it is a valid
+/// slave address.
+fn stored_address(storage: &mut dyn PersistentStorage) -> Option<(u8, bool)> {
+    storage
+        .load_address()
+        .filter(|(t_s, _)| *t_s <= MAX_SLAVE_ADD)
+}
+
+#[allow(dead_code)]
+pub struct PbDpSlave<
+    Serial,
+    DataHandling,
+    const BUF_SIZE: usize,
+    const INPUT_DATA_SIZE: usize,
+    const OUTPUT_DATA_SIZE: usize,
+    const USER_PARA_SIZE: usize,
+    const EXTERN_DIAG_PARA_SIZE: usize,
+    const MODULE_CONFIG_SIZE: usize,
+> {
+    pub(super) hw_interface: Serial,
+    pub(super) data_handling_interface: DataHandling,
+    pub(super) tx_buffer: [u8; BUF_SIZE],
+    pub(super) rx_buffer: [u8; BUF_SIZE],
+
+    pub(super) codec: Codec,
+
+    fdl : FdlConfig,
+
+    slave_state: DpSlaveState,
+
+    input_data: [u8; INPUT_DATA_SIZE],
+    input_data_buffer: [u8; INPUT_DATA_SIZE],
+    output_data: [u8; OUTPUT_DATA_SIZE],
+    output_data_buffer: [u8; OUTPUT_DATA_SIZE],
+    user_para: [u8; USER_PARA_SIZE],
+    extern_diag_para: [u8; EXTERN_DIAG_PARA_SIZE],
+    extern_diag_len: usize,
+    extern_diag_overflow: bool,
+    // Bloecke der Anwendung, danach folgen die Kanalfehler
+    extern_diag_app_len: usize,
+    extern_diag_app_overflow: bool,
+    channel_faults: ChannelFaults,
+    channel_faults_overflow: bool,
+    // neue Diagnose, wird mit Data high angezeigt bis der Master sie abholt
+    new_diag: bool,
+    module_config: [u8; MODULE_CONFIG_SIZE],
+    module_config_len: usize,
+    catalog: Option<ModuleCatalog<'static>>,
+    // jedes Modul belegt mindestens ein Konfigurationsbyte
+    slots: [Slot; MODULE_CONFIG_SIZE],
+    slot_count: usize,
+    input_len: usize,
+    output_len: usize,
+
+    diagnose_status_1: u8,
+    master_addr: u8,
+    // neue Adresse von Set_Slave_Address, gilt erst nach der Kurzquittung
+    new_t_s: Option<u8>,
+    // No_Add_Chg: keine weitere Adressaenderung
+    address_locked: bool,
+    // Adresse fuer den Speicher, geschrieben von store_pending ausserhalb der Antwort
+    pending_address: Option<(u8, bool)>,
+    group: u8,
+
+    source_addr: u8,
+    fcv_activated: bool,
+    fcb_last: bool,
+
+    freeze: bool,
+    sync: bool,
+    watchdog_act: bool,
+    min_tsdr: u8,
+
+    freeze_configured: bool,
+    sync_configured: bool,
+    dpv1_status: Dpv1Status,
+    // Antwort auf Read/Write, wird beim naechsten Poll gesendet
+    msac_c1_response: [u8; DPV1_PDU_SIZE],
+    msac_c1_response_len: usize,
+    // Verbindung eines Klasse 2 Masters und deren Antwort
+    msac_c2: Option<C2Connection>,
+    msac_c2_next_sap: u8,
+    msac_c2_response: [u8; DPV1_PDU_SIZE],
+    msac_c2_response_len: usize,
+    alarms: AlarmQueue,
+
+    last_connection_time: u32,
+    watchdog_time: u32,
+}
+
+impl<
+        Serial,
+        DataHandling,
+        const BUF_SIZE: usize,
+        const INPUT_DATA_SIZE: usize,
+        const OUTPUT_DATA_SIZE: usize,
+        const USER_PARA_SIZE: usize,
+        const EXTERN_DIAG_PARA_SIZE: usize,
+        const MODULE_CONFIG_SIZE: usize,
+    >
+    PbDpSlave<
+        Serial,
+        DataHandling,
+        BUF_SIZE,
+        INPUT_DATA_SIZE,
+        OUTPUT_DATA_SIZE,
+        USER_PARA_SIZE,
+        EXTERN_DIAG_PARA_SIZE,
+        MODULE_CONFIG_SIZE,
+    >
+where
+    Serial: HwInterface,
+    DataHandling: DataHandlingInterface,
+{
+    pub fn new(
+        mut hw_interface: Serial,
+        mut data_handling_interface: DataHandling,
+        mut config: ProfibusConfig,
+        module_config: [u8; MODULE_CONFIG_SIZE],
+    ) -> Self {
+        // gespeicherte Adresse hat Vorrang vor der Konfiguration
+        let mut address_locked = false;
+        if let Some((t_s, locked)) = data_handling_interface
+            .persistent_storage()
+            .and_then(stored_address)
+        {
+            config.codec.t_s = t_s;
+            address_locked = locked;
+        }
+
+        // let input_data = Vec::<u8, InputDatalen>::new();
+        // let output_data = Vec::<u8, OutputDatalen>::new();
+        // let user_para = Vec::<u8, UserParalen>::new();
+        // let extern_diag_para = Vec::<u8, ExternDiagParalen>::new();
+        // let vendor_data = Vec::<u8, VendorDatalen>::new();
+
+        let input_data = [0; INPUT_DATA_SIZE];
+        let input_data_buffer = [0; INPUT_DATA_SIZE];
+        let output_data = [0; OUTPUT_DATA_SIZE];
+        let output_data_buffer = [0; OUTPUT_DATA_SIZE];
+        let user_para = [0; USER_PARA_SIZE];
+        let extern_diag_para = [0; EXTERN_DIAG_PARA_SIZE];
+
+        let mut codec = Codec::default();
+        PbDpSlave::<
+            Serial,
+            DataHandling,
+            BUF_SIZE,
+            INPUT_DATA_SIZE,
+            OUTPUT_DATA_SIZE,
+            USER_PARA_SIZE,
+            EXTERN_DIAG_PARA_SIZE,
+            MODULE_CONFIG_SIZE,
+        >::codec_init(
+            &mut codec,
+            &mut hw_interface,
+            &mut data_handling_interface,
+            config.codec,
+        );
+
+        let current_time = data_handling_interface.millis();
+
+        let mut slots = [Slot::default(); MODULE_CONFIG_SIZE];
+        let slot_count = cfg::slots(&module_config, &mut slots).unwrap_or(0);
+
+        Self {
+            hw_interface,
+            data_handling_interface,
+            tx_buffer: [0; BUF_SIZE],
+            rx_buffer: [0; BUF_SIZE],
+            codec,
+            fdl: config.fdl,
+            slave_state: DpSlaveState::Por,
+            input_data,
+            input_data_buffer,
+            output_data,
+            output_data_buffer,
+            user_para,
+            extern_diag_para,
+            extern_diag_len: 0,
+            extern_diag_overflow: false,
+            extern_diag_app_len: 0,
+            extern_diag_app_overflow: false,
+            channel_faults: ChannelFaults::new(),
+            channel_faults_overflow: false,
+            new_diag: false,
+            module_config,
+            module_config_len: MODULE_CONFIG_SIZE,
+            catalog: None,
+            slots,
+            slot_count,
+            input_len: INPUT_DATA_SIZE,
+            output_len: OUTPUT_DATA_SIZE,
+            diagnose_status_1: sap_diagnose_byte1::STATION_NOT_READY,
+            master_addr: 0xFF,
+            new_t_s: None,
+            address_locked,
+            pending_address: None,
+            group: 0,
+            source_addr: 0xFF,
+            fcv_activated: false,
+            fcb_last: false,
+            freeze: false,
+            sync: false,
+            watchdog_act: false,
+            min_tsdr: 0,
+            freeze_configured: false,
+            sync_configured: false,
+            dpv1_status: Dpv1Status::default(),
+            msac_c1_response: [0; DPV1_PDU_SIZE],
+            msac_c1_response_len: 0,
+            msac_c2: None,
+            msac_c2_next_sap: sap::MSAC_C2_LAST,
+            msac_c2_response: [0; DPV1_PDU_SIZE],
+            msac_c2_response_len: 0,
+            alarms: AlarmQueue::new(),
+            last_connection_time: current_time,
+            watchdog_time: 0xFFFFFF,
+        }
+    }
+
+    /// Slave accepting any combination of the catalog's modules. Input and
+    /// output data are placed slot by slot, see `slots`, and have to fit into
+    /// INPUT_DATA_SIZE and OUTPUT_DATA_SIZE.
+    pub fn new_modular(
+        hw_interface: Serial,
+        data_handling_interface: DataHandling,
+        config: ProfibusConfig,
+        catalog: ModuleCatalog<'static>,
+    ) -> Self {
+        let mut slave = Self::new(
+            hw_interface,
+            data_handling_interface,
+            config,
+            [0; MODULE_CONFIG_SIZE],
+        );
+        slave.catalog = Some(catalog);
+        slave.module_config_len = 0;
+        slave.slot_count = 0;
+        slave.input_len = 0;
+        slave.output_len = 0;
+        slave
+    }
+
+    pub fn access_output(&mut self) -> &mut [u8; OUTPUT_DATA_SIZE] {
+        &mut self.output_data
+    }
+
+    pub fn access_input(&mut self) -> &mut [u8; INPUT_DATA_SIZE] {
+        &mut self.input_data
+    }
+
+    /// Station address, changed by Set_Slave_Address.
+    pub fn station_address(&self) -> u8 {
+        self.codec.config.t_s
+    }
+
+    /// Takes over the station address kept in `storage`, before the bus is
+    /// started. `new` does this with `DataHandlingInterface::persistent_storage`,
+    /// applications which keep the storage apart from the slave call it
+    /// themselves.
+    pub fn restore_address(&mut self, storage: &mut dyn PersistentStorage) {
+        if let Some((t_s, locked)) = stored_address(storage) {
+            self.codec.config.t_s = t_s;
+            self.address_locked = locked;
+        }
+    }
+
+    /// Next value set by the master which is not stored yet, copied into
+    /// `data`. For applications which keep the storage apart from the slave,
+    /// e.g. to write the flash outside of a lock shared with the bus
+    /// interrupts.
+    pub fn take_pending_value(
+        &mut self,
+        data: &mut [u8; MAX_VALUE_LEN],
+    ) -> Option<(StorageKey, usize)> {
+        let (t_s, locked) = self.pending_address.take()?;
+        let value = persistent_storage::address_value(t_s, locked);
+        data[..value.len()].copy_from_slice(&value);
+        Some((StorageKey::Address, value.len()))
+    }
+
+    /// Writes the values set by the master to
+    /// `DataHandlingInterface::persistent_storage`. A flash write takes far
+    /// longer than the slave may take to answer, so the application calls
+    /// this outside of the bus handling, e.g. in its idle loop. Without
+    /// storage the values are dropped.
+    pub fn store_pending(&mut self) -> Result<(), StorageError> {
+        let mut data = [0; MAX_VALUE_LEN];
+        let mut result = Ok(());
+        while let Some((key, len)) = self.take_pending_value(&mut data) {
+            if let Some(storage) = self.data_handling_interface.persistent_storage() {
+                // nach einem Fehler fehlt der Wert beim Neustart
+                result = result.and(storage.store_changed(key, &data[..len]));
+            }
+        }
+        result
+    }
+
+    pub fn slave_state(&self) -> DpSlaveState {
+        self.slave_state
+    }
+
+    /// Config accepted by the last Chk_Cfg, the fixed config without
+    /// catalog.
+    pub fn module_config(&self) -> &[u8] {
+        &self.module_config[..self.module_config_len]
+    }
+
+    /// User parameters of the last Set_Prm, without the DPV1 status bytes.
+    pub fn user_para(&self) -> &[u8; USER_PARA_SIZE] {
+        &self.user_para
+    }
+
+    /// DPV1 status bytes of the last accepted Set_Prm, all zero for a DPV0
+    /// slave.
+    pub fn dpv1_status(&self) -> Dpv1Status {
+        self.dpv1_status
+    }
+
+    /// Queues a DPV1 alarm for `slot` and returns its sequence number. The
+    /// alarm is reported in the diagnostic until the master acknowledges it.
+    pub fn raise_alarm(
+        &mut self,
+        slot: u8,
+        alarm_type: AlarmType,
+        specifier: AlarmSpecifier,
+        data: &[u8],
+    ) -> Result<u8, AlarmError> {
+        if !self.dpv1_status.dpv1_mode() || (self.dpv1_status.alarms() & alarm_type.enable_bit()) == 0
+        {
+            return Err(AlarmError::NotEnabled);
+        }
+        // SD2 Rahmen (9), SAPs (2), 6 Diagnosebytes und externe Diagnose
+        if 17 + EXTERN_DIAG_PARA_SIZE + ALARM_HEADER_SIZE + data.len() > BUF_SIZE {
+            return Err(AlarmError::TooLong);
+        }
+        self.alarms.push(slot, alarm_type, specifier, data)
+    }
+
+    /// Replaces the extended diagnostic and announces it with a high
+    /// priority response. The channel faults follow the blocks of `diag`.
+    /// Blocks beyond `EXTERN_DIAG_PARA_SIZE` are left out and reported as
+    /// diagnostic overflow.
+    pub fn set_ext_diag(&mut self, diag: &ExtDiag) {
+        let len = diag.fitting_len(EXTERN_DIAG_PARA_SIZE);
+        self.extern_diag_para[..len].copy_from_slice(&diag.as_bytes()[..len]);
+        self.extern_diag_app_len = len;
+        self.extern_diag_app_overflow = diag.is_overflow() || len < diag.byte_len();
+        self.update_channel_diag();
+    }
+
+    /// Reports a fault of a channel until `clear_channel_fault`. A new fault
+    /// of the same channel replaces the old one. Returns `false` if
+    /// `MAX_CHANNEL_FAULTS` channels are faulty already, which is reported
+    /// as diagnostic overflow.
+    pub fn channel_fault(&mut self, fault: ChannelDiag) -> bool {
+        match self.channel_faults.set(fault) {
+            Some(changed) => {
+                if changed {
+                    self.update_channel_diag();
+                    self.channel_alarm(AlarmSpecifier::Appears, &fault);
+                }
+                true
+            }
+            None => {
+                self.channel_faults_overflow = true;
+                self.update_channel_diag();
+                false
+            }
+        }
+    }
+
+    pub fn clear_channel_fault(&mut self, slot: u8, channel: u8, direction: ChannelDirection) {
+        if let Some(fault) = self.channel_faults.clear(slot, channel, direction) {
+            if self.channel_faults.iter().next().is_none() {
+                self.channel_faults_overflow = false;
+            }
+            self.update_channel_diag();
+            let specifier = if self.channel_faults.has_slot(slot) {
+                AlarmSpecifier::DisappearsOtherError
+            } else {
+                AlarmSpecifier::Disappears
+            };
+            self.channel_alarm(specifier, &fault);
+        }
+    }
+
+    /// Channels with a fault, in the order they appeared.
+    pub fn channel_faults(&self) -> impl Iterator<Item = &ChannelDiag> {
+        self.channel_faults.iter()
+    }
+
+    /// Appends the channel faults to the blocks of the application.
+    fn update_channel_diag(&mut self) {
+        let mut len = self.extern_diag_app_len;
+        let mut overflow = self.extern_diag_app_overflow || self.channel_faults_overflow;
+        for fault in self.channel_faults.iter() {
+            let entry = fault.encode();
+            match self.extern_diag_para.get_mut(len..len + entry.len()) {
+                Some(dest) => {
+                    dest.copy_from_slice(&entry);
+                    len += entry.len();
+                }
+                None => overflow = true,
+            }
+        }
+        self.extern_diag_len = len;
+        self.extern_diag_overflow = overflow;
+        self.new_diag = true;
+    }
+
+    /// DPV1 diagnostic alarm for an appearing or disappearing channel fault.
+    fn channel_alarm(&mut self, specifier: AlarmSpecifier, fault: &ChannelDiag) {
+        // ohne freigegebenen Diagnosealarm oder bei voller Warteschlange
+        // bleibt es bei der Diagnose
+        self.raise_alarm(fault.slot, AlarmType::Diagnostic, specifier, &fault.encode())
+            .ok();
+    }
+
+    pub fn slots(&self) -> &[Slot] {
+        &self.slots[..self.slot_count]
+    }
+
+    /// DPV1 status bytes of a Set_Prm, `None` if they are missing or ask for
+    /// something the slave does not support.
+    fn dpv1_status_of(&self, pdu: &[u8]) -> Option<Dpv1Status> {
+        if !self.fdl.dpv1 {
+            return Some(Dpv1Status::default());
+        }
+        let status = Dpv1Status(pdu.get(7..10)?.try_into().ok()?);
+        status
+            .is_supported(self.fdl.dpv1_alarms)
+            .then_some(status)
+    }
+
+    fn msac_c1_active(&mut self) -> bool {
+        self.dpv1_status.dpv1_mode()
+            && DpSlaveState::Dxchg == self.slave_state
+            && self.data_handling_interface.record_handling().is_some()
+    }
+
+    /// Runs a Read or Write and keeps the response for the poll.
+    fn msac_c1_request(&mut self, pdu: &[u8]) {
+        self.msac_c1_response_len = dpv1::record_service(
+            self.data_handling_interface.record_handling(),
+            pdu,
+            &mut self.msac_c1_response,
+            Self::max_dpv1_data_len(),
+            false,
+        );
+    }
+
+    fn msac_c2_supported(&mut self) -> bool {
+        self.fdl.dpv1 && self.data_handling_interface.record_handling().is_some()
+    }
+
+    /// Opens the class 2 connection on the next free SAP and keeps the
+    /// Initiate response for the poll. Returns `false` for a malformed request.
+    fn msac_c2_initiate(&mut self, source_addr: u8, ssap: u8, pdu: &[u8]) -> bool {
+        let max_len_data_unit = (DPV1_HEADER_SIZE + Self::max_dpv1_data_len()) as u8;
+        let Some((timeout, response_len)) =
+            dpv1::initiate(pdu, max_len_data_unit, &mut self.msac_c2_response)
+        else {
+            return false;
+        };
+        // jede Verbindung bekommt einen neuen SAP, damit alte Telegramme ins Leere gehen
+        let sap = self.msac_c2_next_sap;
+        self.msac_c2_next_sap = sap.checked_sub(1).unwrap_or(sap::MSAC_C2_LAST);
+        self.msac_c2 = Some(C2Connection {
+            master_addr: source_addr,
+            master_sap: ssap,
+            sap,
+            timeout,
+            last_time: self.data_handling_interface.millis(),
+        });
+        self.msac_c2_response_len = response_len;
+        true
+    }
+
+    /// Sends the pending class 2 response from the SAP of the connection.
+    fn msac_c2_poll(&mut self, source_addr: u8) {
+        match self.msac_c2 {
+            Some(connection) if self.msac_c2_response_len > 0 => {
+                let buf = self.msac_c2_response;
+                self.transmit_message_sd2(
+                    source_addr,
+                    fc_response::DATA_LOW,
+                    Some((connection.master_sap, connection.sap)),
+                    &buf[..self.msac_c2_response_len],
+                    &[0; 0],
+                );
+                self.msac_c2_response_len = 0;
+            }
+            // Antwort noch nicht bereit
+            _ => self.transmit_message_sc(),
+        }
+    }
+
+    /// Closes the class 2 connection if its master stayed silent for longer
+    /// than Send_Timeout.
+    fn msac_c2_supervision(&mut self) {
+        if let Some(connection) = self.msac_c2 {
+            if connection.is_timed_out(self.data_handling_interface.millis()) {
+                self.msac_c2 = None;
+                self.msac_c2_response_len = 0;
+            }
+        }
+    }
+
+    // SD2 Rahmen (9), SAPs (2) und Kopf muessen mit in den Sendepuffer
+    fn max_dpv1_data_len() -> usize {
+        BUF_SIZE
+            .saturating_sub(11 + DPV1_HEADER_SIZE)
+            .min(DPV1_PDU_SIZE - DPV1_HEADER_SIZE)
+    }
+
+    /// Checks a Chk_Cfg and takes it over if the slave is modular.
+    fn check_config(&mut self, config: &[u8]) -> bool {
+        let Some(catalog) = self.catalog else {
+            return self.module_config[..] == config[..]
+                && cfg::io_len(config) == Ok((INPUT_DATA_SIZE, OUTPUT_DATA_SIZE));
+        };
+        // ohne Module kein Data Exchange
+        if config.is_empty() || config.len() > MODULE_CONFIG_SIZE {
+            return false;
+        }
+        let mut slots = [Slot::default(); MODULE_CONFIG_SIZE];
+        let Ok(slot_count) = catalog.split(config, &mut slots) else {
+            return false;
+        };
+        let input_len: usize = slots[..slot_count].iter().map(|slot| slot.input_len).sum();
+        let output_len: usize = slots[..slot_count].iter().map(|slot| slot.output_len).sum();
+        if input_len > INPUT_DATA_SIZE || output_len > OUTPUT_DATA_SIZE {
+            return false;
+        }
+        self.module_config[..config.len()].copy_from_slice(config);
+        self.module_config_len = config.len();
+        self.slots = slots;
+        self.slot_count = slot_count;
+        self.input_len = input_len;
+        self.output_len = output_len;
+        true
+    }
+
+    /// GSD description with the ident number, module config and parameter
+    /// and diagnostic lengths of this slave. Names and baudrates are up to
+    /// the application.
+    pub fn gsd_config(&self) -> GsdConfig<'_> {
+        let gsd_config = GsdConfig::default()
+            .ident_number(u16::from_be_bytes([self.fdl.ident_high, self.fdl.ident_low]))
+            .user_prm_data_len(USER_PARA_SIZE)
+            .ext_diag_len(EXTERN_DIAG_PARA_SIZE)
+            .dpv1(self.fdl.dpv1)
+            .max_record_len(Self::max_dpv1_data_len())
+            .alarms(self.fdl.dpv1_alarms);
+        match self.catalog {
+            Some(catalog) => gsd_config
+                .modules(catalog)
+                .max_io_len(INPUT_DATA_SIZE, OUTPUT_DATA_SIZE),
+            None => gsd_config.module_config(&self.module_config),
+        }
+    }
+
+    pub(super) fn fdl_handle_data(
+        &mut self,
+        source_addr: u8,
+        destination_addr: u8,
+        function_code: u8,
+        sap: Option<(u8, u8)>,
+        pdu: &[u8],
+    ) -> bool {
+        let mut response: bool = false;
+        self.last_connection_time = self.data_handling_interface.millis(); // letzte Zeit eines Telegramms sichern
+        // an unanswered telegram must not change the FCB state, the master repeats it
+        let fcb_state = (self.fcv_activated, self.fcb_last, self.source_addr);
+
+                if (function_code & 0x30) == fc_request::FCB
+                // Startbedingung
+                {
+                    self.fcv_activated = true;
+                    self.fcb_last = true;
+                } else if (function_code & fc_request::FCV) != 0 && self.fcv_activated {
+                    if source_addr != self.source_addr {
+                        // new address so fcv is deactivated
+                        self.fcv_activated = false;
+                    } else if ((function_code & fc_request::FCB) != 0) == self.fcb_last {
+                        // FCB is identical, repeat message
+                        self.transmit();
+                        return true;
+                    } else {
+                        // save new FCB bit
+                        self.fcb_last = !self.fcb_last;
+                    }
+                } else if (function_code & fc_request::FCV) != 0
+                // wenn es keine Startbedingung gibt und wir nicht eingeschaltet sind, können wir fcv ausschalten
+                {
+                    self.fcv_activated = false;
+                }
+
+                // letzte Adresse sichern
+                self.source_addr = source_addr;
+
+                // FCV und FCB loeschen, da vorher überprüft
+                let function_code = function_code & 0xCF;
+
+                // Service Access Point erkannt?
+                if let Some((dsap_data, ssap_data)) = sap {
+                    // Ablauf Reboot:
+                    // 1) SSAP 62 -> DSAP 60 (Get Diagnostics Request)
+                    // 2) SSAP 62 -> DSAP 61 (Set Parameters Request)
+                    // 3) SSAP 62 -> DSAP 62 (Check Config Request)
+                    // 4) SSAP 62 -> DSAP 60 (Get Diagnostics Request)
+                    // 5) Data Exchange Request (normaler Zyklus)
+
+                    // Siehe Felser 8/2009 Kap. 4.1
+                    match dsap_data {
+                        // PDU passt nicht zum Dienst: negative Quittung statt hinter die Daten zu lesen
+                        sap::GLOBAL_CONTROL if pdu.len() != 2 => {
+                            // Broadcast (SDN), keine Antwort moeglich
+                        }
+
+                        sap::SET_SLAVE_ADR if pdu.len() < 4 => {
+                            self.transmit_message_sd1(source_addr, fc_response::USER_ERROR);
+                            response = true;
+                        }
+
+                        sap::SET_PRM if pdu.len() < 7 => {
+                            self.transmit_message_sd1(source_addr, fc_response::USER_ERROR);
+                            response = true;
+                        }
+
+                        sap::SET_SLAVE_ADR => {
+                            // Set Slave Address (SSAP 62 -> DSAP 55)
+                            // Siehe Felser 8/2009 Kap. 4.2
+                            // SAP nur vor der Parametrierung und ohne Sperre aktiv
+                            let function_code = if !matches!(self.slave_state, DpSlaveState::Por | DpSlaveState::Wrpm)
+                                || self.address_locked
+                            {
+                                Some(fc_response::SAP_NOT_ACTIVE)
+                            } else if pdu[0] > MAX_SLAVE_ADD
+                                || pdu[1] != self.fdl.ident_high
+                                || pdu[2] != self.fdl.ident_low
+                            {
+                                Some(fc_response::USER_ERROR)
+                            } else {
+                                // erst antworten, gespeichert wird mit store_pending
+                                self.new_t_s = Some(pdu[0]);
+                                self.address_locked = pdu[3] != 0;
+                                self.pending_address = Some((pdu[0], self.address_locked));
+                                None
+                            };
+                            match function_code {
+                                Some(function_code) => self.transmit_message_sd1(source_addr, function_code),
+                                None => self.transmit_message_sc(),
+                            }
+                            response = true;
+                        }
+
+                        sap::GLOBAL_CONTROL => {
+                            // Global Control Request (SSAP 62 -> DSAP 58)
+                            // Siehe Felser 8/2009 Kap. 4.6.2
+
+                            // Wenn "Clear Data" high, dann SPS CPU auf "Stop"
+                            if (pdu[0] & sap_global_control::CLEAR_DATA) != 0 {
+                                self.data_handling_interface.error_led_on(); // Status "SPS nicht bereit"
+                            } else {
+                                self.data_handling_interface.error_led_off(); // Status "SPS OK"
+                            }
+
+                            // Gruppe berechnen
+                            // for (cnt = 0;  pb_uart_buffer[10] != 0; cnt++) pb_uart_buffer[10]>>=1;
+
+                            // Wenn Befehl fuer uns ist
+                            if (pdu[1] & self.group) != 0
+                            //(cnt == group)
+                            {
+                                if (pdu[0] & sap_global_control::UNFREEZE) != 0 {
+                                    // FREEZE Zustand loeschen
+                                    self.freeze = false;
+                                    self.data_handling_interface
+                                        .data_processing(&mut self.input_data[..self.input_len], &[0; 0]);
+                                    //TODO: only a copy is given
+                                    self.input_data_buffer = self.input_data;
+                                } else if (pdu[0] & sap_global_control::UNSYNC) != 0 {
+                                    // SYNC Zustand loeschen
+                                    self.sync = false;
+                                    self.output_data = self.output_data_buffer;
+                                    self.data_handling_interface
+                                        .data_processing(&mut [0; 0], &self.output_data[..self.output_len]);
+                                } else if (pdu[0] & sap_global_control::FREEZE) != 0 {
+                                    // Eingaenge nicht mehr neu einlesen
+                                    self.freeze = true;
+                                    self.data_handling_interface
+                                        .data_processing(&mut self.input_data[..self.input_len], &[0; 0]);
+                                    //TODO: only a copy is given
+                                    self.input_data_buffer = self.input_data;
+                                } else if (pdu[0] & sap_global_control::SYNC) != 0 {
+                                    // Ausgaenge nur bei SYNC Befehl setzen
+                                    self.sync = true;
+                                    self.output_data = self.output_data_buffer;
+                                    self.data_handling_interface
+                                        .data_processing(&mut [0; 0], &self.output_data[..self.output_len]);
+                                }
+                            }
+                        }
+
+                        sap::SLAVE_DIAGNOSTIC => {
+                            // Get Diagnostics Request (SSAP 62 -> DSAP 60)
+                            // Siehe Felser 8/2009 Kap. 4.5.2
+
+                            // Nach dem Erhalt der Diagnose wechselt der DP-Slave vom Zustand
+                            // "Power on Reset" (POR) in den Zustand "Wait Parameter" (WPRM)
+
+                            // Am Ende der Initialisierung (Zustand "Data Exchange" (DXCHG))
+                            // sendet der Master ein zweites mal ein Diagnostics Request um die
+                            // korrekte Konfiguration zu pruefen
+                            // m_printfunc((int)function_code);
+                            // m_printfunc(REQUEST_ + SRD_HIGH);
+                            if (function_code == (fc_request::REQUEST + fc_request::SRD_HIGH))
+                                || (function_code == (fc_request::REQUEST + fc_request::SRD_LOW))
+                            {
+                                // Erste Diagnose Abfrage (Aufruf Telegramm)
+                                // 6 Diagnosebytes und der aelteste unquittierte Alarm
+                                let mut diagnose_data: [u8; 6 + MAX_ALARM_BLOCK_LEN] =
+                                    [0; 6 + MAX_ALARM_BLOCK_LEN];
+                                let mut diagnose_len = 6;
+                                diagnose_data[0] = self.diagnose_status_1; // Status 1
+                                if let Some(alarm) = self.alarms.first() {
+                                    diagnose_data[0] |= sap_diagnose_byte1::EXT_DIAG;
+                                    alarm.write_block(&mut diagnose_data[6..]);
+                                    diagnose_len += alarm.block_len();
+                                }
+                                if DpSlaveState::Por == self.slave_state {
+                                    diagnose_data[1] = sap_diagnose_byte2::STATUS_2_DEFAULT
+                                        + sap_diagnose_byte2::PRM_REQ
+                                        + 0x04; // Status 2
+                                    diagnose_data[3] = MASTER_ADD_DEFAULT; // Adresse Master
+                                } else {
+                                    diagnose_data[1] = sap_diagnose_byte2::STATUS_2_DEFAULT + 0x04; // Status 2
+                                    diagnose_data[3] = self.master_addr;
+                                    // Adresse Master
+                                }
+
+                                if self.watchdog_act {
+                                    diagnose_data[1] |= sap_diagnose_byte2::WD_ON;
+                                }
+
+                                if self.freeze_configured {
+                                    diagnose_data[1] |= sap_diagnose_byte2::FREEZE_MODE;
+                                }
+
+                                if self.sync_configured {
+                                    diagnose_data[1] |= sap_diagnose_byte2::SYNC_MODE;
+                                }
+
+                                // Status 3: Diagnose passte nicht in Max_Diag_Data_Len
+                                diagnose_data[2] = if self.extern_diag_overflow {
+                                    sap_diagnose_byte3::DIAG_SIZE_ERROR
+                                } else {
+                                    sap_diagnose_byte3::DIAG_SIZE_OK
+                                };
+                                diagnose_data[4] = self.fdl.ident_high; // Ident high
+                                diagnose_data[5] = self.fdl.ident_low; // Ident low
+                                if self.extern_diag_len > 0 {
+                                    diagnose_data[0] |= sap_diagnose_byte1::EXT_DIAG;
+                                }
+                                let buf = self.extern_diag_para;
+                                self.transmit_message_sd2(
+                                    source_addr,
+                                    fc_response::DATA_LOW,
+                                    Some((ssap_data, dsap_data)),
+                                    &diagnose_data[..diagnose_len],
+                                    &buf[..self.extern_diag_len],
+                                );
+                                self.new_diag = false;
+                                response = true;
+                            }
+
+                            // Status aendern
+                            if DpSlaveState::Por == self.slave_state {
+                                self.slave_state = DpSlaveState::Wrpm;
+                            }
+                        }
+
+                        sap::SET_PRM => {
+                            // Set Parameters Request (SSAP 62 -> DSAP 61)
+                            // Siehe Felser 8/2009 Kap. 4.3.1
+
+                            // Nach dem Erhalt der Parameter wechselt der DP-Slave vom Zustand
+                            // "Wait Parameter" (WPRM) in den Zustand "Wait Configuration" (WCFG)
+                            let ident_is_valid =
+                                (pdu[4] == self.fdl.ident_high) && (pdu[5] == self.fdl.ident_low);
+                            let dpv1_status = self.dpv1_status_of(pdu);
+                            if ident_is_valid && dpv1_status.is_none() {
+                                // DPV1 Funktionen werden nicht unterstuetzt: Quittung,
+                                // aber Prm_Fault in der Diagnose und weiter in WPRM
+                                self.diagnose_status_1 |= sap_diagnose_byte1::PRM_FAULT;
+                                self.transmit_message_sc();
+                                response = true;
+                            } else if let (true, Some(dpv1_status)) = (ident_is_valid, dpv1_status)
+                            {
+                                self.master_addr = source_addr;
+
+                                if (pdu[0] & sap_set_parameter_request::ACTIVATE_WATCHDOG)
+                                    != 0
+                                // Watchdog aktivieren
+                                {
+                                    self.watchdog_act = true;
+                                } else {
+                                    self.watchdog_act = false;
+                                }
+
+                                self.freeze_configured = (pdu[0] & sap_set_parameter_request::ACTIVATE_FREEZE) != 0;
+
+                                self.sync_configured = (pdu[0] & sap_set_parameter_request::ACTIVATE_SYNC) != 0;
+
+                                // watchdog1 = m_pbUartRxBuffer[10];
+                                // watchdog2 = m_pbUartRxBuffer[11];
+
+                                let watchdog_base = if dpv1_status.watchdog_1ms() { 1 } else { 10 };
+                                self.watchdog_time =
+                                    u32::from(pdu[1]) * u32::from(pdu[2]) * watchdog_base;
+
+                                if pdu[3] > 10 {
+                                    self.min_tsdr = pdu[3] - 11;
+                                } else {
+                                    self.min_tsdr = 0;
+                                }
+
+                                self.fdl.ident_high = pdu[4];
+                                self.fdl.ident_low = pdu[5];
+
+                                self.group = pdu[6]; // wir speichern das gesamte Byte und sparen uns damit die Schleife. Ist unsere Gruppe gemeint, ist die Verundung von Gruppe und Empfang ungleich 0
+
+                                self.dpv1_status = dpv1_status;
+                                self.msac_c1_response_len = 0;
+                                self.alarms.clear();
+                                self.diagnose_status_1 &= !sap_diagnose_byte1::PRM_FAULT;
+
+                                // User Parameter einlesen
+                                if !self.user_para.is_empty() {
+                                    // User Parameter folgen auf 7 Parameter Bytes (+ 3 DPV1 Statusbytes)
+                                    let user_para_start: usize = if self.fdl.dpv1 { 10 } else { 7 };
+                                    let user_para_len: usize = pdu.len() - user_para_start;
+                                    if user_para_len <= self.user_para.len() {
+                                        self.user_para[..user_para_len].copy_from_slice(&pdu[user_para_start..]);
+                                    }
+                                }
+                                // Kurzquittung
+                                self.transmit_message_sc();
+                                response = true;
+                                // m_printfunc("Quittung");
+                                if DpSlaveState::Wrpm == self.slave_state {
+                                    self.slave_state = DpSlaveState::Wcfg;
+                                }
+                            }
+                        }
+
+                        sap::GET_CFG => {
+                            // Send configuration to master (SSAP 62 -> DSAP 59)
+                            // see Felser 8/2009 chapter 4.4.3.1
+
+                            if (function_code == (fc_request::REQUEST + fc_request::SRD_HIGH))
+                                || (function_code == (fc_request::REQUEST + fc_request::SRD_LOW))
+                            {
+                                // Erste Diagnose Abfrage (Aufruf Telegramm)
+                                let mut buf: [u8; MODULE_CONFIG_SIZE] = [0; MODULE_CONFIG_SIZE];
+                                buf.copy_from_slice(&self.module_config[..]);
+                                self.transmit_message_sd2(
+                                    source_addr,
+                                    fc_response::DATA_LOW,
+                                    Some((ssap_data, dsap_data)),
+                                    &buf[..self.module_config_len],
+                                    &[0; 0],
+                                );
+                                response = true;
+                            }
+                        }
+
+                        sap::RD_INP | sap::RD_OUTP
+                            if (function_code == (fc_request::REQUEST + fc_request::SRD_HIGH))
+                                || (function_code == (fc_request::REQUEST + fc_request::SRD_LOW)) =>
+                        {
+                            // Read Inputs / Read Outputs (SSAP 62 -> DSAP 56/57)
+                            // Jeder Master, z.B. ein Klasse 2 Master, darf das Prozessabbild lesen,
+                            // solange der Slave im Data Exchange ist
+                            if !pdu.is_empty() {
+                                self.transmit_message_sd1(source_addr, fc_response::USER_ERROR);
+                            } else if DpSlaveState::Dxchg != self.slave_state {
+                                self.transmit_message_sd1(source_addr, fc_response::SAP_NOT_ACTIVE);
+                            } else if dsap_data == sap::RD_INP {
+                                let mut buf: [u8; INPUT_DATA_SIZE] = [0; INPUT_DATA_SIZE];
+                                buf.copy_from_slice(&self.input_data_buffer[..]);
+                                self.transmit_message_sd2(
+                                    source_addr,
+                                    fc_response::DATA_LOW,
+                                    Some((ssap_data, dsap_data)),
+                                    &buf[..self.input_len],
+                                    &[0; 0],
+                                );
+                            } else {
+                                let mut buf: [u8; OUTPUT_DATA_SIZE] = [0; OUTPUT_DATA_SIZE];
+                                buf.copy_from_slice(&self.output_data[..]);
+                                self.transmit_message_sd2(
+                                    source_addr,
+                                    fc_response::DATA_LOW,
+                                    Some((ssap_data, dsap_data)),
+                                    &buf[..self.output_len],
+                                    &[0; 0],
+                                );
+                            }
+                            response = true;
+                        }
+
+                        sap::CHK_CFG => {
+                            // Check Config Request (SSAP 62 -> DSAP 62)
+                            // Siehe Felser 8/2009 Kap. 4.4.1
+
+                            // Nach dem Erhalt der Konfiguration wechselt der DP-Slave vom Zustand
+                            // "Wait Configuration" (WCFG) in den Zustand "Data Exchange" (DXCHG)
+
+                            // IO Konfiguration:
+                            // Kompaktes Format fuer max. 16/32 Byte IO
+                            // Spezielles Format fuer max. 64/132 Byte IO
+
+                            // Je nach PDU Datengroesse mehrere Bytes auswerten
+                            // LE/LEr - (DA+SA+FC+DSAP+SSAP) = Anzahl Config Bytes
+                            // Die Konfiguration muss der eigenen (bzw. dem Katalog)
+                            // entsprechen und in die Datenpuffer passen
+                            let config_is_valid = self.check_config(pdu);
+                            if !config_is_valid {
+                                self.diagnose_status_1 |= sap_diagnose_byte1::CFG_FAULT;
+                            } else {
+                                self.diagnose_status_1 &= !(sap_diagnose_byte1::STATION_NOT_READY
+                                    + sap_diagnose_byte1::CFG_FAULT);
+                            }
+
+                            // Kurzquittung
+                            self.transmit_message_sc();
+                            response = true;
+                            if config_is_valid && DpSlaveState::Wcfg == self.slave_state {
+                                self.slave_state = DpSlaveState::Dxchg;
+                            }
+                        }
+
+                        sap::MSAC_C1 if !self.msac_c1_active() => {
+                            // nur im DPV1 Modus waehrend Data Exchange
+                            if (function_code == (fc_request::REQUEST + fc_request::SRD_HIGH))
+                                || (function_code == (fc_request::REQUEST + fc_request::SRD_LOW))
+                            {
+                                self.transmit_message_sd1(source_addr, fc_response::SAP_NOT_ACTIVE);
+                                response = true;
+                            }
+                        }
+
+                        sap::MSAC_C1 => {
+                            // DPV1 Read/Write (SSAP 51 -> DSAP 51)
+                            // Der Auftrag wird quittiert, die Antwort holt der Master mit
+                            // einem Poll ohne Daten ab
+                            if (function_code == (fc_request::REQUEST + fc_request::SRD_HIGH))
+                                || (function_code == (fc_request::REQUEST + fc_request::SRD_LOW))
+                            {
+                                if !pdu.is_empty() {
+                                    self.msac_c1_request(pdu);
+                                    self.transmit_message_sc();
+                                } else if self.msac_c1_response_len > 0 {
+                                    let buf = self.msac_c1_response;
+                                    self.transmit_message_sd2(
+                                        source_addr,
+                                        fc_response::DATA_LOW,
+                                        Some((ssap_data, dsap_data)),
+                                        &buf[..self.msac_c1_response_len],
+                                        &[0; 0],
+                                    );
+                                    self.msac_c1_response_len = 0;
+                                } else {
+                                    // Antwort noch nicht bereit
+                                    self.transmit_message_sc();
+                                }
+                                response = true;
+                            }
+                        }
+
+                        sap::MSAL1_ALARM
+                            if !self.dpv1_status.dpv1_mode()
+                                || DpSlaveState::Dxchg != self.slave_state =>
+                        {
+                            // nur im DPV1 Modus waehrend Data Exchange
+                            if (function_code == (fc_request::REQUEST + fc_request::SRD_HIGH))
+                                || (function_code == (fc_request::REQUEST + fc_request::SRD_LOW))
+                            {
+                                self.transmit_message_sd1(source_addr, fc_response::SAP_NOT_ACTIVE);
+                                response = true;
+                            }
+                        }
+
+                        sap::MSAL1_ALARM => {
+                            // DPV1 Alarm_Ack (SSAP 51 -> DSAP 50)
+                            // Der quittierte Alarm wird aus der Diagnose genommen
+                            if (function_code == (fc_request::REQUEST + fc_request::SRD_HIGH))
+                                || (function_code == (fc_request::REQUEST + fc_request::SRD_LOW))
+                            {
+                                match *pdu {
+                                    [dpv1_function::ALARM_ACK, slot, alarm_type, specifier]
+                                        if self.alarms.acknowledge(slot, alarm_type, specifier) =>
+                                    {
+                                        self.transmit_message_sc();
+                                    }
+                                    _ => self.transmit_message_sd1(source_addr, fc_response::USER_ERROR),
+                                }
+                                response = true;
+                            }
+                        }
+
+                        sap::MSAC_C2_INITIATE if !self.msac_c2_supported() => {
+                            // nur fuer DPV1 Slaves mit Datensaetzen
+                            if (function_code == (fc_request::REQUEST + fc_request::SRD_HIGH))
+                                || (function_code == (fc_request::REQUEST + fc_request::SRD_LOW))
+                            {
+                                self.transmit_message_sd1(source_addr, fc_response::SAP_NOT_ACTIVE);
+                                response = true;
+                            }
+                        }
+
+                        sap::MSAC_C2_INITIATE => {
+                            // DPV1 Initiate Klasse 2 Master (SSAP x -> DSAP 49)
+                            // Die Antwort holt der Master mit einem Poll ohne Daten ab,
+                            // sie kommt vom SAP der neuen Verbindung
+                            if (function_code == (fc_request::REQUEST + fc_request::SRD_HIGH))
+                                || (function_code == (fc_request::REQUEST + fc_request::SRD_LOW))
+                            {
+                                if pdu.is_empty() {
+                                    self.msac_c2_poll(source_addr);
+                                } else if self
+                                    .msac_c2
+                                    .is_some_and(|connection| connection.master_addr != source_addr)
+                                {
+                                    // nur eine Verbindung, ein neues Initiate desselben
+                                    // Masters ersetzt sie
+                                    self.transmit_message_sd1(source_addr, fc_response::NO_RESOURCE);
+                                } else if self.msac_c2_initiate(source_addr, ssap_data, pdu) {
+                                    self.transmit_message_sc();
+                                } else {
+                                    self.transmit_message_sd1(source_addr, fc_response::USER_ERROR);
+                                }
+                                response = true;
+                            }
+                        }
+
+                        dsap if self.msac_c2.is_some_and(|connection| {
+                            connection.is_addressed(source_addr, dsap, ssap_data)
+                        }) =>
+                        {
+                            // DPV1 Read/Write/Data_Transport/Abort auf dem SAP der Verbindung,
+                            // jedes Telegramm startet die Zeitueberwachung neu
+                            if (function_code == (fc_request::REQUEST + fc_request::SRD_HIGH))
+                                || (function_code == (fc_request::REQUEST + fc_request::SRD_LOW))
+                            {
+                                let now = self.data_handling_interface.millis();
+                                if let Some(connection) = self.msac_c2.as_mut() {
+                                    connection.last_time = now;
+                                }
+                                if pdu.is_empty() {
+                                    self.msac_c2_poll(source_addr);
+                                } else if pdu[0] == dpv1_function::ABORT {
+                                    self.msac_c2 = None;
+                                    self.msac_c2_response_len = 0;
+                                    self.transmit_message_sc();
+                                } else {
+                                    self.msac_c2_response_len = dpv1::record_service(
+                                        self.data_handling_interface.record_handling(),
+                                        pdu,
+                                        &mut self.msac_c2_response,
+                                        Self::max_dpv1_data_len(),
+                                        true,
+                                    );
+                                    self.transmit_message_sc();
+                                }
+                                response = true;
+                            }
+                        }
+
+                        _ => {
+                            // SAP wird nicht unterstuetzt
+                            if (function_code == (fc_request::REQUEST + fc_request::SRD_HIGH))
+                                || (function_code == (fc_request::REQUEST + fc_request::SRD_LOW))
+                            {
+                                self.transmit_message_sd1(source_addr, fc_response::SAP_NOT_ACTIVE);
+                                response = true;
+                            }
+                        }
+                    } // Switch DSAP_data Ende
+                }
+                // Ziel: Slave Adresse, but no SAP
+                else if destination_addr == self.codec.config.t_s {
+                    // Status Abfrage
+
+                    if function_code == (fc_request::REQUEST + fc_request::FDL_STATUS) {
+                        self.transmit_message_sd1(source_addr, fc_response::FDL_STATUS_OK);
+                        response = true;
+                    }
+                    // Master sendet Ausgangsdaten und verlangt Eingangsdaten (Send and Request Data)
+                    /*
+                    else if (function_code == (REQUEST_ + FCV_ + SRD_HIGH) ||
+                             function_code == (REQUEST_ + FCV_ + FCB_ + SRD_HIGH))
+                    {
+                     */
+                    else if function_code == (fc_request::REQUEST + fc_request::SRD_HIGH)
+                        || function_code == (fc_request::REQUEST + fc_request::SRD_LOW)
+                    {   //TODO
+                        let output_data_len = pdu.len();
+
+                        if self.sync_configured && self.sync
+                        // write data in output_register when sync
+                        {
+                            if self.output_len > 0 {
+                                if output_data_len == self.output_len {
+                                    self.output_data_buffer[..output_data_len].copy_from_slice(pdu);
+                                } else if output_data_len == 0 && self.dpv1_status.fail_safe() {
+                                    // Master im Zustand Clear (Fail Safe): sichere Ausgaenge
+                                    self.output_data_buffer.fill(0);
+                                }
+                            }
+                        } else
+                        // normaler Betrieb
+                        {
+                            if self.output_len > 0 {
+                                if output_data_len == self.output_len {
+                                    self.output_data_buffer[..output_data_len].copy_from_slice(pdu);
+                                } else if output_data_len == 0 && self.dpv1_status.fail_safe() {
+                                    // Master im Zustand Clear (Fail Safe): sichere Ausgaenge
+                                    self.output_data_buffer.fill(0);
+                                }
+                            }
+                            self.output_data = self.output_data_buffer;
+                            self.data_handling_interface
+                                .data_processing(&mut [0; 0], &self.output_data[..self.output_len]);
+                        }
+
+                        if !(self.freeze_configured && self.freeze)
+                        // normaler Betrieb
+                        {
+                            self.data_handling_interface
+                                .data_processing(&mut self.input_data[..self.input_len], &[0; 0]);
+                            self.input_data_buffer = self.input_data;
+                            if !self.input_data.is_empty() {
+                                // self.input_data[0] = 1;
+                            }
+                        }
+
+                        // Diagnose Abfrage mit hoher Prioritaet anfordern
+                        let function_code = if (self.diagnose_status_1 & sap_diagnose_byte1::EXT_DIAG) != 0
+                            || self.new_diag
+                            || self.alarms.first().is_some()
+                        {
+                            fc_response::DATA_HIGH
+                        } else {
+                            fc_response::DATA_LOW
+                        };
+                        if self.input_len > 0 {
+                            let mut buf: [u8; INPUT_DATA_SIZE] = [0; INPUT_DATA_SIZE];
+                            buf.copy_from_slice(&self.input_data_buffer[..]);
+                            self.transmit_message_sd2(source_addr, function_code, None, &buf[..self.input_len], &[0; 0]);
+                        } else if function_code == fc_response::DATA_HIGH {
+                            self.transmit_message_sd1(source_addr, function_code);
+                        } else {
+                            // Kurzquittung
+                            self.transmit_message_sc();
+                        }
+                        response = true;
+                    }
+                }
+                if !response {
+                    (self.fcv_activated, self.fcb_last, self.source_addr) = fcb_state;
+                }
+                    response
+    }
+
+    pub(super) fn fdl_timer_call(&mut self) {
+        self.msac_c2_supervision();
+        // Kurzquittung ist gesendet, neue Adresse uebernehmen
+        if matches!(self.codec.stream_state, StreamState::WaitSyn | StreamState::WaitData) {
+            if let Some(t_s) = self.new_t_s.take() {
+                self.codec.config.t_s = t_s;
+            }
+        }
+        if self.watchdog_act
+            && self
+                .data_handling_interface
+                .millis()
+                .wrapping_sub(self.last_connection_time)
+                > self.watchdog_time
+        {
+            self.output_data.fill(0);
+            //TODO:
+            // std::vector<uint8_t> unUsed;
+            // m_datafunc(m_outputReg, unUsed); // outputs,inputs
+            self.data_handling_interface
+                .data_processing(&mut [0; 0], &self.output_data[..self.output_len]);
+        }
+    }
+}
@@ -0,0 +1,149 @@
+/*********************************************************************
+ * DPV1 acyclic services
+ *
+ * Copyright (C) 2023 Marcel Maage
+ *
+ * This library is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2.1 of the License, or (at your option) any later version.
+ *
+ * This library is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * LICENSE file for more details.
+ */
+
+use crate::record_handling_interface::{RecordError, RecordHandlingInterface, MAX_RECORD_LEN};
+use crate::types::{dpv1_error, dpv1_function};
+
+// Function_Num, Slot, Index, Length
+pub(super) const DPV1_HEADER_SIZE: usize = 4;
+pub(super) const DPV1_PDU_SIZE: usize = DPV1_HEADER_SIZE + MAX_RECORD_LEN;
+// Function_Num, Reserved (3), Send_Timeout (2), Features_Supported (2),
+// Profile_Features_Supported (2), Profile_Ident_Number (2), S_Type, S_Len, D_Type, D_Len
+const INITIATE_REQUEST_SIZE: usize = 16;
+// Features_Supported_1 Bit 0: Read/Write
+const FEATURES_SUPPORTED_1: u8 = 0x01;
+
+/// Runs a Read, Write or (for class 2 only) Data_Transport and writes the
+/// response PDU into `response`. Returns the length of the response.
+pub(super) fn record_service(
+    records: Option<&mut dyn RecordHandlingInterface>,
+    pdu: &[u8],
+    response: &mut [u8; DPV1_PDU_SIZE],
+    max_data_len: usize,
+    data_transport: bool,
+) -> usize {
+    let max_data_len = max_data_len.min(MAX_RECORD_LEN);
+    let result = match (records, pdu) {
+        (Some(records), &[dpv1_function::READ, slot, index, len]) => {
+            let len = usize::from(len).min(max_data_len);
+            records
+                .read_record(slot, index, &mut response[DPV1_HEADER_SIZE..][..len])
+                .map(|read_len| (read_len.min(len), read_len.min(len)))
+        }
+        (Some(records), &[dpv1_function::WRITE, slot, index, len, ref data @ ..]) => {
+            if usize::from(len) == data.len() {
+                records
+                    .write_record(slot, index, data)
+                    .map(|_| (data.len(), 0))
+            } else {
+                Err(RecordError::WriteLength)
+            }
+        }
+        (Some(records), &[dpv1_function::DATA_TRANSPORT, slot, index, len, ref data @ ..])
+            if data_transport =>
+        {
+            if usize::from(len) == data.len() {
+                records
+                    .data_transport(
+                        slot,
+                        index,
+                        data,
+                        &mut response[DPV1_HEADER_SIZE..][..max_data_len],
+                    )
+                    .map(|len| (len.min(max_data_len), len.min(max_data_len)))
+            } else {
+                Err(RecordError::WriteLength)
+            }
+        }
+        (_, &[dpv1_function::DATA_TRANSPORT, ..]) if data_transport => {
+            Err(RecordError::InvalidParameter)
+        }
+        (_, &[dpv1_function::READ | dpv1_function::WRITE, ..]) => {
+            Err(RecordError::InvalidParameter)
+        }
+        _ => Err(RecordError::FeatureNotSupported),
+    };
+    match result {
+        // Length und Anzahl Datenbytes der Antwort
+        Ok((len, data_len)) => {
+            response[..DPV1_HEADER_SIZE].copy_from_slice(&pdu[..DPV1_HEADER_SIZE]);
+            response[3] = len as u8;
+            DPV1_HEADER_SIZE + data_len
+        }
+        Err(error) => {
+            let (error_code_1, error_code_2) = error.codes();
+            response[0] = pdu.first().copied().unwrap_or_default() | dpv1_function::ERROR;
+            response[1] = dpv1_error::DECODE_DPV1;
+            response[2] = error_code_1;
+            response[3] = error_code_2;
+            DPV1_HEADER_SIZE
+        }
+    }
+}
+
+/// MSAC_C2 connection to a class 2 master.
+#[derive(Clone, Copy)]
+pub(super) struct C2Connection {
+    pub(super) master_addr: u8,
+    pub(super) master_sap: u8,
+    /// SAP of the slave assigned to the connection
+    pub(super) sap: u8,
+    /// Send_Timeout in ms
+    pub(super) timeout: u32,
+    pub(super) last_time: u32,
+}
+
+impl C2Connection {
+    pub(super) fn is_addressed(&self, source_addr: u8, dsap: u8, ssap: u8) -> bool {
+        source_addr == self.master_addr && dsap == self.sap && ssap == self.master_sap
+    }
+
+    pub(super) fn is_timed_out(&self, now: u32) -> bool {
+        now.wrapping_sub(self.last_time) > self.timeout
+    }
+}
+
+/// Checks an Initiate request and writes the positive response into
+/// `response`. Returns Send_Timeout in ms and the length of the response,
+/// `None` if the request is malformed.
+pub(super) fn initiate(
+    pdu: &[u8],
+    max_len_data_unit: u8,
+    response: &mut [u8; DPV1_PDU_SIZE],
+) -> Option<(u32, usize)> {
+    let header = pdu.get(..INITIATE_REQUEST_SIZE)?;
+    if header[0] != dpv1_function::INITIATE {
+        return None;
+    }
+    // Send_Timeout in 10 ms
+    let send_timeout = u32::from(u16::from_be_bytes([header[4], header[5]])) * 10;
+    let addr_len = usize::from(header[13]) + usize::from(header[15]);
+    if send_timeout == 0 || pdu.len() != INITIATE_REQUEST_SIZE + addr_len {
+        return None;
+    }
+    // Funktion, Max_Len_Data_Unit, Features, Profile Features, Profile Ident,
+    // Adressparameter wie angefragt
+    let response_len = pdu.len() - 4;
+    let response = response.get_mut(..response_len)?;
+    response[0] = dpv1_function::INITIATE;
+    response[1] = max_len_data_unit;
+    response[2] = header[6] & FEATURES_SUPPORTED_1;
+    response[3] = 0;
+    response[4] = 0;
+    response[5] = 0;
+    response[6..].copy_from_slice(&pdu[10..]);
+    Some((send_timeout, response_len))
+}
@@ -0,0 +1,8 @@
+pub use codec::{ReceiveHandling, UartAccess};
+pub use codec_hw_interface::HwInterface;
+pub use dp_slave::{Dpv1Status, PbDpSlave, ProfibusConfig};
+
+pub mod codec_hw_interface;
+pub mod dp_slave;
+mod codec;
+mod dpv1;
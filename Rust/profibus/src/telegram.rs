@@ -0,0 +1,431 @@
+/*********************************************************************
+ * Profibus telegram
+ *
+ * Copyright (C) 2023 Marcel Maage
+ *
+ * This library is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2.1 of the License, or (at your option) any later version.
+ *
+ * This library is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * LICENSE file for more details.
+ */
+
+use crate::types::cmd_type;
+
+pub const BROADCAST_ADDR: u8 = 127;
+/// Address extension bit, set if a SAP follows in the data unit
+pub const SAP_OFFSET: u8 = 0x80;
+/// Longest data unit of a SD2 telegram (LE = DA + SA + FC + 246)
+pub const MAX_PDU_SIZE: usize = 246;
+/// Data unit of a SD3 telegram
+pub const SD3_PDU_SIZE: usize = 8;
+/// Longest telegram (SD2 with the longest data unit)
+pub const MAX_TELEGRAM_SIZE: usize = MAX_PDU_SIZE + 9;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum FrameError {
+    /// Nothing was received
+    Empty,
+    /// First byte is none of SD1, SD2, SD3, SD4 and SC, or the repeated SD2
+    /// does not match
+    InvalidStartDelimiter(u8),
+    /// Received length does not fit the telegram type or LE
+    LengthMismatch { expected: usize, received: usize },
+    /// LE is outside of 3..=249
+    InvalidLength(usize),
+    /// LE and the repeated LEr differ
+    LengthRepeatMismatch { le: u8, ler: u8 },
+    /// Frame check sequence does not match the data
+    ChecksumMismatch { expected: u8, received: u8 },
+    /// Last byte is not ED
+    MissingEndDelimiter(u8),
+    /// Address extension bit set, but the data unit is too short for the SAP
+    MissingSap,
+    /// Telegram is addressed to another station (address without SAP bit)
+    NotForUs(u8),
+    /// Encode only: the telegram does not fit into the buffer
+    BufferTooSmall,
+}
+
+/// One FDL telegram.
+///
+/// Addresses are stored without the address extension bit. `dsap`/`ssap`
+/// are `Some` if the bit is set, `pdu` is the data unit after the SAPs.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Telegram<'a> {
+    Sd1 {
+        destination_addr: u8,
+        source_addr: u8,
+        function_code: u8,
+    },
+    Sd2 {
+        destination_addr: u8,
+        source_addr: u8,
+        function_code: u8,
+        dsap: Option<u8>,
+        ssap: Option<u8>,
+        pdu: &'a [u8],
+    },
+    Sd3 {
+        destination_addr: u8,
+        source_addr: u8,
+        function_code: u8,
+        dsap: Option<u8>,
+        ssap: Option<u8>,
+        pdu: &'a [u8],
+    },
+    Sd4 {
+        destination_addr: u8,
+        source_addr: u8,
+    },
+    Sc,
+}
+
+impl<'a> Telegram<'a> {
+    /// Decodes one complete telegram, `data` has to end with its last byte.
+    pub fn decode(data: &'a [u8]) -> Result<Self, FrameError> {
+        let Some(&start_delimiter) = data.first() else {
+            return Err(FrameError::Empty);
+        };
+        match start_delimiter {
+            cmd_type::SD1 => {
+                check_len(data, 6)?;
+                check_end_delimiter(data)?;
+                check_fcs(&data[1..4], data[4])?;
+                Ok(Telegram::Sd1 {
+                    destination_addr: data[1] & !SAP_OFFSET,
+                    source_addr: data[2] & !SAP_OFFSET,
+                    function_code: data[3],
+                })
+            }
+
+            cmd_type::SD2 => {
+                if data.len() < 4 {
+                    return Err(FrameError::LengthMismatch {
+                        expected: 9,
+                        received: data.len(),
+                    });
+                }
+                let le = data[1];
+                let ler = data[2];
+                if le != ler {
+                    return Err(FrameError::LengthRepeatMismatch { le, ler });
+                }
+                if data[3] != cmd_type::SD2 {
+                    return Err(FrameError::InvalidStartDelimiter(data[3]));
+                }
+                let le = usize::from(le);
+                if !(3..=MAX_PDU_SIZE + 3).contains(&le) {
+                    return Err(FrameError::InvalidLength(le));
+                }
+                check_len(data, le + 6)?;
+                check_end_delimiter(data)?;
+                check_fcs(&data[4..4 + le], data[4 + le])?;
+                let (dsap, ssap, pdu) = split_sap(data[4], data[5], &data[7..4 + le])?;
+                Ok(Telegram::Sd2 {
+                    destination_addr: data[4] & !SAP_OFFSET,
+                    source_addr: data[5] & !SAP_OFFSET,
+                    function_code: data[6],
+                    dsap,
+                    ssap,
+                    pdu,
+                })
+            }
+
+            cmd_type::SD3 => {
+                check_len(data, 14)?;
+                check_end_delimiter(data)?;
+                check_fcs(&data[1..12], data[12])?;
+                let (dsap, ssap, pdu) = split_sap(data[1], data[2], &data[4..12])?;
+                Ok(Telegram::Sd3 {
+                    destination_addr: data[1] & !SAP_OFFSET,
+                    source_addr: data[2] & !SAP_OFFSET,
+                    function_code: data[3],
+                    dsap,
+                    ssap,
+                    pdu,
+                })
+            }
+
+            cmd_type::SD4 => {
+                check_len(data, 3)?;
+                Ok(Telegram::Sd4 {
+                    destination_addr: data[1] & !SAP_OFFSET,
+                    source_addr: data[2] & !SAP_OFFSET,
+                })
+            }
+
+            cmd_type::SC => {
+                check_len(data, 1)?;
+                Ok(Telegram::Sc)
+            }
+
+            _ => Err(FrameError::InvalidStartDelimiter(start_delimiter)),
+        }
+    }
+
+    /// Like `decode`, but fails with `NotForUs` if the telegram is neither
+    /// addressed to `station_addr` nor a broadcast.
+    pub fn decode_for(data: &'a [u8], station_addr: u8) -> Result<Self, FrameError> {
+        let telegram = Self::decode(data)?;
+        if let Some(destination_addr) = telegram.destination_addr() {
+            if (destination_addr != station_addr) && (destination_addr != BROADCAST_ADDR) {
+                return Err(FrameError::NotForUs(destination_addr));
+            }
+        }
+        Ok(telegram)
+    }
+
+    /// Writes the telegram into `buffer` and returns its length.
+    pub fn encode(&self, buffer: &mut [u8]) -> Result<usize, FrameError> {
+        match *self {
+            Telegram::Sd1 {
+                destination_addr,
+                source_addr,
+                function_code,
+            } => {
+                let telegram = buffer.get_mut(..6).ok_or(FrameError::BufferTooSmall)?;
+                telegram[0] = cmd_type::SD1;
+                telegram[1] = destination_addr;
+                telegram[2] = source_addr;
+                telegram[3] = function_code;
+                telegram[4] = calc_checksum(&telegram[1..4]);
+                telegram[5] = cmd_type::ED;
+                Ok(6)
+            }
+
+            Telegram::Sd2 {
+                destination_addr,
+                source_addr,
+                function_code,
+                dsap,
+                ssap,
+                pdu,
+            } => {
+                let du_len = sap_len(dsap, ssap) + pdu.len();
+                if du_len > MAX_PDU_SIZE {
+                    return Err(FrameError::InvalidLength(du_len + 3));
+                }
+                let len = du_len + 9;
+                let telegram = buffer.get_mut(..len).ok_or(FrameError::BufferTooSmall)?;
+                telegram[0] = cmd_type::SD2;
+                telegram[1] = (du_len + 3) as u8;
+                telegram[2] = (du_len + 3) as u8;
+                telegram[3] = cmd_type::SD2;
+                write_header(
+                    &mut telegram[4..],
+                    destination_addr,
+                    source_addr,
+                    function_code,
+                    dsap,
+                    ssap,
+                    pdu,
+                );
+                telegram[len - 2] = calc_checksum(&telegram[4..len - 2]);
+                telegram[len - 1] = cmd_type::ED;
+                Ok(len)
+            }
+
+            Telegram::Sd3 {
+                destination_addr,
+                source_addr,
+                function_code,
+                dsap,
+                ssap,
+                pdu,
+            } => {
+                let du_len = sap_len(dsap, ssap) + pdu.len();
+                if du_len != SD3_PDU_SIZE {
+                    return Err(FrameError::InvalidLength(du_len + 3));
+                }
+                let telegram = buffer.get_mut(..14).ok_or(FrameError::BufferTooSmall)?;
+                telegram[0] = cmd_type::SD3;
+                write_header(
+                    &mut telegram[1..],
+                    destination_addr,
+                    source_addr,
+                    function_code,
+                    dsap,
+                    ssap,
+                    pdu,
+                );
+                telegram[12] = calc_checksum(&telegram[1..12]);
+                telegram[13] = cmd_type::ED;
+                Ok(14)
+            }
+
+            Telegram::Sd4 {
+                destination_addr,
+                source_addr,
+            } => {
+                let telegram = buffer.get_mut(..3).ok_or(FrameError::BufferTooSmall)?;
+                telegram[0] = cmd_type::SD4;
+                telegram[1] = destination_addr;
+                telegram[2] = source_addr;
+                Ok(3)
+            }
+
+            Telegram::Sc => {
+                let telegram = buffer.get_mut(..1).ok_or(FrameError::BufferTooSmall)?;
+                telegram[0] = cmd_type::SC;
+                Ok(1)
+            }
+        }
+    }
+
+    pub fn destination_addr(&self) -> Option<u8> {
+        match *self {
+            Telegram::Sd1 {
+                destination_addr, ..
+            }
+            | Telegram::Sd2 {
+                destination_addr, ..
+            }
+            | Telegram::Sd3 {
+                destination_addr, ..
+            }
+            | Telegram::Sd4 {
+                destination_addr, ..
+            } => Some(destination_addr),
+            Telegram::Sc => None,
+        }
+    }
+
+    pub fn source_addr(&self) -> Option<u8> {
+        match *self {
+            Telegram::Sd1 { source_addr, .. }
+            | Telegram::Sd2 { source_addr, .. }
+            | Telegram::Sd3 { source_addr, .. }
+            | Telegram::Sd4 { source_addr, .. } => Some(source_addr),
+            Telegram::Sc => None,
+        }
+    }
+
+    pub fn function_code(&self) -> Option<u8> {
+        match *self {
+            Telegram::Sd1 { function_code, .. }
+            | Telegram::Sd2 { function_code, .. }
+            | Telegram::Sd3 { function_code, .. } => Some(function_code),
+            Telegram::Sd4 { .. } | Telegram::Sc => None,
+        }
+    }
+
+    /// (dsap, ssap)
+    pub fn sap(&self) -> (Option<u8>, Option<u8>) {
+        match *self {
+            Telegram::Sd2 { dsap, ssap, .. } | Telegram::Sd3 { dsap, ssap, .. } => (dsap, ssap),
+            _ => (None, None),
+        }
+    }
+
+    pub fn pdu(&self) -> &'a [u8] {
+        match *self {
+            Telegram::Sd2 { pdu, .. } | Telegram::Sd3 { pdu, .. } => pdu,
+            _ => &[],
+        }
+    }
+}
+
+pub fn calc_checksum(data: &[u8]) -> u8 {
+    data.iter().fold(0, |checksum, x| checksum.wrapping_add(*x))
+}
+
+/// Length of the telegram at the start of `data` as given by its start
+/// delimiter, so telegrams received back to back can be split. `None` if
+/// `data` does not start with a start delimiter.
+pub fn frame_len(data: &[u8]) -> Option<usize> {
+    match *data.first()? {
+        cmd_type::SD1 => Some(6),
+        cmd_type::SD2 => data.get(1).map(|le| usize::from(*le) + 6),
+        cmd_type::SD3 => Some(14),
+        cmd_type::SD4 => Some(3),
+        cmd_type::SC => Some(1),
+        _ => None,
+    }
+}
+
+fn check_len(data: &[u8], expected: usize) -> Result<(), FrameError> {
+    if data.len() != expected {
+        Err(FrameError::LengthMismatch {
+            expected,
+            received: data.len(),
+        })
+    } else {
+        Ok(())
+    }
+}
+
+fn check_end_delimiter(data: &[u8]) -> Result<(), FrameError> {
+    let end_delimiter = data[data.len() - 1];
+    if end_delimiter != cmd_type::ED {
+        Err(FrameError::MissingEndDelimiter(end_delimiter))
+    } else {
+        Ok(())
+    }
+}
+
+fn check_fcs(data: &[u8], fcs: u8) -> Result<(), FrameError> {
+    let expected = calc_checksum(data);
+    if fcs != expected {
+        Err(FrameError::ChecksumMismatch {
+            expected,
+            received: fcs,
+        })
+    } else {
+        Ok(())
+    }
+}
+
+type SplitSap<'a> = (Option<u8>, Option<u8>, &'a [u8]);
+
+fn split_sap(destination_addr: u8, source_addr: u8, du: &[u8]) -> Result<SplitSap<'_>, FrameError> {
+    let mut pdu = du;
+    let mut dsap = None;
+    let mut ssap = None;
+    if (destination_addr & SAP_OFFSET) != 0 {
+        let (sap, rest) = pdu.split_first().ok_or(FrameError::MissingSap)?;
+        dsap = Some(*sap);
+        pdu = rest;
+    }
+    if (source_addr & SAP_OFFSET) != 0 {
+        let (sap, rest) = pdu.split_first().ok_or(FrameError::MissingSap)?;
+        ssap = Some(*sap);
+        pdu = rest;
+    }
+    Ok((dsap, ssap, pdu))
+}
+
+fn sap_len(dsap: Option<u8>, ssap: Option<u8>) -> usize {
+    usize::from(dsap.is_some()) + usize::from(ssap.is_some())
+}
+
+/// Writes DA, SA, FC, the SAPs and the pdu to the start of `buffer`.
+fn write_header(
+    buffer: &mut [u8],
+    destination_addr: u8,
+    source_addr: u8,
+    function_code: u8,
+    dsap: Option<u8>,
+    ssap: Option<u8>,
+    pdu: &[u8],
+) {
+    buffer[0] = destination_addr;
+    buffer[1] = source_addr;
+    buffer[2] = function_code;
+    let mut pos = 3;
+    if let Some(dsap) = dsap {
+        buffer[0] |= SAP_OFFSET;
+        buffer[pos] = dsap;
+        pos += 1;
+    }
+    if let Some(ssap) = ssap {
+        buffer[1] |= SAP_OFFSET;
+        buffer[pos] = ssap;
+        pos += 1;
+    }
+    buffer[pos..pos + pdu.len()].copy_from_slice(pdu);
+}
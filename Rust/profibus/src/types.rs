@@ -1,4 +1,4 @@
-#[derive(PartialEq, Eq)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
 pub enum DpSlaveState {
     Por = 1,   // Power on reset
     Wrpm = 2,  // Wait for parameter
@@ -6,7 +6,7 @@ pub enum DpSlaveState {
     Dxchg = 4, // Data exchange
 }
 
-#[derive(PartialEq, Eq)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
 pub enum StreamState {
     WaitSyn,
     WaitData,
@@ -38,10 +38,62 @@ pub mod fc_request {
 #[allow(dead_code)]
 pub mod fc_response {
     pub const FDL_STATUS_OK: u8 = 0x00; // SLA: OK
+    pub const USER_ERROR: u8 = 0x01; // SLA: (UE) Anfrage fehlerhaft
+    pub const NO_RESOURCE: u8 = 0x02; // SLA: (RR) keine Ressourcen frei
+    pub const SAP_NOT_ACTIVE: u8 = 0x03; // SLA: (RS) SAP nicht aktiviert
     pub const DATA_LOW: u8 = 0x08; // SLA: (Data low) Daten Eingaenge senden
     pub const DATA_HIGH: u8 = 0x0A; // SLA: (Data high) Diagnose anstehend
 }
 
+#[allow(dead_code)]
+pub mod fc_request_low_nibble {
+    pub const TIME_EVENT: u8 = 0x0;
+    pub const SDA_LOW: u8 = 0x3;
+    pub const SDN_LOW: u8 = 0x4;
+    pub const SDA_HIGH: u8 = 0x5;
+    pub const SDN_HIGH: u8 = 0x6;
+    pub const MSRD: u8 = 0x7;
+    pub const REQUEST_FDL_STATUS: u8 = 0x9;
+    pub const TIME_EVENT_SYNCHRONISATION: u8 = 0xA;
+    pub const COUNTER_VALUE: u8 = 0xB;
+    pub const SRD_LOW: u8 = 0xC;
+    pub const SRD_HIGH: u8 = 0xD;
+    pub const REQUEST_IDENT_WITH_REPLY: u8 = 0xE;
+    pub const REQUEST_LSAP_STATUS_WITH_REPLY: u8 = 0xF;
+}
+
+#[allow(dead_code)]
+pub mod fc_request_high_nibble {
+    pub const FCV: u8 = 0x10;
+    pub const FCB: u8 = 0x20;
+    pub const REQUEST_TELEGRAM: u8 = 0x40;
+    pub const RESERVED: u8 = 0x80; // must be zero
+}
+
+#[allow(dead_code)]
+pub mod fc_response_low_nibble {
+    pub const OK: u8 = 0x0;
+    pub const USER_ERROR: u8 = 0x1; // UE
+    pub const NO_RESOURCE: u8 = 0x2; // RR
+    pub const SAP_NOT_ACTIVE: u8 = 0x3; // RS
+    pub const DATA_LOW: u8 = 0x8; // DL
+    pub const NO_RESPONSE: u8 = 0x9; // NR
+    pub const DATA_HIGH: u8 = 0xA; // DH
+    pub const DATA_NOT_RECEIVED_LOW: u8 = 0xC; // RDL
+    pub const DATA_NOT_RECEIVED_HIGH: u8 = 0xD; // RDH
+}
+
+#[allow(dead_code)]
+pub mod fc_response_high_nibble {
+    pub const DEVICE_MASK: u8 = 0x30;
+    pub const SLAVE: u8 = 0x00;
+    pub const MASTER_NOT_READY: u8 = 0x10;
+    pub const MASTER_READY_WITHOUT_TOKEN: u8 = 0x20;
+    pub const MASTER_READY_TOKEN: u8 = 0x30;
+    pub const REQUEST_TELEGRAM: u8 = 0x40; // must be zero
+    pub const RESERVED: u8 = 0x80; // must be zero
+}
+
 #[allow(dead_code)]
 pub mod sap {
     pub const SET_SLAVE_ADR:u8 = 55;     // Master setzt Slave Adresse, Slave Anwortet mit SC
@@ -52,6 +104,10 @@ pub mod sap {
     pub const SLAVE_DIAGNOSTIC :u8 = 60; // Master fordert Diagnose, Slave sendet Diagnose Daten
     pub const SET_PRM :u8 = 61;          // Master sendet Parameter, Slave sendet SC
     pub const CHK_CFG :u8 = 62;          // Master sendet Konfuguration, Slave sendet SC
+    pub const MSAC_C1 :u8 = 51;          // DPV1 azyklisch Klasse 1 Master, Read/Write Datensatz
+    pub const MSAL1_ALARM :u8 = 50;      // DPV1 Alarm_Ack vom Klasse 1 Master
+    pub const MSAC_C2_INITIATE :u8 = 49; // DPV1 Klasse 2 Master, Verbindungsaufbau
+    pub const MSAC_C2_LAST :u8 = 48;     // DPV1 Klasse 2 Master, Verbindungs-SAPs 0..48
 }
 
 #[allow(dead_code)]
@@ -170,3 +226,71 @@ pub mod sap_check_config_request {
     /* Spezielles Format / Laengenbyte */
     pub const CFG_SP_BYTE_CNT: u8 = 0x3F; // Bit 0-5 sind Anzahl der Bytes oder Worte. 0 = 1 Byte, 1 = 2 Byte usw.
 }
+
+#[allow(dead_code)]
+pub mod dpv1_function {
+    pub const READ: u8 = 0x5E; // Datensatz lesen
+    pub const WRITE: u8 = 0x5F; // Datensatz schreiben
+    pub const DATA_TRANSPORT: u8 = 0x51; // Daten senden und Antwort holen (nur Klasse 2)
+    pub const INITIATE: u8 = 0x57; // Verbindungsaufbau Klasse 2
+    pub const ABORT: u8 = 0x58; // Verbindungsabbau Klasse 2
+    pub const ALARM_ACK: u8 = 0x5C; // Alarm quittieren
+    pub const ERROR: u8 = 0x80; // Fehlerantwort: Funktionsnummer + 0x80
+}
+
+#[allow(dead_code)]
+pub mod dpv1_error {
+    pub const DECODE_DPV1: u8 = 0x80; // Error_Decode: Fehler nach DPV1
+
+    /* Error_Code_1: Bit 4-7 Klasse, Bit 0-3 Code */
+    pub const READ_ERROR: u8 = 0xA0;
+    pub const WRITE_ERROR: u8 = 0xA1;
+    pub const FEATURE_NOT_SUPPORTED: u8 = 0xA9;
+    pub const INVALID_INDEX: u8 = 0xB0;
+    pub const WRITE_LENGTH_ERROR: u8 = 0xB1;
+    pub const INVALID_SLOT: u8 = 0xB2;
+    pub const TYPE_CONFLICT: u8 = 0xB3;
+    pub const INVALID_AREA: u8 = 0xB4;
+    pub const STATE_CONFLICT: u8 = 0xB5;
+    pub const ACCESS_DENIED: u8 = 0xB6;
+    pub const INVALID_RANGE: u8 = 0xB7;
+    pub const INVALID_PARAMETER: u8 = 0xB8;
+    pub const INVALID_TYPE: u8 = 0xB9;
+    pub const RESOURCE_BUSY: u8 = 0xC2;
+}
+
+#[allow(dead_code)]
+pub mod dpv1_alarm {
+    /* Alarm_Type */
+    pub const DIAGNOSE: u8 = 1;
+    pub const PROZESS: u8 = 2;
+    pub const PULL: u8 = 3;
+    pub const PLUG: u8 = 4;
+    pub const STATUS: u8 = 5;
+    pub const UPDATE: u8 = 6;
+    pub const VENDOR_FIRST: u8 = 32; // Herstellerspezifisch 32..126
+    pub const VENDOR_LAST: u8 = 126;
+
+    /* Alarm_Specifier: Bit 3-7 Seq_Nr, Bit 2 Add_Ack, Bit 0-1 Specifier */
+    pub const SEQ_NR_SHIFT: u8 = 3;
+    pub const SEQ_NR_COUNT: u8 = 32;
+    pub const ADD_ACK: u8 = 0x04;
+    pub const SPECIFIER: u8 = 0x03;
+}
+
+#[allow(dead_code)]
+pub mod dpv1_abort {
+    /* Instance_Reason_Code: Bit 4-5 Instanz, Bit 0-3 Grund */
+    pub const INSTANCE_FDL: u8 = 0x00;
+    pub const INSTANCE_DDLM: u8 = 0x10;
+    pub const INSTANCE_USER: u8 = 0x20;
+
+    pub const ABT_SE: u8 = 0x01; // Sequenzfehler
+    pub const ABT_FE: u8 = 0x02; // ungueltige Anfrage PDU
+    pub const ABT_TO: u8 = 0x03; // Verbindung abgelaufen
+    pub const ABT_RE: u8 = 0x04; // ungueltige Antwort PDU
+    pub const ABT_IV: u8 = 0x05; // ungueltiger Dienst
+    pub const ABT_STO: u8 = 0x06; // Send_Timeout zu klein
+    pub const ABT_IA: u8 = 0x07; // ungueltige Zusatzadresse
+    pub const ABT_OC: u8 = 0x08; // Antwort noch ausstehend
+}
@@ -0,0 +1,120 @@
+use profibus::alarm::{AlarmError, AlarmSpecifier, AlarmType, ALARM_QUEUE_SIZE};
+use profibus::sim::{DpMaster, MasterConfig, MasterError, SimBus, SimHwInterface};
+use profibus::{
+    dpv1_status_byte2, fc_response, sap_diagnose_byte1, DataHandlingInterface, PbDpSlave,
+    ProfibusConfig,
+};
+
+const SLAVE_ADDR: u8 = 0x0B;
+const MODULE_CONFIG: [u8; 2] = [0x10, 0x20];
+
+struct DataHandling;
+
+impl DataHandlingInterface for DataHandling {}
+
+type Slave = PbDpSlave<SimHwInterface, DataHandling, 128, 1, 1, 0, 0, 2>;
+
+fn setup(dpv1_status: [u8; 3]) -> (Slave, DpMaster) {
+    let bus = SimBus::new(500_000);
+    let slave = PbDpSlave::new(
+        bus.interface(),
+        DataHandling,
+        ProfibusConfig::default()
+            .ident_high(0x00)
+            .ident_low(0x2B)
+            .t_s(SLAVE_ADDR)
+            .dpv1(true)
+            .dpv1_alarms(dpv1_status_byte2::PROZESS_ALARM | dpv1_status_byte2::DIAGNOSE_ALARM),
+        MODULE_CONFIG,
+    );
+    let master = DpMaster::new(
+        bus,
+        MasterConfig::default()
+            .ident_high(0x00)
+            .ident_low(0x2B)
+            .slave_addr(SLAVE_ADDR)
+            .module_config(&MODULE_CONFIG)
+            .user_para(&dpv1_status),
+    );
+    (slave, master)
+}
+
+#[test]
+fn alarm_is_reported_until_acknowledged() {
+    let (mut slave, mut master) = setup([0x80, dpv1_status_byte2::PROZESS_ALARM, 0x00]);
+    master.startup(&mut slave).unwrap();
+    assert!(!master.data_exchange(&mut slave, &[0x01]).unwrap().1);
+
+    assert_eq!(
+        slave.raise_alarm(2, AlarmType::Process, AlarmSpecifier::Appears, &[0xAB, 0xCD]),
+        Ok(0)
+    );
+    // high priority response asks the master for the diagnostic
+    assert!(master.data_exchange(&mut slave, &[0x01]).unwrap().1);
+    let diagnostic = master.get_diagnostic(&mut slave).unwrap();
+    assert_ne!(diagnostic.status_1 & sap_diagnose_byte1::EXT_DIAG, 0);
+    let alarm = diagnostic.alarm().unwrap();
+    assert_eq!(alarm.slot, 2);
+    assert_eq!(alarm.alarm_type, AlarmType::Process);
+    assert_eq!(alarm.specifier, AlarmSpecifier::Appears);
+    assert_eq!(alarm.seq_nr, 0);
+    assert_eq!(alarm.data(), [0xAB, 0xCD]);
+
+    master.alarm_ack(&mut slave, &alarm).unwrap();
+    assert!(!master.data_exchange(&mut slave, &[0x01]).unwrap().1);
+    let diagnostic = master.get_diagnostic(&mut slave).unwrap();
+    assert_eq!(diagnostic.status_1 & sap_diagnose_byte1::EXT_DIAG, 0);
+    assert_eq!(diagnostic.alarm(), None);
+}
+
+#[test]
+fn only_alarms_enabled_in_set_prm_are_raised() {
+    let (mut slave, mut master) = setup([0x80, dpv1_status_byte2::PROZESS_ALARM, 0x00]);
+    master.startup(&mut slave).unwrap();
+    // supported by the slave, but not enabled by the master
+    assert_eq!(
+        slave.raise_alarm(0, AlarmType::Diagnostic, AlarmSpecifier::Appears, &[]),
+        Err(AlarmError::NotEnabled)
+    );
+    assert_eq!(
+        slave.raise_alarm(0, AlarmType::Process, AlarmSpecifier::None, &[0; 60]),
+        Err(AlarmError::TooLong)
+    );
+
+    let (mut slave, mut master) = setup([0x00, 0x00, 0x00]);
+    master.startup(&mut slave).unwrap();
+    assert_eq!(
+        slave.raise_alarm(0, AlarmType::Process, AlarmSpecifier::Appears, &[]),
+        Err(AlarmError::NotEnabled)
+    );
+}
+
+#[test]
+fn queued_alarms_are_reported_in_order() {
+    let (mut slave, mut master) = setup([0x80, dpv1_status_byte2::PROZESS_ALARM, 0x00]);
+    master.startup(&mut slave).unwrap();
+    for seq_nr in 0..ALARM_QUEUE_SIZE as u8 {
+        assert_eq!(
+            slave.raise_alarm(1, AlarmType::Process, AlarmSpecifier::Appears, &[seq_nr]),
+            Ok(seq_nr)
+        );
+    }
+    assert_eq!(
+        slave.raise_alarm(1, AlarmType::Process, AlarmSpecifier::Appears, &[]),
+        Err(AlarmError::QueueFull)
+    );
+
+    let first = master.get_diagnostic(&mut slave).unwrap().alarm().unwrap();
+    assert_eq!(first.data(), [0]);
+    master.alarm_ack(&mut slave, &first).unwrap();
+    // the same alarm can not be acknowledged twice
+    assert_eq!(
+        master.alarm_ack(&mut slave, &first),
+        Err(MasterError::Rejected {
+            function_code: fc_response::USER_ERROR
+        })
+    );
+    let second = master.get_diagnostic(&mut slave).unwrap().alarm().unwrap();
+    assert_eq!(second.seq_nr, 1);
+    assert_eq!(second.data(), [1]);
+}
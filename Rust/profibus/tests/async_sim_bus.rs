@@ -0,0 +1,217 @@
+use profibus::device::Connection;
+use profibus::sim::{run_until_stalled, AsyncSimBus, AsyncSimHwInterface};
+use profibus::{
+    cmd_type, fc_request, fc_response, Codec, CodecConfig, Device, DeviceConfig, FrameError,
+};
+
+const BAUDRATE: u32 = 500_000;
+const SLAVE_ADDR: u8 = 0x0B;
+const MASTER_ADDR: u8 = 0x02;
+
+fn sd1(destination_addr: u8, source_addr: u8, function_code: u8) -> [u8; 6] {
+    let fcs = destination_addr
+        .wrapping_add(source_addr)
+        .wrapping_add(function_code);
+    [
+        cmd_type::SD1,
+        destination_addr,
+        source_addr,
+        function_code,
+        fcs,
+        cmd_type::ED,
+    ]
+}
+
+fn codec(bus: &AsyncSimBus) -> Codec<AsyncSimHwInterface> {
+    Codec::new(bus.interface(), CodecConfig::default().t_s(SLAVE_ADDR))
+}
+
+/// Receives one telegram and returns (source address, function code).
+fn receive(codec: &mut Codec<AsyncSimHwInterface>) -> Option<(u8, u8)> {
+    let mut buffer = [0; 255];
+    run_until_stalled(codec.receive(&mut buffer))
+        .flatten()
+        .map(|conn| (conn.source_addr, conn.function_code))
+}
+
+fn reply(codec: &mut Codec<AsyncSimHwInterface>) {
+    let connection = Connection::new(
+        SLAVE_ADDR,
+        MASTER_ADDR,
+        fc_response::FDL_STATUS_OK,
+        None,
+        &[],
+    );
+    run_until_stalled(codec.transmit_message_sd1(connection)).unwrap();
+}
+
+#[test]
+fn receives_telegram_after_syn_time() {
+    let bus = AsyncSimBus::new(BAUDRATE);
+    let mut codec = codec(&bus);
+
+    let function_code = fc_request::REQUEST + fc_request::FDL_STATUS;
+    bus.send_after_us(100, &sd1(SLAVE_ADDR, MASTER_ADDR, function_code));
+    assert_eq!(receive(&mut codec), Some((MASTER_ADDR, function_code)));
+    assert_eq!(bus.pending(), 0);
+}
+
+#[test]
+fn drops_telegram_started_during_syn_time() {
+    let bus = AsyncSimBus::new(BAUDRATE);
+    let mut codec = codec(&bus);
+
+    let function_code = fc_request::REQUEST + fc_request::FDL_STATUS;
+    // starts before the 33 bit SYN pause of the codec is over
+    bus.send(&sd1(SLAVE_ADDR, 0x03, function_code));
+    bus.send_after_us(100, &sd1(SLAVE_ADDR, MASTER_ADDR, function_code));
+    assert_eq!(receive(&mut codec), Some((MASTER_ADDR, function_code)));
+}
+
+#[test]
+fn ignores_other_destination() {
+    let bus = AsyncSimBus::new(BAUDRATE);
+    let mut codec = codec(&bus);
+
+    let function_code = fc_request::REQUEST + fc_request::FDL_STATUS;
+    bus.send_after_us(100, &sd1(0x0C, MASTER_ADDR, function_code));
+    assert_eq!(receive(&mut codec), None);
+    assert_eq!(bus.pending(), 0);
+}
+
+#[test]
+fn repeats_last_response_on_same_fcb() {
+    let bus = AsyncSimBus::new(BAUDRATE);
+    let mut codec = codec(&bus);
+
+    let request = fc_request::REQUEST + fc_request::SRD_HIGH;
+    let first = request + fc_request::FCB;
+    let repeat = request + fc_request::FCV + fc_request::FCB;
+    let next = request + fc_request::FCV;
+
+    bus.send_after_us(100, &sd1(SLAVE_ADDR, MASTER_ADDR, first));
+    assert_eq!(receive(&mut codec), Some((MASTER_ADDR, first)));
+    reply(&mut codec);
+    let response = sd1(MASTER_ADDR, SLAVE_ADDR, fc_response::FDL_STATUS_OK);
+    assert_eq!(bus.take_transmitted(), vec![response.to_vec()]);
+
+    // the master did not get the response and repeats the request
+    bus.send_after_us(100, &sd1(SLAVE_ADDR, MASTER_ADDR, repeat));
+    bus.send_after_us(100, &sd1(SLAVE_ADDR, MASTER_ADDR, next));
+    assert_eq!(receive(&mut codec), Some((MASTER_ADDR, next)));
+    assert_eq!(bus.take_transmitted(), vec![response.to_vec()]);
+}
+
+#[test]
+fn ignores_fcb_without_fcv() {
+    let bus = AsyncSimBus::new(BAUDRATE);
+    let mut codec = codec(&bus);
+
+    let request = fc_request::REQUEST + fc_request::SRD_HIGH;
+    let first = request + fc_request::FCB;
+    let fdl_status = fc_request::REQUEST + fc_request::FDL_STATUS;
+    let next = request + fc_request::FCV;
+
+    bus.send_after_us(100, &sd1(SLAVE_ADDR, MASTER_ADDR, first));
+    assert_eq!(receive(&mut codec), Some((MASTER_ADDR, first)));
+    reply(&mut codec);
+    // FDL status carries no valid FCB
+    bus.send_after_us(100, &sd1(SLAVE_ADDR, MASTER_ADDR, fdl_status));
+    assert_eq!(receive(&mut codec), Some((MASTER_ADDR, fdl_status)));
+    reply(&mut codec);
+    bus.send_after_us(100, &sd1(SLAVE_ADDR, MASTER_ADDR, next));
+    assert_eq!(receive(&mut codec), Some((MASTER_ADDR, next)));
+}
+
+#[test]
+fn keeps_min_tsdr_before_response() {
+    let bus = AsyncSimBus::new(BAUDRATE);
+    let mut codec = Codec::new(
+        bus.interface(),
+        CodecConfig::default().t_s(SLAVE_ADDR).t_sdr_min(100),
+    );
+
+    bus.send_after_us(100, &sd1(SLAVE_ADDR, MASTER_ADDR, 0x49));
+    receive(&mut codec).unwrap();
+    let request_end = bus.now_ns();
+    reply(&mut codec);
+    let (start, _) = bus.take_transmitted_with_time()[0];
+    // 100 bit at 500 kBit/s
+    assert_eq!(start - request_end, 200_000);
+}
+
+#[test]
+fn device_handles_one_telegram_per_run() {
+    let bus = AsyncSimBus::new(BAUDRATE);
+    let mut config = DeviceConfig::default();
+    config.fdl_config.codec_config.t_s = SLAVE_ADDR;
+    let mut device = Device::<AsyncSimHwInterface, 50, 2, 5, 0, 0, 5>::new(
+        bus.interface(),
+        config,
+        [0x22, 0x20, 0x20, 0x10, 0x10],
+    );
+
+    bus.send_after_us(100, &sd1(SLAVE_ADDR, MASTER_ADDR, 0x49));
+    assert_eq!(run_until_stalled(device.run()), Some(true));
+    assert_eq!(run_until_stalled(device.run()), None);
+}
+
+#[test]
+fn monitor_reports_every_telegram() {
+    let bus = AsyncSimBus::new(BAUDRATE);
+    let mut codec = Codec::new(
+        bus.interface(),
+        CodecConfig::default().t_s(SLAVE_ADDR).monitor(true),
+    );
+
+    let function_code = fc_request::REQUEST + fc_request::FDL_STATUS;
+    bus.send_after_us(100, &sd1(0x0C, MASTER_ADDR, function_code));
+    // answer and short acknowledge without pause in between
+    let mut answer = sd1(MASTER_ADDR, 0x0C, fc_response::FDL_STATUS_OK).to_vec();
+    answer.push(cmd_type::SC);
+    bus.send_after_us(20, &answer);
+    let mut faulty = sd1(SLAVE_ADDR, MASTER_ADDR, function_code);
+    faulty[4] ^= 0xFF;
+    bus.send_after_us(100, &faulty);
+
+    let mut frames = Vec::new();
+    let mut buffer = [0; 255];
+    let monitor = codec.monitor(&mut buffer, |frame| {
+        frames.push((
+            frame.timestamp_us,
+            frame.data.to_vec(),
+            frame.fcs_ok(),
+            frame.telegram.map(|telegram| telegram.destination_addr()),
+        ))
+    });
+    assert!(run_until_stalled(monitor).is_none());
+
+    // 22 us per character at 500 kBit/s
+    assert_eq!(frames.len(), 4);
+    assert_eq!(frames[0].0, 100);
+    assert_eq!(frames[0].3, Ok(Some(0x0C)));
+    assert_eq!(frames[1].0, 252);
+    assert_eq!(frames[1].1, answer[..6]);
+    assert_eq!((frames[2].0, frames[2].3), (384, Ok(None)));
+    assert_eq!(frames[3].0, 506);
+    assert!(!frames[3].2);
+    assert!(matches!(
+        frames[3].3,
+        Err(FrameError::ChecksumMismatch { .. })
+    ));
+}
+
+#[test]
+fn monitor_never_transmits() {
+    let bus = AsyncSimBus::new(BAUDRATE);
+    let mut codec = Codec::new(
+        bus.interface(),
+        CodecConfig::default().t_s(SLAVE_ADDR).monitor(true),
+    );
+
+    let function_code = fc_request::REQUEST + fc_request::FDL_STATUS;
+    bus.send_after_us(100, &sd1(0x0C, MASTER_ADDR, function_code));
+    assert_eq!(receive(&mut codec), Some((MASTER_ADDR, function_code)));
+    reply(&mut codec);
+    assert!(bus.take_transmitted().is_empty());
+}
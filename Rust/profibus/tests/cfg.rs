@@ -0,0 +1,47 @@
+use profibus::cfg::{self, CfgData, CfgError, Consistency, Direction, Width};
+
+#[test]
+fn decodes_compact_format() {
+    // 3 output bytes, 2 input words consistent, 1 in/out byte
+    let modules: Vec<_> = cfg::decode(&[0x22, 0xD1, 0x30])
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(modules.len(), 3);
+    assert_eq!(modules[0].direction(), Direction::Output);
+    assert_eq!(modules[0].output_len(), 3);
+    assert_eq!(
+        modules[1].input,
+        Some(CfgData {
+            len: 4,
+            width: Width::Word,
+            consistency: Consistency::Module,
+        })
+    );
+    assert_eq!(modules[2].direction(), Direction::InputOutput);
+    assert_eq!(cfg::io_len(&[0x22, 0xD1, 0x30]), Ok((5, 4)));
+}
+
+#[test]
+fn decodes_special_format() {
+    // empty slot, then in/out with 64 output bytes, 8 input words and 2
+    // vendor bytes
+    let config = [0x00, 0xC2, 0x3F, 0x47, 0xAA, 0xBB];
+    let modules: Vec<_> = cfg::decode(&config).collect::<Result<_, _>>().unwrap();
+    assert_eq!(modules[0].direction(), Direction::Empty);
+    assert_eq!(modules[1].output_len(), 64);
+    assert_eq!(modules[1].input_len(), 16);
+    assert_eq!(modules[1].input.unwrap().width, Width::Word);
+    assert_eq!(modules[1].vendor_data, &[0xAA, 0xBB]);
+    assert_eq!(cfg::io_len(&config), Ok((16, 64)));
+}
+
+#[test]
+fn reports_truncated_identifier() {
+    assert_eq!(
+        cfg::io_len(&[0x10, 0xC1, 0x3F, 0x07]),
+        Err(CfgError::Truncated { offset: 1 })
+    );
+    let mut modules = cfg::decode(&[0x80]);
+    assert_eq!(modules.next(), Some(Err(CfgError::Truncated { offset: 0 })));
+    assert_eq!(modules.next(), None);
+}
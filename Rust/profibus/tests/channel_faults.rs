@@ -0,0 +1,128 @@
+use profibus::alarm::{AlarmSpecifier, AlarmType};
+use profibus::diag::{
+    ChannelDiag, ChannelDirection, ChannelError, ChannelType, ExtDiag, MAX_CHANNEL_FAULTS,
+};
+use profibus::sim::{DpMaster, MasterConfig, SimBus, SimHwInterface};
+use profibus::{
+    dpv1_status_byte2, sap_diagnose_byte1, sap_diagnose_byte3, DataHandlingInterface, PbDpSlave,
+    ProfibusConfig,
+};
+
+const SLAVE_ADDR: u8 = 0x0B;
+const MODULE_CONFIG: [u8; 2] = [0x10, 0x20];
+
+struct DataHandling;
+
+impl DataHandlingInterface for DataHandling {}
+
+type Slave = PbDpSlave<SimHwInterface, DataHandling, 200, 1, 1, 0, 64, 2>;
+
+fn wire_break(channel: u8) -> ChannelDiag {
+    ChannelDiag {
+        slot: 2,
+        channel,
+        direction: ChannelDirection::Input,
+        channel_type: ChannelType::Word,
+        error: ChannelError::LineBreak,
+    }
+}
+
+fn setup(user_para: &[u8]) -> (Slave, DpMaster) {
+    let bus = SimBus::new(500_000);
+    let slave = PbDpSlave::new(
+        bus.interface(),
+        DataHandling,
+        ProfibusConfig::default()
+            .ident_high(0x00)
+            .ident_low(0x2B)
+            .t_s(SLAVE_ADDR)
+            .dpv1(!user_para.is_empty())
+            .dpv1_alarms(dpv1_status_byte2::DIAGNOSE_ALARM),
+        MODULE_CONFIG,
+    );
+    let master = DpMaster::new(
+        bus,
+        MasterConfig::default()
+            .ident_high(0x00)
+            .ident_low(0x2B)
+            .slave_addr(SLAVE_ADDR)
+            .module_config(&MODULE_CONFIG)
+            .user_para(user_para),
+    );
+    (slave, master)
+}
+
+#[test]
+fn wire_break_appears_and_disappears() {
+    let (mut slave, mut master) = setup(&[]);
+    master.startup(&mut slave).unwrap();
+    slave.set_ext_diag(&ExtDiag::default().device(&[0x11]));
+
+    assert!(slave.channel_fault(wire_break(1)));
+    assert!(master.data_exchange(&mut slave, &[0x01]).unwrap().1);
+    let diagnostic = master.get_diagnostic(&mut slave).unwrap();
+    assert_ne!(diagnostic.status_1 & sap_diagnose_byte1::EXT_DIAG, 0);
+    // device block of the application first, then the channel
+    assert_eq!(diagnostic.ext_diag, [0x02, 0x11, 0x82, 0x41, 0xA6]);
+
+    slave.clear_channel_fault(2, 1, ChannelDirection::Input);
+    assert!(master.data_exchange(&mut slave, &[0x01]).unwrap().1);
+    assert_eq!(
+        master.get_diagnostic(&mut slave).unwrap().ext_diag,
+        [0x02, 0x11]
+    );
+
+    slave.set_ext_diag(&ExtDiag::default());
+    let diagnostic = master.get_diagnostic(&mut slave).unwrap();
+    assert_eq!(diagnostic.status_1 & sap_diagnose_byte1::EXT_DIAG, 0);
+}
+
+#[test]
+fn one_entry_per_channel_and_limited_table() {
+    let (mut slave, mut master) = setup(&[]);
+    master.startup(&mut slave).unwrap();
+    assert!(slave.channel_fault(wire_break(0)));
+    assert!(slave.channel_fault(ChannelDiag {
+        error: ChannelError::UpperLimit,
+        ..wire_break(0)
+    }));
+    assert_eq!(slave.channel_faults().count(), 1);
+    assert_eq!(
+        slave.channel_faults().next().unwrap().error,
+        ChannelError::UpperLimit
+    );
+
+    for channel in 1..MAX_CHANNEL_FAULTS as u8 {
+        assert!(slave.channel_fault(wire_break(channel)));
+    }
+    assert!(!slave.channel_fault(wire_break(MAX_CHANNEL_FAULTS as u8)));
+    let diagnostic = master.get_diagnostic(&mut slave).unwrap();
+    assert_eq!(diagnostic.status_3, sap_diagnose_byte3::DIAG_SIZE_ERROR);
+    assert_eq!(diagnostic.ext_diag.len(), 3 * MAX_CHANNEL_FAULTS);
+}
+
+#[test]
+fn faults_raise_diagnostic_alarms() {
+    let (mut slave, mut master) = setup(&[0x80, dpv1_status_byte2::DIAGNOSE_ALARM, 0x00]);
+    master.startup(&mut slave).unwrap();
+
+    slave.channel_fault(wire_break(1));
+    slave.channel_fault(wire_break(2));
+    let alarm = master.get_diagnostic(&mut slave).unwrap().alarm().unwrap();
+    assert_eq!(alarm.alarm_type, AlarmType::Diagnostic);
+    assert_eq!(alarm.slot, 2);
+    assert_eq!(alarm.specifier, AlarmSpecifier::Appears);
+    assert_eq!(alarm.data(), [0x82, 0x41, 0xA6]);
+    master.alarm_ack(&mut slave, &alarm).unwrap();
+    let alarm = master.get_diagnostic(&mut slave).unwrap().alarm().unwrap();
+    master.alarm_ack(&mut slave, &alarm).unwrap();
+
+    // channel 2 is still faulty
+    slave.clear_channel_fault(2, 1, ChannelDirection::Input);
+    let alarm = master.get_diagnostic(&mut slave).unwrap().alarm().unwrap();
+    assert_eq!(alarm.specifier, AlarmSpecifier::DisappearsOtherError);
+    master.alarm_ack(&mut slave, &alarm).unwrap();
+    slave.clear_channel_fault(2, 2, ChannelDirection::Input);
+    let alarm = master.get_diagnostic(&mut slave).unwrap().alarm().unwrap();
+    assert_eq!(alarm.specifier, AlarmSpecifier::Disappears);
+}
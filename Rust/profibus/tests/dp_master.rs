@@ -0,0 +1,215 @@
+use profibus::sim::{DpMaster, MasterConfig, MasterError, SimBus, SimHwInterface};
+use profibus::{
+    fc_response, sap_diagnose_byte1, sap_diagnose_byte2, DataHandlingInterface, DpSlaveState,
+    FrameError, PbDpSlave, ProfibusConfig,
+};
+
+const BAUDRATE: u32 = 500_000;
+const SLAVE_ADDR: u8 = 0x0B;
+const MODULE_CONFIG: [u8; 5] = [0x22, 0x20, 0x20, 0x10, 0x10];
+
+struct DataHandling {}
+
+impl DataHandlingInterface for DataHandling {}
+
+type Slave = PbDpSlave<SimHwInterface, DataHandling, 50, 2, 5, 0, 0, 5>;
+
+fn setup(master_config: MasterConfig) -> (Slave, DpMaster) {
+    let bus = SimBus::new(BAUDRATE);
+    let slave = PbDpSlave::new(
+        bus.interface(),
+        DataHandling {},
+        ProfibusConfig::default()
+            .ident_high(0x00)
+            .ident_low(0x2B)
+            .t_s(SLAVE_ADDR),
+        MODULE_CONFIG,
+    );
+    let master = DpMaster::new(bus, master_config.slave_addr(SLAVE_ADDR));
+    (slave, master)
+}
+
+fn master_config() -> MasterConfig {
+    MasterConfig::default()
+        .ident_high(0x00)
+        .ident_low(0x2B)
+        .module_config(&MODULE_CONFIG)
+}
+
+#[test]
+fn startup_walks_through_slave_states() {
+    let (mut slave, mut master) = setup(master_config());
+    assert_eq!(slave.slave_state(), DpSlaveState::Por);
+
+    let diagnostic = master.get_diagnostic(&mut slave).unwrap();
+    assert_ne!(diagnostic.status_2 & sap_diagnose_byte2::PRM_REQ, 0);
+    assert_eq!(diagnostic.master_addr, 0xFF);
+    assert_eq!((diagnostic.ident_high, diagnostic.ident_low), (0x00, 0x2B));
+    assert_eq!(slave.slave_state(), DpSlaveState::Wrpm);
+
+    master.set_prm(&mut slave).unwrap();
+    assert_eq!(slave.slave_state(), DpSlaveState::Wcfg);
+
+    master.chk_cfg(&mut slave).unwrap();
+    assert_eq!(slave.slave_state(), DpSlaveState::Dxchg);
+
+    let diagnostic = master.get_diagnostic(&mut slave).unwrap();
+    assert!(diagnostic.is_ready_for_data_exchange());
+    assert_eq!(diagnostic.master_addr, 2);
+}
+
+#[test]
+fn diagnostic_reports_the_parameterizing_master() {
+    let (mut slave, mut master) = setup(master_config().master_addr(1));
+    master.startup(&mut slave).unwrap();
+    // the address without the SAP extension bit, stored by Set_Prm
+    let diagnostic = master.get_diagnostic(&mut slave).unwrap();
+    assert_eq!(diagnostic.master_addr, 1);
+}
+
+#[test]
+fn data_exchange_round_trip() {
+    let (mut slave, mut master) = setup(master_config().watchdog(10, 10).group(0x01));
+    master.startup(&mut slave).unwrap();
+
+    slave.access_input().copy_from_slice(&[0x12, 0x34]);
+    let (inputs, diagnostic) = master.data_exchange(&mut slave, &[1, 2, 3, 4, 5]).unwrap();
+    assert_eq!(inputs, [0x12, 0x34]);
+    assert!(!diagnostic);
+    assert_eq!(slave.access_output(), &[1, 2, 3, 4, 5]);
+
+    for cycle in 0..10u8 {
+        slave.access_input()[1] = cycle;
+        let (inputs, _) = master.data_exchange(&mut slave, &[cycle; 5]).unwrap();
+        assert_eq!(inputs, [0x12, cycle]);
+        assert_eq!(slave.access_output(), &[cycle; 5]);
+    }
+    assert_eq!(slave.slave_state(), DpSlaveState::Dxchg);
+}
+
+#[test]
+fn get_cfg_returns_module_config() {
+    let (mut slave, mut master) = setup(master_config());
+    assert_eq!(master.get_cfg(&mut slave).unwrap(), MODULE_CONFIG);
+}
+
+#[test]
+fn wrong_config_is_reported() {
+    let (mut slave, mut master) = setup(master_config().module_config(&[0x22, 0x10]));
+    match master.startup(&mut slave) {
+        Err(MasterError::NotReady(diagnostic)) => {
+            assert_ne!(diagnostic.status_1 & sap_diagnose_byte1::CFG_FAULT, 0);
+        }
+        result => panic!("unexpected startup result {:?}", result),
+    }
+}
+
+#[test]
+fn config_not_matching_data_size_is_reported() {
+    // the slave announces 3 input bytes but only has 2
+    let module_config = [0x22, 0x20, 0x20, 0x10, 0x11];
+    let bus = SimBus::new(BAUDRATE);
+    let mut slave: Slave = PbDpSlave::new(
+        bus.interface(),
+        DataHandling {},
+        ProfibusConfig::default()
+            .ident_high(0x00)
+            .ident_low(0x2B)
+            .t_s(SLAVE_ADDR),
+        module_config,
+    );
+    let mut master = DpMaster::new(
+        bus,
+        master_config()
+            .module_config(&module_config)
+            .slave_addr(SLAVE_ADDR),
+    );
+    match master.startup(&mut slave) {
+        Err(MasterError::NotReady(diagnostic)) => {
+            assert_ne!(diagnostic.status_1 & sap_diagnose_byte1::CFG_FAULT, 0);
+        }
+        result => panic!("unexpected startup result {:?}", result),
+    }
+    assert_eq!(slave.slave_state(), DpSlaveState::Wcfg);
+}
+
+#[test]
+fn outputs_longer_than_a_telegram_are_refused() {
+    let (mut slave, mut master) = setup(master_config());
+    master.startup(&mut slave).unwrap();
+    assert_eq!(
+        master.data_exchange(&mut slave, &[0; 247]),
+        Err(MasterError::Frame(FrameError::InvalidLength(250)))
+    );
+}
+
+#[test]
+fn wrong_ident_is_not_acknowledged() {
+    let (mut slave, mut master) = setup(master_config().ident_low(0x2C));
+    master.get_diagnostic(&mut slave).unwrap();
+    assert_eq!(master.set_prm(&mut slave), Err(MasterError::NoResponse));
+    assert_eq!(slave.slave_state(), DpSlaveState::Wrpm);
+}
+
+#[test]
+fn read_inputs_and_outputs_in_data_exchange() {
+    let (mut slave, mut master) = setup(master_config());
+    master.startup(&mut slave).unwrap();
+    slave.access_input().copy_from_slice(&[0x12, 0x34]);
+    master.data_exchange(&mut slave, &[1, 2, 3, 4, 5]).unwrap();
+
+    assert_eq!(master.read_inputs(&mut slave).unwrap(), vec![0x12, 0x34]);
+    assert_eq!(
+        master.read_outputs(&mut slave).unwrap(),
+        vec![1, 2, 3, 4, 5]
+    );
+
+    // the cyclic exchange goes on afterwards
+    let (inputs, _) = master.data_exchange(&mut slave, &[6; 5]).unwrap();
+    assert_eq!(inputs, vec![0x12, 0x34]);
+}
+
+#[test]
+fn class2_master_reads_the_process_image() {
+    let bus = SimBus::new(BAUDRATE);
+    let mut slave: Slave = PbDpSlave::new(
+        bus.interface(),
+        DataHandling {},
+        ProfibusConfig::default()
+            .ident_high(0x00)
+            .ident_low(0x2B)
+            .t_s(SLAVE_ADDR),
+        MODULE_CONFIG,
+    );
+    let mut master = DpMaster::new(bus.clone(), master_config().slave_addr(SLAVE_ADDR));
+    master.startup(&mut slave).unwrap();
+    slave.access_input().copy_from_slice(&[0xAB, 0xCD]);
+    master.data_exchange(&mut slave, &[9; 5]).unwrap();
+
+    let mut class2 = DpMaster::new(bus, master_config().master_addr(5).slave_addr(SLAVE_ADDR));
+    assert_eq!(class2.read_inputs(&mut slave).unwrap(), vec![0xAB, 0xCD]);
+    assert_eq!(class2.read_outputs(&mut slave).unwrap(), vec![9; 5]);
+
+    // the parameterizing master keeps the slave
+    let diagnostic = master.get_diagnostic(&mut slave).unwrap();
+    assert_eq!(diagnostic.master_addr, 2);
+}
+
+#[test]
+fn read_io_rejected_before_data_exchange() {
+    let (mut slave, mut master) = setup(master_config());
+    master.get_diagnostic(&mut slave).unwrap();
+    assert_eq!(
+        master.read_inputs(&mut slave),
+        Err(MasterError::Rejected {
+            function_code: fc_response::SAP_NOT_ACTIVE
+        })
+    );
+    master.set_prm(&mut slave).unwrap();
+    assert_eq!(
+        master.read_outputs(&mut slave),
+        Err(MasterError::Rejected {
+            function_code: fc_response::SAP_NOT_ACTIVE
+        })
+    );
+}
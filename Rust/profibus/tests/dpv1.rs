@@ -0,0 +1,95 @@
+use profibus::sim::{DpMaster, MasterConfig, SimBus, SimHwInterface};
+use profibus::{
+    dpv1_status_byte1, dpv1_status_byte2, sap_diagnose_byte1, DataHandlingInterface, DpSlaveState,
+    Dpv1Status, PbDpSlave, ProfibusConfig,
+};
+
+const SLAVE_ADDR: u8 = 0x0B;
+const MODULE_CONFIG: [u8; 2] = [0x10, 0x20];
+
+struct DataHandling {}
+
+impl DataHandlingInterface for DataHandling {}
+
+type Slave = PbDpSlave<SimHwInterface, DataHandling, 50, 1, 1, 2, 0, 2>;
+
+fn setup(user_para: &[u8]) -> (Slave, DpMaster) {
+    let bus = SimBus::new(500_000);
+    let slave = PbDpSlave::new(
+        bus.interface(),
+        DataHandling {},
+        ProfibusConfig::default()
+            .ident_high(0x00)
+            .ident_low(0x2B)
+            .t_s(SLAVE_ADDR)
+            .dpv1(true)
+            .dpv1_alarms(dpv1_status_byte2::DIAGNOSE_ALARM),
+        MODULE_CONFIG,
+    );
+    let master = DpMaster::new(
+        bus,
+        MasterConfig::default()
+            .ident_high(0x00)
+            .ident_low(0x2B)
+            .slave_addr(SLAVE_ADDR)
+            .module_config(&MODULE_CONFIG)
+            .user_para(user_para),
+    );
+    (slave, master)
+}
+
+#[test]
+fn status_bytes_are_split_from_user_parameters() {
+    let (mut slave, mut master) = setup(&[0xC4, 0x20, 0x00, 0x12, 0x34]);
+    master.startup(&mut slave).unwrap();
+    let status = slave.dpv1_status();
+    assert_eq!(status, Dpv1Status([0xC4, 0x20, 0x00]));
+    assert!(status.dpv1_mode() && status.fail_safe() && status.watchdog_1ms());
+    assert_eq!(status.alarms(), dpv1_status_byte2::DIAGNOSE_ALARM);
+    assert_eq!(slave.user_para(), &[0x12, 0x34]);
+}
+
+#[test]
+fn unsupported_features_are_a_parameter_fault() {
+    for user_para in [
+        // publisher mode
+        &[
+            dpv1_status_byte1::DPV1_MODE | dpv1_status_byte1::PUBLISHER_MODE,
+            0x00,
+            0x00,
+        ][..],
+        // process alarm is not offered by the slave
+        &[
+            dpv1_status_byte1::DPV1_MODE,
+            dpv1_status_byte2::PROZESS_ALARM,
+            0x00,
+        ][..],
+        // Chk_Cfg is not handed to the application
+        &[
+            dpv1_status_byte1::DPV1_MODE,
+            dpv1_status_byte2::CHECK_CONFIG_MODE,
+            0x00,
+        ][..],
+        // status bytes missing
+        &[dpv1_status_byte1::DPV1_MODE][..],
+    ] {
+        let (mut slave, mut master) = setup(user_para);
+        master.get_diagnostic(&mut slave).unwrap();
+        master.set_prm(&mut slave).unwrap();
+        assert_eq!(slave.slave_state(), DpSlaveState::Wrpm);
+        let diagnostic = master.get_diagnostic(&mut slave).unwrap();
+        assert_ne!(diagnostic.status_1 & sap_diagnose_byte1::PRM_FAULT, 0);
+    }
+}
+
+#[test]
+fn fail_safe_clears_outputs_on_empty_data_exchange() {
+    let (mut slave, mut master) = setup(&[0xC0, 0x00, 0x00, 0x00, 0x00]);
+    master.startup(&mut slave).unwrap();
+    master.data_exchange(&mut slave, &[0x55]).unwrap();
+    assert_eq!(slave.access_output(), &[0x55]);
+    master.data_exchange(&mut slave, &[]).unwrap();
+    assert_eq!(slave.access_output(), &[0x00]);
+    master.data_exchange(&mut slave, &[0x66]).unwrap();
+    assert_eq!(slave.access_output(), &[0x66]);
+}
@@ -0,0 +1,131 @@
+use profibus::diag::{ChannelDiag, ChannelDirection, ChannelError, ChannelType, ExtDiag};
+use profibus::sim::{DpMaster, MasterConfig, SimBus, SimHwInterface};
+use profibus::{
+    sap_diagnose_byte1, sap_diagnose_byte3, DataHandlingInterface, PbDpSlave, ProfibusConfig,
+};
+
+const SLAVE_ADDR: u8 = 0x0B;
+const MODULE_CONFIG: [u8; 2] = [0x10, 0x20];
+
+struct DataHandling;
+
+impl DataHandlingInterface for DataHandling {}
+
+type Slave = PbDpSlave<SimHwInterface, DataHandling, 128, 1, 1, 0, 12, 2>;
+
+const SHORT_CIRCUIT: ChannelDiag = ChannelDiag {
+    slot: 1,
+    channel: 3,
+    direction: ChannelDirection::Output,
+    channel_type: ChannelType::Bit,
+    error: ChannelError::ShortCircuit,
+};
+
+fn setup() -> (Slave, DpMaster) {
+    let bus = SimBus::new(500_000);
+    let slave = PbDpSlave::new(
+        bus.interface(),
+        DataHandling,
+        ProfibusConfig::default()
+            .ident_high(0x00)
+            .ident_low(0x2B)
+            .t_s(SLAVE_ADDR),
+        MODULE_CONFIG,
+    );
+    let master = DpMaster::new(
+        bus,
+        MasterConfig::default()
+            .ident_high(0x00)
+            .ident_low(0x2B)
+            .slave_addr(SLAVE_ADDR)
+            .module_config(&MODULE_CONFIG),
+    );
+    (slave, master)
+}
+
+#[test]
+fn blocks_are_reported_in_the_diagnostic() {
+    let (mut slave, mut master) = setup();
+    master.startup(&mut slave).unwrap();
+    assert!(master.get_diagnostic(&mut slave).unwrap().ext_diag.is_empty());
+
+    slave.set_ext_diag(
+        &ExtDiag::default()
+            .device(&[0xAA, 0xBB])
+            .identifier(&[1, 9])
+            .channel(SHORT_CIRCUIT),
+    );
+    // new diagnostic is announced once
+    assert!(master.data_exchange(&mut slave, &[0x01]).unwrap().1);
+    let diagnostic = master.get_diagnostic(&mut slave).unwrap();
+    assert_ne!(diagnostic.status_1 & sap_diagnose_byte1::EXT_DIAG, 0);
+    assert_eq!(diagnostic.status_3, sap_diagnose_byte3::DIAG_SIZE_OK);
+    assert_eq!(
+        diagnostic.ext_diag,
+        [0x03, 0xAA, 0xBB, 0x43, 0x02, 0x02, 0x81, 0x83, 0x21]
+    );
+    assert!(!master.data_exchange(&mut slave, &[0x01]).unwrap().1);
+
+    slave.set_ext_diag(&ExtDiag::default());
+    let diagnostic = master.get_diagnostic(&mut slave).unwrap();
+    assert_eq!(diagnostic.status_1 & sap_diagnose_byte1::EXT_DIAG, 0);
+    assert!(diagnostic.ext_diag.is_empty());
+}
+
+#[test]
+fn blocks_beyond_max_diag_len_set_overflow() {
+    let (mut slave, mut master) = setup();
+    master.startup(&mut slave).unwrap();
+    // 9 + 3 bytes fit, the last channel does not
+    let diag = ExtDiag::default()
+        .device(&[0; 8])
+        .channel(SHORT_CIRCUIT)
+        .channel(SHORT_CIRCUIT);
+    assert_eq!(diag.byte_len(), 15);
+    slave.set_ext_diag(&diag);
+    let diagnostic = master.get_diagnostic(&mut slave).unwrap();
+    assert_eq!(diagnostic.status_3, sap_diagnose_byte3::DIAG_SIZE_ERROR);
+    assert_eq!(diagnostic.ext_diag.len(), 12);
+}
+
+#[test]
+fn builder_leaves_out_oversized_blocks() {
+    let diag = ExtDiag::default().device(&[0; 63]).channel(SHORT_CIRCUIT);
+    assert!(diag.is_overflow());
+    assert_eq!(diag.as_bytes(), [0x81, 0x83, 0x21]);
+    assert!(!ExtDiag::default().device(&[0; 62]).is_overflow());
+}
+
+#[test]
+fn manufacturer_error_type_is_checked() {
+    assert_eq!(ChannelError::manufacturer(3), None);
+    let error = ChannelError::manufacturer(17).unwrap();
+    assert_eq!(error, ChannelError::Manufacturer(17));
+    let diag = ChannelDiag {
+        error,
+        ..SHORT_CIRCUIT
+    };
+    assert_eq!(diag.encode()[2], 0x20 | 17);
+    // not reported as overvoltage
+    assert_eq!(ChannelError::Manufacturer(3).code(), ChannelError::Error.code());
+}
+
+#[test]
+fn high_priority_response_carries_the_inputs() {
+    let (mut slave, mut master) = setup();
+    master.startup(&mut slave).unwrap();
+    slave.access_input()[0] = 0x5A;
+    slave.set_ext_diag(&ExtDiag::default().channel(SHORT_CIRCUIT));
+    assert_eq!(
+        master.data_exchange(&mut slave, &[0x01]).unwrap(),
+        (vec![0x5A], true)
+    );
+    assert_eq!(slave.access_output(), &[0x01]);
+    // low priority again once the master fetched the diagnostic
+    slave.access_input()[0] = 0x5B;
+    master.get_diagnostic(&mut slave).unwrap();
+    assert_eq!(
+        master.data_exchange(&mut slave, &[0x02]).unwrap(),
+        (vec![0x5B], false)
+    );
+}
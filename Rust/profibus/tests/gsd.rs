@@ -0,0 +1,136 @@
+use profibus::gsd::{Baudrate, GsdConfig, GsdError};
+use profibus::sim::{SimBus, SimHwInterface};
+use profibus::{dpv1_status_byte2, DataHandlingInterface, PbDpSlave, ProfibusConfig};
+
+struct DataHandling {}
+
+impl DataHandlingInterface for DataHandling {}
+
+fn gsd(config: &GsdConfig) -> Vec<String> {
+    let mut text = String::new();
+    config.write(&mut text).unwrap();
+    assert!(text.ends_with("\r\n"));
+    text.split("\r\n").map(str::to_owned).collect()
+}
+
+#[test]
+fn describes_slave_configuration() {
+    let bus = SimBus::new(500_000);
+    let slave = PbDpSlave::<SimHwInterface, DataHandling, 50, 2, 5, 3, 4, 5>::new(
+        bus.interface(),
+        DataHandling {},
+        ProfibusConfig::default().ident_high(0x00).ident_low(0x2B),
+        [0x22, 0x20, 0x20, 0x10, 0x10],
+    );
+    let lines = gsd(&slave
+        .gsd_config()
+        .model_name("Remote IO")
+        .baudrate(Baudrate::B500k)
+        .baudrate(Baudrate::B1M5)
+        .sync(false));
+
+    assert_eq!(lines[0], "#Profibus_DP");
+    for expected in [
+        "GSD_Revision = 5",
+        "Model_Name = \"Remote IO\"",
+        "Ident_Number = 0x002B",
+        "500_supp = 1",
+        "1.5M_supp = 1",
+        "MaxTsdr_1.5M = 150",
+        "Freeze_Mode_supp = 1",
+        "Sync_Mode_supp = 0",
+        "Max_Input_Len = 2",
+        "Max_Output_Len = 5",
+        "Max_Diag_Data_Len = 10",
+        "User_Prm_Data_Len = 3",
+        "User_Prm_Data = 0x00,0x00,0x00",
+        "Module = \"Module\" 0x22,0x20,0x20,0x10,0x10",
+        "EndModule",
+    ] {
+        assert!(lines.iter().any(|line| line == expected), "{expected}");
+    }
+    assert!(!lines.iter().any(|line| line.starts_with("12M_supp")));
+}
+
+#[test]
+fn dpv1_record_len_fits_the_buffer() {
+    let bus = SimBus::new(500_000);
+    let slave = PbDpSlave::<SimHwInterface, DataHandling, 50, 2, 5, 3, 0, 5>::new(
+        bus.interface(),
+        DataHandling {},
+        ProfibusConfig::default().dpv1(true),
+        [0x22, 0x20, 0x20, 0x10, 0x10],
+    );
+    let lines = gsd(&slave.gsd_config());
+    // 50 bytes less SD2 frame, SAPs and DPV1 header
+    assert!(lines.iter().any(|line| line == "C1_Max_Data_Len = 35"));
+    assert!(lines.iter().any(|line| line == "C2_Max_Data_Len = 35"));
+}
+
+#[test]
+fn counts_special_identifier_format() {
+    // 0xC1: input/output with one vendor byte, 20 output words, 4 input bytes
+    let lines = gsd(&GsdConfig::default().module_config(&[0xC1, 0x53, 0x03, 0xAA, 0x13]));
+    assert!(lines.iter().any(|line| line == "Max_Input_Len = 8"));
+    assert!(lines.iter().any(|line| line == "Max_Output_Len = 40"));
+}
+
+#[test]
+fn rejects_values_outside_gsd_limits() {
+    let mut text = String::new();
+    let config = GsdConfig::default()
+        .module_config(&[0x10])
+        .model_name("A model name which is far too long");
+    assert_eq!(
+        config.write(&mut text),
+        Err(GsdError::TooLong("Model_Name"))
+    );
+
+    let config = GsdConfig::default()
+        .module_config(&[0x10])
+        .user_prm_data_len(238);
+    assert_eq!(
+        config.write(&mut text),
+        Err(GsdError::TooLong("User_Prm_Data_Len"))
+    );
+
+    let config = GsdConfig::default()
+        .module_config(&[0x10])
+        .vendor_name("A \"quoted\" name");
+    assert_eq!(
+        config.write(&mut text),
+        Err(GsdError::InvalidValue("Vendor_Name"))
+    );
+
+    let config = GsdConfig::default();
+    assert_eq!(
+        config.write(&mut text),
+        Err(GsdError::InvalidValue("Module"))
+    );
+}
+
+#[test]
+fn alarm_block_counts_into_max_diag_data_len() {
+    let config = GsdConfig::default()
+        .module_config(&[0x10])
+        .dpv1(true)
+        .alarms(dpv1_status_byte2::DIAGNOSE_ALARM)
+        .ext_diag_len(175);
+    assert!(gsd(&config)
+        .iter()
+        .any(|line| line == "Max_Diag_Data_Len = 244"));
+
+    let mut text = String::new();
+    assert_eq!(
+        config.ext_diag_len(176).write(&mut text),
+        Err(GsdError::TooLong("Max_Diag_Data_Len"))
+    );
+    // without alarms the extended diagnostic may use the room
+    let config = GsdConfig::default()
+        .module_config(&[0x10])
+        .dpv1(true)
+        .ext_diag_len(238);
+    assert!(gsd(&config)
+        .iter()
+        .any(|line| line == "Max_Diag_Data_Len = 244"));
+}
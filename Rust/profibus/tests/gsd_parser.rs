@@ -0,0 +1,182 @@
+use profibus::gsd::{Baudrate, GsdConfig, GsdDevice, GsdParseError, PrmDataType, PrmValues};
+
+const VENDOR_GSD: &str = r#"; Remote IO with two module types
+#Profibus_DP
+GSD_Revision = 3
+Vendor_Name = "ACME"        ; comment after a value
+Model_Name = "IO; 8 channels"
+Ident_Number = 0x1234
+500_supp = 1
+1.5M_supp = 1
+MaxTsdr_500 = 100
+MaxTsdr_1.5M = 150
+Freeze_Mode_supp = 1
+Sync_Mode_supp = 0
+Min_Slave_Intervall = 6
+Modular_Station = 1
+Max_Module = 4
+Max_Diag_Data_Len = 32
+Max_User_Prm_Data_Len = 5
+
+PrmText = 1
+Text(0) = "off"
+Text(1) = "on"
+EndPrmText
+
+ExtUserPrmData = 1 "Diagnosis"
+Bit(2) 1 0-1
+Prm_Text_Ref = 1
+EndExtUserPrmData
+
+ExtUserPrmData = 2 "Filter"
+BitArea(4-6) 3 0-7
+EndExtUserPrmData
+
+ExtUserPrmData = 3 "Cycle time"
+Unsigned16 100 10-1000
+EndExtUserPrmData
+
+ExtUserPrmData = 4 "Range"
+Unsigned8 2 1,2,4,8
+EndExtUserPrmData
+
+Ext_User_Prm_Data_Const(0) = 0x00,0x00,0x00
+Ext_User_Prm_Data_Ref(0) = 1
+Ext_User_Prm_Data_Ref(0) = 2
+Ext_User_Prm_Data_Ref(1) = 3
+
+Module = "8 DI" 0x10
+1
+EndModule
+Module = "8 DO, range" \
+    0x20
+2
+Ext_Module_Prm_Data_Len = 1
+Ext_User_Prm_Data_Ref(0) = 4
+EndModule
+"#;
+
+#[test]
+fn parses_vendor_gsd() {
+    let device = GsdDevice::parse(VENDOR_GSD).unwrap();
+    assert_eq!(device.vendor_name, "ACME");
+    assert_eq!(device.model_name, "IO; 8 channels");
+    assert_eq!(device.ident_number, 0x1234);
+    assert_eq!(
+        device.baudrates,
+        vec![(Baudrate::B500k, 100), (Baudrate::B1M5, 150)]
+    );
+    assert!(device.freeze && !device.sync);
+    assert_eq!(device.min_slave_interval, 6);
+    assert_eq!(device.max_diag_data_len, 32);
+    assert_eq!(device.max_user_prm_data_len, 5);
+
+    assert_eq!(device.prms.len(), 3);
+    let diagnosis = &device.prms[0];
+    assert_eq!(diagnosis.data_type, PrmDataType::Bit(2));
+    assert_eq!(diagnosis.text(1), Some("on"));
+    assert_eq!(device.prms[1].data_type, PrmDataType::BitArea(4, 6));
+    assert_eq!(device.prms[2].values, PrmValues::Range(10, 1000));
+
+    let module = device.module("8 DO, range").unwrap();
+    assert_eq!(module.config, vec![0x20]);
+    assert_eq!(module.reference, Some(2));
+    assert_eq!(module.prms[0].values, PrmValues::List(vec![1, 2, 4, 8]));
+
+    assert_eq!(
+        device.module_config(&[0, 1, 1]),
+        Some(vec![0x10, 0x20, 0x20])
+    );
+    assert_eq!(device.module_config(&[2]), None);
+    // bit 2 and bits 4..=6 of byte 0, big endian cycle time, one byte per module
+    assert_eq!(
+        device.default_user_prm_data(&[1, 0]),
+        Some(vec![0x34, 0x00, 100, 2])
+    );
+    // beyond Max_User_Prm_Data_Len
+    assert_eq!(
+        device.default_user_prm_data(&[1, 1]),
+        Some(vec![0x34, 0x00, 100, 2, 2])
+    );
+    assert_eq!(device.default_user_prm_data(&[1, 1, 1]), None);
+}
+
+#[test]
+fn max_user_prm_data_len_limits_all_parameters() {
+    let gsd = "Ident_Number = 1\nUser_Prm_Data_Len = 1\nMax_User_Prm_Data_Len = 20\n\
+               User_Prm_Data = 0x05\n\
+               Module = \"A\" 0x10\nExt_Module_Prm_Data_Len = 1\n\
+               Ext_User_Prm_Data_Const(0) = 0x07\nEndModule\n";
+    let device = GsdDevice::parse(gsd).unwrap();
+    assert_eq!(device.user_prm_data_len, 1);
+    assert_eq!(device.default_user_prm_data(&[0]), Some(vec![0x05, 0x07]));
+}
+
+#[test]
+fn reads_generated_gsd() {
+    let mut text = String::new();
+    GsdConfig::default()
+        .ident_number(0x002B)
+        .baudrate(Baudrate::B12M)
+        .module_config(&[0x22, 0x10])
+        .user_prm_data_len(2)
+        .write(&mut text)
+        .unwrap();
+    let device = GsdDevice::parse(&text).unwrap();
+    assert_eq!(device.ident_number, 0x002B);
+    assert_eq!(
+        device.baudrates,
+        vec![(Baudrate::B12M, Baudrate::B12M.max_tsdr())]
+    );
+    assert_eq!(device.modules.len(), 1);
+    assert_eq!(device.modules[0].config, vec![0x22, 0x10]);
+    assert_eq!(device.default_user_prm_data(&[0]), Some(vec![0, 0]));
+}
+
+#[test]
+fn reports_errors_with_line() {
+    let unknown_ref = "Ident_Number = 1\nExt_User_Prm_Data_Ref(0) = 7\n";
+    assert_eq!(
+        GsdDevice::parse(unknown_ref),
+        Err(GsdParseError::UnknownReference {
+            line: 2,
+            reference: 7
+        })
+    );
+    let open_module = "Ident_Number = 1\n\nModule = \"A\" 0x10\n";
+    assert_eq!(
+        GsdDevice::parse(open_module),
+        Err(GsdParseError::MissingEnd { line: 3 })
+    );
+    assert_eq!(
+        GsdDevice::parse("Vendor_Name = \"A\""),
+        Err(GsdParseError::MissingIdentNumber)
+    );
+}
+
+#[test]
+fn rejects_numbers_out_of_range() {
+    for (gsd, line) in [
+        ("Ident_Number = 0x12345\n", 1),
+        ("Ident_Number = 1\nGSD_Revision = 256\n", 2),
+        ("Ident_Number = 1\nExt_User_Prm_Data_Ref(-1) = 1\n", 2),
+        (
+            "Ident_Number = 1\nExtUserPrmData = 1 \"A\"\nBit(9) 0 0-1\n",
+            3,
+        ),
+        (
+            "Ident_Number = 1\nExtUserPrmData = 1 \"A\"\nBitArea(0-40) 0 0-1\n",
+            3,
+        ),
+        (
+            "Ident_Number = 1\nExtUserPrmData = 1 \"A\"\nBitArea(5-2) 0 0-1\n",
+            3,
+        ),
+    ] {
+        assert_eq!(
+            GsdDevice::parse(gsd),
+            Err(GsdParseError::InvalidNumber { line }),
+            "{gsd}"
+        );
+    }
+}
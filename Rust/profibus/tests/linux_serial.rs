@@ -0,0 +1,119 @@
+#![cfg(target_os = "linux")]
+
+use std::ffi::CStr;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::os::fd::{AsRawFd, FromRawFd};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use profibus::linux::{block_on, LinuxBus, LinuxCodecHwInterface, SerialConfig, SerialPort};
+use profibus::{
+    cmd_type, fc_request, fc_response, Codec, CodecConfig, DataHandlingInterface, PbDpSlave,
+    ProfibusConfig, UartAccess,
+};
+
+const BAUDRATE: u32 = 19_200;
+const SLAVE_ADDR: u8 = 0x0B;
+const MASTER_ADDR: u8 = 0x02;
+
+struct DataHandling {}
+
+impl DataHandlingInterface for DataHandling {}
+
+fn sd1(destination_addr: u8, source_addr: u8, function_code: u8) -> [u8; 6] {
+    let fcs = destination_addr
+        .wrapping_add(source_addr)
+        .wrapping_add(function_code);
+    [
+        cmd_type::SD1,
+        destination_addr,
+        source_addr,
+        function_code,
+        fcs,
+        cmd_type::ED,
+    ]
+}
+
+/// Opens a pty and returns its master side and the path of the slave side.
+fn pty() -> (File, String) {
+    unsafe {
+        let fd = libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY);
+        assert!(fd >= 0);
+        assert_eq!(libc::grantpt(fd), 0);
+        assert_eq!(libc::unlockpt(fd), 0);
+        let mut name = [0 as libc::c_char; 64];
+        assert_eq!(libc::ptsname_r(fd, name.as_mut_ptr(), name.len()), 0);
+        let path = CStr::from_ptr(name.as_ptr()).to_str().unwrap().to_owned();
+        (File::from_raw_fd(fd), path)
+    }
+}
+
+fn read_response(master: &mut File, len: usize, timeout: Duration) -> Vec<u8> {
+    let end = Instant::now() + timeout;
+    let mut response = Vec::new();
+    let mut buffer = [0; 64];
+    while response.len() < len && Instant::now() < end {
+        let mut poll_fd = libc::pollfd {
+            fd: master.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        if unsafe { libc::poll(&mut poll_fd, 1, 50) } > 0 {
+            let count = master.read(&mut buffer).unwrap();
+            response.extend_from_slice(&buffer[..count]);
+        }
+    }
+    response
+}
+
+#[test]
+fn slave_answers_fdl_status_on_pty() {
+    let (mut master, path) = pty();
+    let slave = thread::spawn(move || {
+        let port = SerialPort::open(&path, &SerialConfig::default().baudrate(BAUDRATE)).unwrap();
+        let bus = LinuxBus::new(port);
+        let mut slave = PbDpSlave::<_, _, 50, 2, 5, 0, 0, 5>::new(
+            bus.interface(),
+            DataHandling {},
+            ProfibusConfig::default()
+                .t_s(SLAVE_ADDR)
+                .tx_handling(UartAccess::Dma),
+            [0x22, 0x20, 0x20, 0x10, 0x10],
+        );
+        bus.run_for(&mut slave, Duration::from_millis(500)).unwrap();
+    });
+
+    thread::sleep(Duration::from_millis(100));
+    let function_code = fc_request::REQUEST + fc_request::FDL_STATUS;
+    master
+        .write_all(&sd1(SLAVE_ADDR, MASTER_ADDR, function_code))
+        .unwrap();
+    let response = read_response(&mut master, 6, Duration::from_millis(300));
+    assert_eq!(
+        response,
+        sd1(MASTER_ADDR, SLAVE_ADDR, fc_response::FDL_STATUS_OK)
+    );
+    slave.join().unwrap();
+}
+
+#[test]
+fn codec_receives_telegram_on_pty() {
+    let (mut master, path) = pty();
+    let codec = thread::spawn(move || {
+        let port = SerialPort::open(&path, &SerialConfig::default().baudrate(BAUDRATE)).unwrap();
+        let mut codec = Codec::new(
+            LinuxCodecHwInterface::new(port),
+            CodecConfig::default().t_s(SLAVE_ADDR),
+        );
+        let mut buffer = [0; 255];
+        block_on(codec.receive(&mut buffer)).map(|conn| (conn.source_addr, conn.function_code))
+    });
+
+    thread::sleep(Duration::from_millis(100));
+    let function_code = fc_request::REQUEST + fc_request::FDL_STATUS;
+    master
+        .write_all(&sd1(SLAVE_ADDR, MASTER_ADDR, function_code))
+        .unwrap();
+    assert_eq!(codec.join().unwrap(), Some((MASTER_ADDR, function_code)));
+}
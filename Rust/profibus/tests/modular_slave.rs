@@ -0,0 +1,136 @@
+use profibus::cfg::{CatalogModule, ModuleCatalog, Slot};
+use profibus::gsd::GsdDevice;
+use profibus::sim::{DpMaster, MasterConfig, MasterError, SimBus, SimHwInterface};
+use profibus::{
+    sap_diagnose_byte1, DataHandlingInterface, DpSlaveState, PbDpSlave, ProfibusConfig,
+};
+
+const SLAVE_ADDR: u8 = 0x0B;
+
+static CATALOG: [CatalogModule; 6] = [
+    CatalogModule {
+        name: "8 DI",
+        config: &[0x10],
+    },
+    CatalogModule {
+        name: "16 DI",
+        config: &[0x11],
+    },
+    CatalogModule {
+        name: "8 DO",
+        config: &[0x20],
+    },
+    CatalogModule {
+        name: "16 DO",
+        config: &[0x21],
+    },
+    CatalogModule {
+        name: "2 AI",
+        config: &[0x51],
+    },
+    CatalogModule {
+        name: "8 DI/8 DO",
+        config: &[0x30],
+    },
+];
+
+struct DataHandling {}
+
+impl DataHandlingInterface for DataHandling {}
+
+type Slave = PbDpSlave<SimHwInterface, DataHandling, 50, 16, 16, 0, 0, 8>;
+
+fn setup(module_config: &[u8]) -> (Slave, DpMaster) {
+    let bus = SimBus::new(500_000);
+    let slave = PbDpSlave::new_modular(
+        bus.interface(),
+        DataHandling {},
+        ProfibusConfig::default()
+            .ident_high(0x00)
+            .ident_low(0x2B)
+            .t_s(SLAVE_ADDR),
+        ModuleCatalog::new(&CATALOG).max_slots(8),
+    );
+    let master = DpMaster::new(
+        bus,
+        MasterConfig::default()
+            .ident_high(0x00)
+            .ident_low(0x2B)
+            .slave_addr(SLAVE_ADDR)
+            .module_config(module_config),
+    );
+    (slave, master)
+}
+
+#[test]
+fn accepts_combination_of_catalog_modules() {
+    // 8 DI, 2 AI, 16 DO, 8 DI/8 DO
+    let (mut slave, mut master) = setup(&[0x10, 0x51, 0x21, 0x30]);
+    master.startup(&mut slave).unwrap();
+    assert_eq!(slave.slave_state(), DpSlaveState::Dxchg);
+    assert_eq!(
+        master.get_cfg(&mut slave).unwrap(),
+        [0x10, 0x51, 0x21, 0x30]
+    );
+    assert_eq!(
+        slave.slots()[1],
+        Slot {
+            module: 4,
+            input_offset: 1,
+            input_len: 4,
+            output_offset: 0,
+            output_len: 0,
+        }
+    );
+    assert_eq!(
+        slave.slots()[3],
+        Slot {
+            module: 5,
+            input_offset: 5,
+            input_len: 1,
+            output_offset: 2,
+            output_len: 1,
+        }
+    );
+
+    slave.access_input()[..6].copy_from_slice(&[1, 2, 3, 4, 5, 6]);
+    let (inputs, _) = master.data_exchange(&mut slave, &[7, 8, 9]).unwrap();
+    assert_eq!(inputs, [1, 2, 3, 4, 5, 6]);
+    assert_eq!(slave.access_output()[..3], [7, 8, 9]);
+}
+
+#[test]
+fn rejects_config_outside_catalog() {
+    for module_config in [
+        // 32 DI is no catalog module
+        &[0x10, 0x13][..],
+        // nine slots
+        &[0x10; 9][..],
+        // no module at all
+        &[][..],
+    ] {
+        let (mut slave, mut master) = setup(module_config);
+        match master.startup(&mut slave) {
+            Err(MasterError::NotReady(diagnostic)) => {
+                assert_ne!(diagnostic.status_1 & sap_diagnose_byte1::CFG_FAULT, 0);
+            }
+            result => panic!("unexpected startup result {:?}", result),
+        }
+        assert_eq!(slave.slave_state(), DpSlaveState::Wcfg);
+        assert!(slave.slots().is_empty());
+    }
+}
+
+#[test]
+fn gsd_lists_catalog() {
+    let (slave, _) = setup(&[]);
+    let mut text = String::new();
+    slave.gsd_config().write(&mut text).unwrap();
+    let device = GsdDevice::parse(&text).unwrap();
+    assert!(device.modular_station);
+    assert_eq!(device.max_module, 8);
+    assert_eq!((device.max_input_len, device.max_output_len), (16, 16));
+    assert_eq!(device.modules.len(), CATALOG.len());
+    assert_eq!(device.module("2 AI").unwrap().config, [0x51]);
+    assert_eq!(device.module("2 AI").unwrap().reference, Some(5));
+}
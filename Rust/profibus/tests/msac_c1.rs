@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+
+use profibus::sim::SlaveResponse;
+use profibus::sim::{DpMaster, MasterConfig, MasterError, SimBus, SimHwInterface};
+use profibus::{
+    dpv1_error, fc_response, sap, DataHandlingInterface, PbDpSlave, ProfibusConfig, RecordError,
+    RecordHandlingInterface,
+};
+
+const SLAVE_ADDR: u8 = 0x0B;
+const MODULE_CONFIG: [u8; 2] = [0x10, 0x20];
+
+#[derive(Default)]
+struct Records {
+    records: HashMap<(u8, u8), Vec<u8>>,
+}
+
+impl RecordHandlingInterface for Records {
+    fn read_record(&mut self, slot: u8, index: u8, data: &mut [u8]) -> Result<usize, RecordError> {
+        let record = self
+            .records
+            .get(&(slot, index))
+            .ok_or(RecordError::InvalidIndex)?;
+        let len = record.len().min(data.len());
+        data[..len].copy_from_slice(&record[..len]);
+        Ok(len)
+    }
+
+    fn write_record(&mut self, slot: u8, index: u8, data: &[u8]) -> Result<(), RecordError> {
+        if slot != 0 {
+            return Err(RecordError::InvalidSlot);
+        }
+        self.records.insert((slot, index), data.to_vec());
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct DataHandling {
+    records: Records,
+}
+
+impl DataHandlingInterface for DataHandling {
+    fn record_handling(&mut self) -> Option<&mut dyn RecordHandlingInterface> {
+        Some(&mut self.records)
+    }
+}
+
+type Slave = PbDpSlave<SimHwInterface, DataHandling, 300, 1, 1, 0, 0, 2>;
+
+fn setup(dpv1_mode: u8) -> (Slave, DpMaster) {
+    let bus = SimBus::new(500_000);
+    let mut data_handling = DataHandling::default();
+    data_handling
+        .records
+        .records
+        .insert((0, 1), vec![0x12, 0x34, 0x56]);
+    let slave = PbDpSlave::new(
+        bus.interface(),
+        data_handling,
+        ProfibusConfig::default()
+            .ident_high(0x00)
+            .ident_low(0x2B)
+            .t_s(SLAVE_ADDR)
+            .dpv1(true),
+        MODULE_CONFIG,
+    );
+    let master = DpMaster::new(
+        bus,
+        MasterConfig::default()
+            .ident_high(0x00)
+            .ident_low(0x2B)
+            .slave_addr(SLAVE_ADDR)
+            .module_config(&MODULE_CONFIG)
+            .user_para(&[dpv1_mode, 0x00, 0x00]),
+    );
+    (slave, master)
+}
+
+#[test]
+fn reads_and_writes_records() {
+    let (mut slave, mut master) = setup(0x80);
+    master.startup(&mut slave).unwrap();
+
+    assert_eq!(
+        master.read_record(&mut slave, 0, 1, 240).unwrap(),
+        [0x12, 0x34, 0x56]
+    );
+    // shorter than the record
+    assert_eq!(
+        master.read_record(&mut slave, 0, 1, 2).unwrap(),
+        [0x12, 0x34]
+    );
+
+    let calibration = [0xAA; 200];
+    master.write_record(&mut slave, 0, 7, &calibration).unwrap();
+    assert_eq!(
+        master.read_record(&mut slave, 0, 7, 240).unwrap(),
+        calibration
+    );
+
+    // cyclic data continues in between
+    master.data_exchange(&mut slave, &[0x01]).unwrap();
+    assert_eq!(slave.access_output(), &[0x01]);
+}
+
+#[test]
+fn errors_of_the_application_are_returned() {
+    let (mut slave, mut master) = setup(0x80);
+    master.startup(&mut slave).unwrap();
+    assert_eq!(
+        master.read_record(&mut slave, 0, 9, 4),
+        Err(MasterError::Dpv1 {
+            error_decode: dpv1_error::DECODE_DPV1,
+            error_code_1: dpv1_error::INVALID_INDEX,
+            error_code_2: 0,
+        })
+    );
+    assert_eq!(
+        master.write_record(&mut slave, 3, 1, &[1]),
+        Err(MasterError::Dpv1 {
+            error_decode: dpv1_error::DECODE_DPV1,
+            error_code_1: dpv1_error::INVALID_SLOT,
+            error_code_2: 0,
+        })
+    );
+}
+
+#[test]
+fn sap_is_not_active_without_dpv1_mode() {
+    let (mut slave, mut master) = setup(0x00);
+    master.startup(&mut slave).unwrap();
+    assert_eq!(
+        master.sap_request(&mut slave, sap::MSAC_C1, &[0x5E, 0, 1, 4]),
+        Ok(SlaveResponse::NoData {
+            function_code: fc_response::SAP_NOT_ACTIVE
+        })
+    );
+}
@@ -0,0 +1,171 @@
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use profibus::sim::{DpMaster, MasterConfig, MasterError, SimBus, SimHwInterface};
+use profibus::{
+    dpv1_function, fc_response, DataHandlingInterface, PbDpSlave, ProfibusConfig, RecordError,
+    RecordHandlingInterface,
+};
+
+const SLAVE_ADDR: u8 = 0x0B;
+const MODULE_CONFIG: [u8; 2] = [0x10, 0x20];
+
+#[derive(Default)]
+struct Records {
+    records: HashMap<(u8, u8), Vec<u8>>,
+}
+
+impl RecordHandlingInterface for Records {
+    fn read_record(&mut self, slot: u8, index: u8, data: &mut [u8]) -> Result<usize, RecordError> {
+        let record = self
+            .records
+            .get(&(slot, index))
+            .ok_or(RecordError::InvalidIndex)?;
+        let len = record.len().min(data.len());
+        data[..len].copy_from_slice(&record[..len]);
+        Ok(len)
+    }
+
+    fn write_record(&mut self, slot: u8, index: u8, data: &[u8]) -> Result<(), RecordError> {
+        self.records.insert((slot, index), data.to_vec());
+        Ok(())
+    }
+
+    fn data_transport(
+        &mut self,
+        _slot: u8,
+        _index: u8,
+        request: &[u8],
+        response: &mut [u8],
+    ) -> Result<usize, RecordError> {
+        for (response, request) in response.iter_mut().zip(request.iter().rev()) {
+            *response = *request;
+        }
+        Ok(request.len())
+    }
+}
+
+#[derive(Default)]
+struct DataHandling {
+    records: Records,
+    now: Rc<Cell<u32>>,
+}
+
+impl DataHandlingInterface for DataHandling {
+    fn millis(&mut self) -> u32 {
+        self.now.get()
+    }
+
+    fn record_handling(&mut self) -> Option<&mut dyn RecordHandlingInterface> {
+        Some(&mut self.records)
+    }
+}
+
+type Slave = PbDpSlave<SimHwInterface, DataHandling, 300, 1, 1, 0, 0, 2>;
+
+fn master(bus: &SimBus, master_addr: u8) -> DpMaster {
+    DpMaster::new(
+        bus.clone(),
+        MasterConfig::default()
+            .master_addr(master_addr)
+            .ident_high(0x00)
+            .ident_low(0x2B)
+            .slave_addr(SLAVE_ADDR)
+            .module_config(&MODULE_CONFIG)
+            .user_para(&[0x80, 0x00, 0x00]),
+    )
+}
+
+fn setup() -> (Slave, SimBus, Rc<Cell<u32>>) {
+    let bus = SimBus::new(500_000);
+    let mut data_handling = DataHandling::default();
+    data_handling
+        .records
+        .records
+        .insert((1, 3), vec![0x0A, 0x0B]);
+    let now = data_handling.now.clone();
+    let slave = PbDpSlave::new(
+        bus.interface(),
+        data_handling,
+        ProfibusConfig::default()
+            .ident_high(0x00)
+            .ident_low(0x2B)
+            .t_s(SLAVE_ADDR)
+            .dpv1(true),
+        MODULE_CONFIG,
+    );
+    (slave, bus, now)
+}
+
+#[test]
+fn class_2_master_reads_while_class_1_exchanges_data() {
+    let (mut slave, bus, _) = setup();
+    let mut plc = master(&bus, 2);
+    let mut laptop = master(&bus, 3);
+    plc.startup(&mut slave).unwrap();
+
+    let response = laptop.initiate(&mut slave, 100).unwrap();
+    assert_eq!(response[0], dpv1_function::INITIATE);
+    assert_eq!(usize::from(response[1]), 244);
+
+    for cycle in 0..3u8 {
+        plc.data_exchange(&mut slave, &[cycle]).unwrap();
+        assert_eq!(slave.access_output(), &[cycle]);
+        assert_eq!(
+            laptop.read_record(&mut slave, 1, 3, 240).unwrap(),
+            [0x0A, 0x0B]
+        );
+    }
+    laptop.write_record(&mut slave, 1, 4, &[7; 100]).unwrap();
+    assert_eq!(laptop.read_record(&mut slave, 1, 4, 240).unwrap(), [7; 100]);
+    assert_eq!(
+        laptop.data_transport(&mut slave, 1, 5, &[1, 2, 3]).unwrap(),
+        [3, 2, 1]
+    );
+    plc.data_exchange(&mut slave, &[0x55]).unwrap();
+    assert_eq!(slave.access_output(), &[0x55]);
+    laptop.abort(&mut slave).unwrap();
+}
+
+#[test]
+fn one_connection_at_a_time() {
+    let (mut slave, bus, _) = setup();
+    let mut laptop = master(&bus, 3);
+    let mut other = master(&bus, 4);
+    laptop.initiate(&mut slave, 100).unwrap();
+    assert_eq!(
+        other.initiate(&mut slave, 100),
+        Err(MasterError::Rejected {
+            function_code: fc_response::NO_RESOURCE
+        })
+    );
+
+    laptop.abort(&mut slave).unwrap();
+    other.initiate(&mut slave, 100).unwrap();
+    assert_eq!(
+        other.read_record(&mut slave, 1, 3, 240).unwrap(),
+        [0x0A, 0x0B]
+    );
+}
+
+#[test]
+fn connection_is_closed_after_send_timeout() {
+    let (mut slave, bus, now) = setup();
+    let mut laptop = master(&bus, 3);
+    // 1 s
+    laptop.initiate(&mut slave, 100).unwrap();
+    now.set(900);
+    laptop.read_record(&mut slave, 1, 3, 240).unwrap();
+    // every request restarts the supervision
+    now.set(1800);
+    laptop.read_record(&mut slave, 1, 3, 240).unwrap();
+
+    now.set(2801);
+    assert_eq!(
+        laptop.read_record(&mut slave, 1, 3, 240),
+        Err(MasterError::Rejected {
+            function_code: fc_response::SAP_NOT_ACTIVE
+        })
+    );
+}
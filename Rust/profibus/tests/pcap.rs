@@ -0,0 +1,103 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use profibus::pcap::{PcapWriter, LINKTYPE_PROFIBUS_DL};
+use profibus::sim::{SimBus, SimHwInterface};
+use profibus::{cmd_type, fc_request, DataHandlingInterface, PbDpSlave, ProfibusConfig};
+
+const BAUDRATE: u32 = 500_000;
+const SLAVE_ADDR: u8 = 0x0B;
+const MASTER_ADDR: u8 = 0x02;
+
+/// Records the traffic of the slave with the time of the simulated bus.
+struct Capture {
+    bus: SimBus,
+    pcap: Rc<RefCell<PcapWriter<Vec<u8>>>>,
+}
+
+impl DataHandlingInterface for Capture {
+    fn telegram_received(&mut self, data: &[u8]) {
+        let timestamp_us = self.bus.now_ns() / 1000;
+        self.pcap
+            .borrow_mut()
+            .write_telegram(timestamp_us, data)
+            .unwrap();
+    }
+
+    fn telegram_sent(&mut self, data: &[u8]) {
+        self.telegram_received(data);
+    }
+}
+
+/// Splits a pcap file into (timestamp in us, packet).
+fn packets(file: &[u8]) -> Vec<(u64, Vec<u8>)> {
+    let mut packets = Vec::new();
+    let mut pos = 24;
+    while pos < file.len() {
+        let field = |offset: usize| {
+            u32::from_le_bytes(file[pos + offset..pos + offset + 4].try_into().unwrap())
+        };
+        let timestamp_us = u64::from(field(0)) * 1_000_000 + u64::from(field(4));
+        let len = field(8) as usize;
+        packets.push((timestamp_us, file[pos + 16..pos + 16 + len].to_vec()));
+        pos += 16 + len;
+    }
+    packets
+}
+
+#[test]
+fn writes_header_and_telegrams() {
+    let mut pcap = PcapWriter::new(Vec::new(), LINKTYPE_PROFIBUS_DL).unwrap();
+    pcap.write_telegram(1_500_000, &[cmd_type::SC]).unwrap();
+    let file = pcap.into_inner();
+
+    assert_eq!(
+        file[..24],
+        [
+            0xD4, 0xC3, 0xB2, 0xA1, 2, 0, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xFF, 0xFF, 0, 0, 0x01,
+            0x01, 0, 0
+        ]
+    );
+    assert_eq!(
+        file[24..],
+        [1, 0, 0, 0, 0x20, 0xA1, 0x07, 0, 1, 0, 0, 0, 1, 0, 0, 0, 0xE5]
+    );
+}
+
+#[test]
+fn captures_slave_traffic() {
+    let bus = SimBus::new(BAUDRATE);
+    let pcap = Rc::new(RefCell::new(
+        PcapWriter::new(Vec::new(), LINKTYPE_PROFIBUS_DL).unwrap(),
+    ));
+    let mut slave = PbDpSlave::<SimHwInterface, Capture, 50, 2, 5, 0, 0, 5>::new(
+        bus.interface(),
+        Capture {
+            bus: bus.clone(),
+            pcap: pcap.clone(),
+        },
+        ProfibusConfig::default().t_s(SLAVE_ADDR),
+        [0x22, 0x20, 0x20, 0x10, 0x10],
+    );
+
+    let function_code = fc_request::REQUEST + fc_request::FDL_STATUS;
+    let fcs = SLAVE_ADDR + MASTER_ADDR + function_code;
+    let request = [
+        cmd_type::SD1,
+        SLAVE_ADDR,
+        MASTER_ADDR,
+        function_code,
+        fcs,
+        cmd_type::ED,
+    ];
+    bus.run_for_ns(&mut slave, 100_000);
+    let response = bus.request(&mut slave, &request, 10_000_000);
+    drop(slave);
+
+    let pcap = Rc::try_unwrap(pcap).ok().unwrap().into_inner();
+    let packets = packets(&pcap.into_inner());
+    assert_eq!(packets.len(), 2);
+    assert_eq!(packets[0].1, request);
+    assert_eq!(packets[1].1, response);
+    assert!(packets[0].0 < packets[1].0);
+}
@@ -0,0 +1,193 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use profibus::persistent_storage::{FileStorage, FlashInterface, FlashStorage};
+use profibus::sim::{DpMaster, MasterConfig, SimBus, SimHwInterface};
+use profibus::{
+    DataHandlingInterface, PbDpSlave, PersistentStorage, ProfibusConfig, StorageError, StorageKey,
+};
+
+const PAGE_SIZE: usize = 128;
+const SLAVE_ADDR: u8 = 0x0B;
+const MODULE_CONFIG: [u8; 2] = [0x10, 0x20];
+
+#[derive(Default)]
+struct FlashState {
+    pages: Vec<Vec<u8>>,
+    erase_counts: Vec<usize>,
+    writes: usize,
+    // bytes written before the next write fails, like a reset
+    fail_after: Option<usize>,
+}
+
+/// Flash of a few pages in RAM, shared to look at it from the test.
+#[derive(Clone)]
+struct RamFlash(Rc<RefCell<FlashState>>);
+
+impl RamFlash {
+    fn new(page_count: usize) -> Self {
+        RamFlash(Rc::new(RefCell::new(FlashState {
+            pages: vec![vec![0xFF; PAGE_SIZE]; page_count],
+            erase_counts: vec![0; page_count],
+            ..FlashState::default()
+        })))
+    }
+}
+
+impl FlashInterface for RamFlash {
+    fn page_size(&self) -> usize {
+        PAGE_SIZE
+    }
+
+    fn page_count(&self) -> usize {
+        self.0.borrow().pages.len()
+    }
+
+    fn erase_page(&mut self, page: usize) -> Result<(), StorageError> {
+        let mut state = self.0.borrow_mut();
+        state.pages[page].fill(0xFF);
+        state.erase_counts[page] += 1;
+        Ok(())
+    }
+
+    fn write(&mut self, page: usize, offset: usize, data: &[u8]) -> Result<(), StorageError> {
+        assert!(offset.is_multiple_of(2) && data.len().is_multiple_of(2));
+        let mut state = self.0.borrow_mut();
+        state.writes += 1;
+        let len = state
+            .fail_after
+            .take()
+            .unwrap_or(data.len())
+            .min(data.len());
+        for (i, &byte) in data[..len].iter().enumerate() {
+            assert_eq!(state.pages[page][offset + i], 0xFF, "written twice");
+            state.pages[page][offset + i] = byte;
+        }
+        if len < data.len() {
+            return Err(StorageError::WriteFailed);
+        }
+        Ok(())
+    }
+
+    fn read(&mut self, page: usize, offset: usize, data: &mut [u8]) {
+        data.copy_from_slice(&self.0.borrow().pages[page][offset..offset + data.len()]);
+    }
+}
+
+fn load(storage: &mut impl PersistentStorage, key: StorageKey) -> Option<Vec<u8>> {
+    let mut data = [0; 255];
+    let len = storage.load(key, &mut data)?;
+    Some(data[..len].to_vec())
+}
+
+#[test]
+fn flash_pages_are_used_in_turn() {
+    let flash = RamFlash::new(3);
+    let mut storage = FlashStorage::new(flash.clone());
+    assert_eq!(storage.load_address(), None);
+    storage.store(StorageKey::Im(0), &[0x12; 20]).unwrap();
+    for address in 0..100u8 {
+        storage.store_address(address, false).unwrap();
+        storage.store(StorageKey::Prm, &[address; 9]).unwrap();
+    }
+    assert_eq!(storage.load_address(), Some((99, false)));
+
+    // all values survive the reset and the copies to other pages
+    let mut storage = FlashStorage::new(storage.release());
+    assert_eq!(storage.load_address(), Some((99, false)));
+    assert_eq!(load(&mut storage, StorageKey::Prm), Some(vec![99; 9]));
+    assert_eq!(load(&mut storage, StorageKey::Im(0)), Some(vec![0x12; 20]));
+    assert_eq!(load(&mut storage, StorageKey::Cfg), None);
+    let erase_counts = flash.0.borrow().erase_counts.clone();
+    let (min, max) = (
+        erase_counts.iter().min().unwrap(),
+        erase_counts.iter().max().unwrap(),
+    );
+    assert!(*min > 0 && max - min <= 1, "{erase_counts:?}");
+
+    assert_eq!(
+        storage.store(StorageKey::Prm, &[0; PAGE_SIZE]),
+        Err(StorageError::TooLong)
+    );
+    assert_eq!(load(&mut storage, StorageKey::Prm), Some(vec![99; 9]));
+}
+
+#[test]
+fn torn_record_keeps_the_old_value() {
+    let flash = RamFlash::new(2);
+    let mut storage = FlashStorage::new(flash.clone());
+    storage.store(StorageKey::Cfg, &[0x10, 0x20]).unwrap();
+    flash.0.borrow_mut().fail_after = Some(4);
+    assert_eq!(
+        storage.store(StorageKey::Cfg, &[0x11, 0x21, 0x31]),
+        Err(StorageError::WriteFailed)
+    );
+
+    let mut storage = FlashStorage::new(storage.release());
+    assert_eq!(load(&mut storage, StorageKey::Cfg), Some(vec![0x10, 0x20]));
+    // writing goes on behind the torn record
+    storage.store(StorageKey::Cfg, &[0x12]).unwrap();
+    let mut storage = FlashStorage::new(storage.release());
+    assert_eq!(load(&mut storage, StorageKey::Cfg), Some(vec![0x12]));
+}
+
+#[test]
+fn file_storage_round_trip() {
+    let dir = std::env::temp_dir().join(format!("profibus-storage-{}", std::process::id()));
+    let mut storage = FileStorage::new(&dir).unwrap();
+    assert_eq!(storage.load_address(), None);
+    storage.store_address(42, true).unwrap();
+    storage.store(StorageKey::Im(1), b"I&M1").unwrap();
+
+    let mut storage = FileStorage::new(&dir).unwrap();
+    assert_eq!(storage.load_address(), Some((42, true)));
+    assert_eq!(
+        load(&mut storage, StorageKey::Im(1)),
+        Some(b"I&M1".to_vec())
+    );
+    assert_eq!(storage.load(StorageKey::Im(1), &mut [0; 3]), None);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+struct DataHandling {
+    storage: FlashStorage<RamFlash>,
+}
+
+impl DataHandlingInterface for DataHandling {
+    fn persistent_storage(&mut self) -> Option<&mut dyn PersistentStorage> {
+        Some(&mut self.storage)
+    }
+}
+
+#[test]
+fn startup_writes_no_flash() {
+    let flash = RamFlash::new(2);
+    let bus = SimBus::new(500_000);
+    let mut slave = PbDpSlave::<SimHwInterface, DataHandling, 64, 1, 1, 0, 0, 2>::new(
+        bus.interface(),
+        DataHandling {
+            storage: FlashStorage::new(flash.clone()),
+        },
+        ProfibusConfig::default()
+            .ident_high(0x00)
+            .ident_low(0x2B)
+            .t_s(SLAVE_ADDR),
+        MODULE_CONFIG,
+    );
+    let mut master = DpMaster::new(
+        bus,
+        MasterConfig::default()
+            .ident_high(0x00)
+            .ident_low(0x2B)
+            .slave_addr(SLAVE_ADDR)
+            .module_config(&MODULE_CONFIG),
+    );
+    master.startup(&mut slave).unwrap();
+    // the master sends Set_Prm and Chk_Cfg again after a reset
+    slave.store_pending().unwrap();
+    assert_eq!(flash.0.borrow().writes, 0);
+
+    let mut storage = FlashStorage::new(flash);
+    assert_eq!(load(&mut storage, StorageKey::Prm), None);
+    assert_eq!(load(&mut storage, StorageKey::Cfg), None);
+}
@@ -0,0 +1,189 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use profibus::sim::{DpMaster, MasterConfig, MasterError, SimBus, SimHwInterface, SlaveResponse};
+use profibus::{
+    fc_response, sap, DataHandlingInterface, PbDpSlave, PersistentStorage, ProfibusConfig,
+    StorageError, StorageKey,
+};
+
+const DEFAULT_ADDR: u8 = 126;
+const MODULE_CONFIG: [u8; 2] = [0x10, 0x20];
+
+#[derive(Clone, Default)]
+struct MemoryStorage {
+    address: Rc<Cell<Option<(u8, bool)>>>,
+    write_failed: bool,
+}
+
+impl PersistentStorage for MemoryStorage {
+    fn load(&mut self, key: StorageKey, data: &mut [u8]) -> Option<usize> {
+        let (address, no_add_chg) = self.address.get().filter(|_| key == StorageKey::Address)?;
+        data[..2].copy_from_slice(&[address, u8::from(no_add_chg)]);
+        Some(2)
+    }
+
+    fn store(&mut self, key: StorageKey, data: &[u8]) -> Result<(), StorageError> {
+        if self.write_failed {
+            return Err(StorageError::WriteFailed);
+        }
+        if key == StorageKey::Address {
+            self.address.set(Some((data[0], data[1] != 0)));
+        }
+        Ok(())
+    }
+}
+
+struct DataHandling {
+    storage: MemoryStorage,
+}
+
+impl DataHandlingInterface for DataHandling {
+    fn persistent_storage(&mut self) -> Option<&mut dyn PersistentStorage> {
+        Some(&mut self.storage)
+    }
+}
+
+type Slave = PbDpSlave<SimHwInterface, DataHandling, 64, 1, 1, 0, 0, 2>;
+
+fn setup_with_ident(storage: &MemoryStorage, ident_low: u8) -> (Slave, DpMaster) {
+    let bus = SimBus::new(500_000);
+    let slave = PbDpSlave::new(
+        bus.interface(),
+        DataHandling {
+            storage: storage.clone(),
+        },
+        ProfibusConfig::default().ident_high(0x00).ident_low(0x2B),
+        MODULE_CONFIG,
+    );
+    let master = DpMaster::new(
+        bus,
+        MasterConfig::default()
+            .ident_high(0x00)
+            .ident_low(ident_low)
+            .slave_addr(slave.station_address())
+            .module_config(&MODULE_CONFIG),
+    );
+    (slave, master)
+}
+
+fn setup(storage: &MemoryStorage) -> (Slave, DpMaster) {
+    setup_with_ident(storage, 0x2B)
+}
+
+#[test]
+fn address_is_changed_and_kept_over_reset() {
+    let storage = MemoryStorage::default();
+    let (mut slave, mut master) = setup(&storage);
+    assert_eq!(slave.station_address(), DEFAULT_ADDR);
+
+    master.set_slave_address(&mut slave, 5, false).unwrap();
+    // stored outside of the answer
+    assert_eq!(storage.address.get(), None);
+    slave.store_pending().unwrap();
+    assert_eq!(storage.address.get(), Some((5, false)));
+    // the new address is taken once the short acknowledge is sent
+    master.startup(&mut slave).unwrap();
+    assert_eq!(slave.station_address(), 5);
+
+    // the stored address wins over the configured one
+    let (mut slave, mut master) = setup(&storage);
+    assert_eq!(slave.station_address(), 5);
+    master.set_slave_address(&mut slave, 6, false).unwrap();
+    slave.store_pending().unwrap();
+    assert_eq!(storage.address.get(), Some((6, false)));
+}
+
+#[test]
+fn no_add_chg_locks_the_address() {
+    let storage = MemoryStorage::default();
+    let (mut slave, mut master) = setup(&storage);
+    master.set_slave_address(&mut slave, 7, true).unwrap();
+    slave.store_pending().unwrap();
+    assert_eq!(
+        master.set_slave_address(&mut slave, 8, false),
+        Err(MasterError::Rejected {
+            function_code: fc_response::SAP_NOT_ACTIVE
+        })
+    );
+
+    let (mut slave, mut master) = setup(&storage);
+    assert_eq!(slave.station_address(), 7);
+    assert_eq!(
+        master.set_slave_address(&mut slave, 8, false),
+        Err(MasterError::Rejected {
+            function_code: fc_response::SAP_NOT_ACTIVE
+        })
+    );
+    master.startup(&mut slave).unwrap();
+}
+
+#[test]
+fn invalid_requests_keep_the_address() {
+    let storage = MemoryStorage::default();
+    let (mut slave, mut master) = setup(&storage);
+    let user_error = Err(MasterError::Rejected {
+        function_code: fc_response::USER_ERROR,
+    });
+    assert_eq!(
+        master.set_slave_address(&mut slave, DEFAULT_ADDR, false),
+        user_error
+    );
+    let (mut slave, mut master) = setup_with_ident(&storage, 0x2C);
+    assert_eq!(master.set_slave_address(&mut slave, 5, false), user_error);
+    assert_eq!(slave.station_address(), DEFAULT_ADDR);
+
+    // only accepted before the parameterization
+    let (mut slave, mut master) = setup(&storage);
+    master.startup(&mut slave).unwrap();
+    assert_eq!(
+        master.set_slave_address(&mut slave, 5, false),
+        Err(MasterError::Rejected {
+            function_code: fc_response::SAP_NOT_ACTIVE
+        })
+    );
+    assert_eq!(storage.address.get(), None);
+}
+
+#[test]
+fn rem_slave_data_is_accepted() {
+    let storage = MemoryStorage::default();
+    let (mut slave, mut master) = setup(&storage);
+    // Rem_Slave_Data follows the four mandatory bytes
+    assert_eq!(
+        master.sap_request(
+            &mut slave,
+            sap::SET_SLAVE_ADR,
+            &[5, 0x00, 0x2B, 0, 0xAA, 0xBB]
+        ),
+        Ok(SlaveResponse::ShortAck)
+    );
+    slave.store_pending().unwrap();
+    assert_eq!(storage.address.get(), Some((5, false)));
+
+    let (mut slave, mut master) = setup(&MemoryStorage::default());
+    assert_eq!(
+        master.sap_request(&mut slave, sap::SET_SLAVE_ADR, &[5, 0x00, 0x2B]),
+        Ok(SlaveResponse::NoData {
+            function_code: fc_response::USER_ERROR
+        })
+    );
+}
+
+#[test]
+fn failed_write_keeps_the_address_until_reset() {
+    let failing = MemoryStorage {
+        write_failed: true,
+        ..MemoryStorage::default()
+    };
+    let (mut slave, mut master) = setup(&failing);
+    master.set_slave_address(&mut slave, 5, false).unwrap();
+    assert_eq!(slave.store_pending(), Err(StorageError::WriteFailed));
+    // tried once only
+    assert_eq!(slave.store_pending(), Ok(()));
+    master.startup(&mut slave).unwrap();
+    assert_eq!(slave.station_address(), 5);
+
+    let (slave, _) = setup(&failing);
+    assert_eq!(slave.station_address(), DEFAULT_ADDR);
+}
@@ -0,0 +1,230 @@
+use profibus::sim::SimBus;
+use profibus::{
+    cmd_type, fc_request, fc_response, sap, sap_set_parameter_request, DataHandlingInterface,
+    PbDpSlave, ProfibusConfig, ReceiveHandling, Telegram, UartAccess,
+};
+
+const BAUDRATE: u32 = 500_000;
+const SLAVE_ADDR: u8 = 0x0B;
+const MASTER_ADDR: u8 = 0x02;
+
+struct DataHandling {}
+
+impl DataHandlingInterface for DataHandling {}
+
+type Slave = PbDpSlave<profibus::sim::SimHwInterface, DataHandling, 50, 2, 5, 0, 0, 5>;
+
+fn slave(bus: &SimBus, config: ProfibusConfig) -> Slave {
+    PbDpSlave::new(
+        bus.interface(),
+        DataHandling {},
+        config.t_s(SLAVE_ADDR),
+        [0x22, 0x20, 0x20, 0x10, 0x10],
+    )
+}
+
+fn sd1(destination_addr: u8, source_addr: u8, function_code: u8) -> [u8; 6] {
+    let fcs = destination_addr
+        .wrapping_add(source_addr)
+        .wrapping_add(function_code);
+    [
+        cmd_type::SD1,
+        destination_addr,
+        source_addr,
+        function_code,
+        fcs,
+        cmd_type::ED,
+    ]
+}
+
+fn sd2(function_code: u8, dsap: u8, pdu: &[u8]) -> Vec<u8> {
+    let mut buffer = [0; 255];
+    let len = Telegram::Sd2 {
+        destination_addr: SLAVE_ADDR,
+        source_addr: MASTER_ADDR,
+        function_code,
+        dsap: Some(dsap),
+        ssap: Some(62),
+        pdu,
+    }
+    .encode(&mut buffer)
+    .unwrap();
+    buffer[..len].to_vec()
+}
+
+fn set_prm(function_code: u8, ident_low: u8) -> Vec<u8> {
+    let pdu = [
+        sap_set_parameter_request::LOCK_SLAVE,
+        0,
+        0,
+        0,
+        0x00,
+        ident_low,
+        0,
+    ];
+    sd2(function_code, sap::SET_PRM, &pdu)
+}
+
+fn fdl_status_request() -> [u8; 6] {
+    sd1(
+        SLAVE_ADDR,
+        MASTER_ADDR,
+        fc_request::REQUEST + fc_request::FDL_STATUS,
+    )
+}
+
+fn fdl_status_response() -> [u8; 6] {
+    sd1(MASTER_ADDR, SLAVE_ADDR, fc_response::FDL_STATUS_OK)
+}
+
+#[test]
+fn answers_fdl_status_after_syn_time() {
+    let bus = SimBus::new(BAUDRATE);
+    let mut slave = slave(&bus, ProfibusConfig::default());
+
+    // the slave only listens after 33 idle bits
+    bus.run_for_ns(&mut slave, 100_000);
+    let response = bus.request(&mut slave, &fdl_status_request(), 10_000_000);
+    assert_eq!(response, fdl_status_response());
+}
+
+#[test]
+fn ignores_telegram_without_syn_pause() {
+    let bus = SimBus::new(BAUDRATE);
+    let mut slave = slave(&bus, ProfibusConfig::default());
+
+    let response = bus.request(&mut slave, &fdl_status_request(), 10_000_000);
+    assert!(response.is_empty());
+}
+
+#[test]
+fn waits_min_tsdr_before_sending() {
+    let bus = SimBus::new(BAUDRATE);
+    let mut slave = slave(&bus, ProfibusConfig::default().t_sdr_min(200));
+
+    bus.run_for_ns(&mut slave, 100_000);
+    bus.send(&fdl_status_request());
+    let request_end = bus.now_ns() + 6 * bus.byte_time_ns();
+    bus.run_for_ns(&mut slave, 10_000_000);
+
+    let response = bus.take_transmitted_with_time();
+    assert_eq!(response.len(), 6);
+    let first_byte_start = response[0].0 - bus.byte_time_ns();
+    // 200 bit at 500 kBit/s
+    assert!(first_byte_start - request_end >= 200_000);
+}
+
+#[test]
+fn thread_receive_handling_and_dma() {
+    let bus = SimBus::new(BAUDRATE);
+    let mut slave = slave(
+        &bus,
+        ProfibusConfig::default()
+            .receive_handling(ReceiveHandling::Thread)
+            .tx_handling(UartAccess::Dma),
+    );
+
+    bus.run_for_ns(&mut slave, 100_000);
+    for _ in 0..3 {
+        let response = bus.request(&mut slave, &fdl_status_request(), 10_000_000);
+        assert_eq!(response, fdl_status_response());
+        bus.run_for_ns(&mut slave, 100_000);
+    }
+}
+
+#[test]
+fn rejects_malformed_requests_without_panic() {
+    let bus = SimBus::new(BAUDRATE);
+    let mut slave = slave(&bus, ProfibusConfig::default());
+    let mut buffer = [0; 255];
+
+    // Set_Prm with only three parameter bytes
+    let len = Telegram::Sd2 {
+        destination_addr: SLAVE_ADDR,
+        source_addr: MASTER_ADDR,
+        function_code: fc_request::REQUEST + fc_request::SRD_HIGH,
+        dsap: Some(sap::SET_PRM),
+        ssap: Some(62),
+        pdu: &[0x88, 0x01, 0x02],
+    }
+    .encode(&mut buffer)
+    .unwrap();
+    bus.run_for_ns(&mut slave, 100_000);
+    let response = bus.request(&mut slave, &buffer[..len], 10_000_000);
+    assert_eq!(
+        response,
+        sd1(MASTER_ADDR, SLAVE_ADDR, fc_response::USER_ERROR)
+    );
+
+    // more bytes than the receive buffer holds
+    bus.run_for_ns(&mut slave, 100_000);
+    let response = bus.request(&mut slave, &[cmd_type::SD2; 80], 10_000_000);
+    assert!(response.is_empty());
+
+    bus.run_for_ns(&mut slave, 100_000);
+    let response = bus.request(&mut slave, &fdl_status_request(), 10_000_000);
+    assert_eq!(response, fdl_status_response());
+}
+
+#[test]
+fn repeats_the_response_on_the_same_fcb() {
+    let bus = SimBus::new(BAUDRATE);
+    let mut slave = slave(&bus, ProfibusConfig::default().ident_low(0x2B));
+    let request = fc_request::REQUEST + fc_request::SRD_HIGH;
+
+    bus.run_for_ns(&mut slave, 100_000);
+    let get_diag = sd2(request + fc_request::FCB, sap::SLAVE_DIAGNOSTIC, &[]);
+    let diagnostic = bus.request(&mut slave, &get_diag, 10_000_000);
+    assert_eq!(diagnostic[0], cmd_type::SD2);
+
+    // same FCB: taken as a repeat, the last response is sent again
+    bus.run_for_ns(&mut slave, 100_000);
+    let prm = set_prm(request + fc_request::FCV + fc_request::FCB, 0x2B);
+    assert_eq!(bus.request(&mut slave, &prm, 10_000_000), diagnostic);
+
+    // the toggled FCB is a new request
+    bus.run_for_ns(&mut slave, 100_000);
+    let prm = set_prm(request + fc_request::FCV, 0x2B);
+    assert_eq!(bus.request(&mut slave, &prm, 10_000_000), [cmd_type::SC]);
+}
+
+#[test]
+fn unanswered_telegram_keeps_the_fcb() {
+    let bus = SimBus::new(BAUDRATE);
+    let mut slave = slave(&bus, ProfibusConfig::default().ident_low(0x2B));
+    let request = fc_request::REQUEST + fc_request::SRD_HIGH;
+
+    bus.run_for_ns(&mut slave, 100_000);
+    let get_diag = sd2(request + fc_request::FCB, sap::SLAVE_DIAGNOSTIC, &[]);
+    assert!(!bus.request(&mut slave, &get_diag, 10_000_000).is_empty());
+
+    // Set_Prm with a wrong ident is not answered
+    bus.run_for_ns(&mut slave, 100_000);
+    let wrong_prm = set_prm(request + fc_request::FCV, 0x2C);
+    assert!(bus.request(&mut slave, &wrong_prm, 10_000_000).is_empty());
+
+    // the next request with the same FCB is processed, not taken as a repeat
+    bus.run_for_ns(&mut slave, 100_000);
+    let prm = set_prm(request + fc_request::FCV, 0x2B);
+    assert_eq!(bus.request(&mut slave, &prm, 10_000_000), [cmd_type::SC]);
+}
+
+#[test]
+fn fcb_is_ignored_without_fcv() {
+    let bus = SimBus::new(BAUDRATE);
+    let mut slave = slave(&bus, ProfibusConfig::default().ident_low(0x2B));
+    let request = fc_request::REQUEST + fc_request::SRD_HIGH;
+
+    bus.run_for_ns(&mut slave, 100_000);
+    let get_diag = sd2(request + fc_request::FCB, sap::SLAVE_DIAGNOSTIC, &[]);
+    assert!(!bus.request(&mut slave, &get_diag, 10_000_000).is_empty());
+
+    // FDL status without FCV does not toggle the FCB
+    bus.run_for_ns(&mut slave, 100_000);
+    let response = bus.request(&mut slave, &fdl_status_request(), 10_000_000);
+    assert_eq!(response, fdl_status_response());
+
+    bus.run_for_ns(&mut slave, 100_000);
+    let prm = set_prm(request + fc_request::FCV, 0x2B);
+    assert_eq!(bus.request(&mut slave, &prm, 10_000_000), [cmd_type::SC]);
+}
@@ -0,0 +1,157 @@
+use profibus::{FrameError, Telegram};
+
+const SLAVE_ADDR: u8 = 0x0B;
+const MASTER_ADDR: u8 = 0x02;
+
+fn encode(telegram: &Telegram) -> Vec<u8> {
+    let mut buffer = [0; 255];
+    let len = telegram.encode(&mut buffer).unwrap();
+    buffer[..len].to_vec()
+}
+
+#[test]
+fn encode_and_decode_round_trip() {
+    let telegrams = [
+        Telegram::Sd1 {
+            destination_addr: SLAVE_ADDR,
+            source_addr: MASTER_ADDR,
+            function_code: 0x49,
+        },
+        Telegram::Sd2 {
+            destination_addr: SLAVE_ADDR,
+            source_addr: MASTER_ADDR,
+            function_code: 0x5D,
+            dsap: Some(61),
+            ssap: Some(62),
+            pdu: &[0x88, 0x01, 0x02, 0x0B, 0x00, 0x2B, 0x00],
+        },
+        Telegram::Sd2 {
+            destination_addr: SLAVE_ADDR,
+            source_addr: MASTER_ADDR,
+            function_code: 0x7D,
+            dsap: None,
+            ssap: None,
+            pdu: &[0x01, 0x02],
+        },
+        Telegram::Sd3 {
+            destination_addr: SLAVE_ADDR,
+            source_addr: MASTER_ADDR,
+            function_code: 0x7D,
+            dsap: None,
+            ssap: None,
+            pdu: &[1, 2, 3, 4, 5, 6, 7, 8],
+        },
+        Telegram::Sd4 {
+            destination_addr: SLAVE_ADDR,
+            source_addr: MASTER_ADDR,
+        },
+        Telegram::Sc,
+    ];
+    for telegram in telegrams {
+        assert_eq!(Telegram::decode(&encode(&telegram)), Ok(telegram));
+    }
+}
+
+#[test]
+fn encodes_sap_in_address_extension() {
+    let data = encode(&Telegram::Sd2 {
+        destination_addr: MASTER_ADDR,
+        source_addr: SLAVE_ADDR,
+        function_code: 0x08,
+        dsap: Some(62),
+        ssap: Some(59),
+        pdu: &[0x22],
+    });
+    assert_eq!(
+        data,
+        [0x68, 0x06, 0x06, 0x68, 0x82, 0x8B, 0x08, 62, 59, 0x22, 0xB0, 0x16]
+    );
+}
+
+#[test]
+fn decode_reports_frame_errors() {
+    let valid = encode(&Telegram::Sd2 {
+        destination_addr: SLAVE_ADDR,
+        source_addr: MASTER_ADDR,
+        function_code: 0x5D,
+        dsap: None,
+        ssap: None,
+        pdu: &[0x01, 0x02],
+    });
+
+    assert_eq!(Telegram::decode(&[]), Err(FrameError::Empty));
+    assert_eq!(
+        Telegram::decode(&[0x42]),
+        Err(FrameError::InvalidStartDelimiter(0x42))
+    );
+    assert_eq!(
+        Telegram::decode(&valid[..valid.len() - 1]),
+        Err(FrameError::LengthMismatch {
+            expected: 11,
+            received: 10
+        })
+    );
+
+    let mut data = valid.clone();
+    data[2] = 0x06;
+    assert_eq!(
+        Telegram::decode(&data),
+        Err(FrameError::LengthRepeatMismatch { le: 5, ler: 6 })
+    );
+
+    let mut data = valid.clone();
+    data[7] ^= 0xFF;
+    assert!(matches!(
+        Telegram::decode(&data),
+        Err(FrameError::ChecksumMismatch { .. })
+    ));
+
+    let mut data = valid.clone();
+    data[10] = 0x00;
+    assert_eq!(
+        Telegram::decode(&data),
+        Err(FrameError::MissingEndDelimiter(0x00))
+    );
+
+    assert_eq!(
+        Telegram::decode_for(&valid, 0x0C),
+        Err(FrameError::NotForUs(SLAVE_ADDR))
+    );
+    assert!(Telegram::decode_for(&valid, SLAVE_ADDR).is_ok());
+}
+
+#[test]
+fn broadcast_is_for_everybody() {
+    let data = encode(&Telegram::Sd1 {
+        destination_addr: 127,
+        source_addr: MASTER_ADDR,
+        function_code: 0x46,
+    });
+    assert!(Telegram::decode_for(&data, SLAVE_ADDR).is_ok());
+}
+
+#[test]
+fn encode_checks_buffer_and_length() {
+    let telegram = Telegram::Sd1 {
+        destination_addr: SLAVE_ADDR,
+        source_addr: MASTER_ADDR,
+        function_code: 0x49,
+    };
+    assert_eq!(
+        telegram.encode(&mut [0; 5]),
+        Err(FrameError::BufferTooSmall)
+    );
+
+    let telegram = Telegram::Sd3 {
+        destination_addr: SLAVE_ADDR,
+        source_addr: MASTER_ADDR,
+        function_code: 0x49,
+        dsap: None,
+        ssap: None,
+        pdu: &[0; 7],
+    };
+    assert_eq!(
+        telegram.encode(&mut [0; 14]),
+        Err(FrameError::InvalidLength(10))
+    );
+}
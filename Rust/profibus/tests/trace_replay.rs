@@ -0,0 +1,78 @@
+use profibus::sim::{replay, ReplayConfig, ReplayError, SimBus, SimHwInterface, Trace};
+use profibus::{DataHandlingInterface, PbDpSlave, ProfibusConfig};
+
+const BAUDRATE: u32 = 500_000;
+const SLAVE_ADDR: u8 = 0x0B;
+// generated on the SimBus, not a PLC capture
+const SIM_STARTUP_500K: &str = include_str!("traces/sim_startup_500k.trace");
+
+struct DataHandling {}
+
+impl DataHandlingInterface for DataHandling {}
+
+type Slave = PbDpSlave<SimHwInterface, DataHandling, 50, 2, 5, 0, 0, 5>;
+
+fn setup() -> (SimBus, Slave) {
+    let bus = SimBus::new(BAUDRATE);
+    let mut slave = PbDpSlave::new(
+        bus.interface(),
+        DataHandling {},
+        ProfibusConfig::default()
+            .ident_high(0x00)
+            .ident_low(0x2B)
+            .t_s(SLAVE_ADDR),
+        [0x22, 0x20, 0x20, 0x10, 0x10],
+    );
+    // the slave only listens after 33 idle bits
+    bus.run_for_ns(&mut slave, 100_000);
+    (bus, slave)
+}
+
+#[test]
+fn replays_sim_startup_500k() {
+    let (bus, mut slave) = setup();
+    let trace = Trace::parse(SIM_STARTUP_500K).unwrap();
+    assert_eq!(
+        replay(&bus, &mut slave, &trace, &ReplayConfig::default()),
+        Ok(())
+    );
+}
+
+#[test]
+fn reports_changed_answer() {
+    let (bus, mut slave) = setup();
+    // FDL status answered with "not ready" instead of "OK"
+    let text = SIM_STARTUP_500K.replace("10 02 0B 00 0D 16", "10 02 0B 01 0E 16");
+    let trace = Trace::parse(&text).unwrap();
+    assert_eq!(
+        replay(&bus, &mut slave, &trace, &ReplayConfig::default()),
+        Err(ReplayError::Mismatch {
+            line: 6,
+            expected: vec![0x10, 0x02, 0x0B, 0x01, 0x0E, 0x16],
+            received: vec![0x10, 0x02, 0x0B, 0x00, 0x0D, 0x16],
+        })
+    );
+}
+
+#[test]
+fn reports_answer_outside_tsdr() {
+    let (bus, mut slave) = setup();
+    let trace = Trace::parse(SIM_STARTUP_500K).unwrap();
+    assert!(matches!(
+        replay(
+            &bus,
+            &mut slave,
+            &trace,
+            &ReplayConfig::default().max_tsdr_bits(15)
+        ),
+        Err(ReplayError::Timing { line: 6, .. })
+    ));
+}
+
+#[test]
+fn station_drops_traffic_of_other_slaves() {
+    let trace = Trace::parse(SIM_STARTUP_500K).unwrap();
+    let station = trace.station(SLAVE_ADDR);
+    assert_eq!(station.entries().len(), trace.entries().len() - 1);
+    assert!(station.entries().iter().all(|entry| entry.line != 30));
+}
@@ -0,0 +1,71 @@
+/*********************************************************************
+ * Flash storage
+ *
+ * Copyright (C) 2023 Marcel Maage
+ *
+ * This library is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2.1 of the License, or (at your option) any later version.
+ *
+ * This library is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * LICENSE file for more details.
+ */
+
+use profibus::persistent_storage::FlashInterface;
+use profibus::StorageError;
+use stm32f1xx_hal::flash::{FlashSize, Parts, SectorSize};
+
+const PAGE_SIZE: usize = 1024;
+const PAGE_COUNT: usize = 2;
+// last two pages of the 64K flash, kept free in memory.x
+const STORAGE_OFFSET: usize = 62 * 1024;
+
+pub struct PbDpFlash {
+    flash: Parts,
+}
+
+impl PbDpFlash {
+    pub fn new(flash: Parts) -> Self {
+        PbDpFlash { flash }
+    }
+
+    fn offset(page: usize, offset: usize) -> u32 {
+        (STORAGE_OFFSET + page * PAGE_SIZE + offset) as u32
+    }
+}
+
+impl FlashInterface for PbDpFlash {
+    fn page_size(&self) -> usize {
+        PAGE_SIZE
+    }
+
+    fn page_count(&self) -> usize {
+        PAGE_COUNT
+    }
+
+    fn erase_page(&mut self, page: usize) -> Result<(), StorageError> {
+        self.flash
+            .writer(SectorSize::Sz1K, FlashSize::Sz64K)
+            .erase(Self::offset(page, 0), PAGE_SIZE)
+            .map_err(|_| StorageError::WriteFailed)
+    }
+
+    fn write(&mut self, page: usize, offset: usize, data: &[u8]) -> Result<(), StorageError> {
+        self.flash
+            .writer(SectorSize::Sz1K, FlashSize::Sz64K)
+            .write(Self::offset(page, offset), data)
+            .map_err(|_| StorageError::WriteFailed)
+    }
+
+    fn read(&mut self, page: usize, offset: usize, data: &mut [u8]) {
+        let writer = self.flash.writer(SectorSize::Sz1K, FlashSize::Sz64K);
+        match writer.read(Self::offset(page, offset), data.len()) {
+            Ok(flash_data) => data.copy_from_slice(flash_data),
+            // read like erased flash
+            Err(_) => data.fill(0xFF),
+        }
+    }
+}
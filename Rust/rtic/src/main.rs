@@ -29,14 +29,18 @@ use panic_halt as _;
 
 //use cortex_m::singleton;
 //use cortex_m_semihosting::{hprintln};
+mod flash_storage;
 mod pb_dp_interface;
-mod profibus;
 mod rtc_millis;
 
 #[rtic::app(device = stm32f1xx_hal::pac, dispatchers = [I2C1_EV], peripherals = true,)]
 mod app {
+    use crate::flash_storage::PbDpFlash;
     use crate::pb_dp_interface::{PbDpDataHandling, PbDpHwInterface};
-    use crate::profibus::{ProfibusConfig as PbDpConfig, PbDpSlave, ReceiveHandling};
+    use profibus::persistent_storage::{FlashStorage, MAX_VALUE_LEN};
+    use profibus::{
+        DpSlaveState, PbDpSlave, PersistentStorage, ProfibusConfig as PbDpConfig, ReceiveHandling,
+    };
     use crate::rtc_millis::Rtc;
     use heapless::{
         spsc::{Consumer, Producer, Queue},
@@ -74,6 +78,7 @@ mod app {
         serial1_tx: serialTx<USART1>,
         debug_consumer: Consumer<'static, u8, DEBUG_QUEUE_SIZE>,
         led: gpioc::PC13<Output<PushPull>>,
+        storage: FlashStorage<PbDpFlash>,
     }
     #[shared]
     struct Shared {
@@ -166,6 +171,7 @@ mod app {
         let mut timer = cx.device.TIM2.counter_us(&clocks);
         timer.listen(Event::Update);
 
+        // 0x0B until the master sets another address, which is kept in flash
         let profibus_config = PbDpConfig::default()
             .ident_high(0x00)
             .ident_low(0x2B)
@@ -186,13 +192,17 @@ mod app {
 
         let data_interface = PbDpDataHandling::new(rtc, debug_pin);
 
-        let profibus_slave = PbDpSlave::new(
+        let mut profibus_slave = PbDpSlave::new(
             serial_interface,
             data_interface,
             profibus_config,
             [0x22, 0x20, 0x20, 0x10, 0x10],
         );
 
+        // the storage stays with idle, which writes it outside of the profibus lock
+        let mut storage = FlashStorage::new(PbDpFlash::new(flash));
+        profibus_slave.restore_address(&mut storage);
+
         blinky::spawn().unwrap();
 
         (
@@ -205,16 +215,20 @@ mod app {
                 serial1_tx,
                 debug_consumer,
                 led,
+                storage,
             },
             init::Monotonics(mono),
         )
     }
 
-    #[idle(local = [serial1_rx, serial1_tx, debug_consumer])]
+    #[idle(local = [serial1_rx, serial1_tx, debug_consumer, storage], shared = [profibus_slave])]
     fn idle(cx: idle::Context) -> ! {
         let _serial1_rx = cx.local.serial1_rx;
         let serial1_tx = cx.local.serial1_tx;
         let debug_consumer = cx.local.debug_consumer;
+        let storage = cx.local.storage;
+        let mut profibus_slave = cx.shared.profibus_slave;
+        let mut value = [0; MAX_VALUE_LEN];
         //let buf = singleton!(: [u8; 8] = [0; 8]).unwrap();
         //let (_buf, _rx) = rx_channel.read(buf).wait();
 
@@ -223,6 +237,18 @@ mod app {
             if let Some(data) = debug_consumer.dequeue() {
                 block!(serial1_tx.write(data)).ok();
             }
+            // values set by the master, the slave has answered already. A page
+            // erase stalls the instruction fetch for tens of ms and USART3
+            // would overrun, so nothing is written during data exchange.
+            if let Some((key, len)) = profibus_slave.lock(|profibus_slave| {
+                if profibus_slave.slave_state() == DpSlaveState::Dxchg {
+                    None
+                } else {
+                    profibus_slave.take_pending_value(&mut value)
+                }
+            }) {
+                storage.store_changed(key, &value[..len]).ok();
+            }
             // match debug_consumer.dequeue() {
             //     Some(data) => {block!(serial1_tx.write(data)).ok();},
             //     None => { /* sleep */ },
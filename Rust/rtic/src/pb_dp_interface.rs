@@ -17,7 +17,7 @@
 use crate::app::{
     handle_data_receive, save_debug_message, timer2_max, usart3_rx, DEBUG_STRING_SIZE,
 };
-use crate::profibus::{DataHandlingInterface as PbDataHandling, HwInterface as PbInterface};
+use profibus::{DataHandlingInterface as PbDataHandling, HwInterface as PbInterface};
 use crate::rtc_millis::Rtc;
 use heapless::String;
 use rtic::mutex_prelude::*;
@@ -1,16 +0,0 @@
-pub use codec_hw_interface::HwInterface;
-pub use data_handling_interface::DataHandlingInterface;
-pub use slave::{PbDpSlave, ProfibusConfig};
-pub use codec::{UartAccess, ReceiveHandling};
-pub use types::{
-    cmd_type, dpv1_status_byte1, dpv1_status_byte2, dpv1_status_byte3, fc_request, fc_response,
-    sap_check_config_request, sap_diagnose_byte1, sap_diagnose_byte2, sap_diagnose_byte3,
-    sap_diagnose_ext, sap_global_control, sap_set_parameter_request, DpSlaveState, StreamState,
-    sap,
-};
-
-pub mod codec_hw_interface;
-pub mod data_handling_interface;
-pub mod slave;
-mod codec;
-mod types;